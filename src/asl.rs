@@ -0,0 +1,199 @@
+// Model of the Apple System Log configuration files in /etc/asl: each `file` output rule is
+// parsed into its query, log path and options (rotate, compress, file_max, ttl, all_max,
+// size_only...). Lines that are not output rules, and rules that are not modified, are
+// written back exactly as they were read.
+
+pub const INSTALL_CONFIG: &str = "/etc/asl/com.apple.install";
+pub const INSTALL_LOG: &str = "/var/log/install.log";
+
+// Minimum retention of install.log, in days (3.3)
+pub const REQUIRED_TTL: u32 = 365;
+
+enum Line {
+    Rule(OutputRule),
+    Other(String),
+}
+
+// `<query> file <path> [option[=value]]...`, e.g. "* file /var/log/install.log rotate=utc ttl=365"
+pub struct OutputRule {
+    query: String, // "*" or a "? [= Facility install]" style filter
+    pub path: String,
+    options: Vec<(String, Option<String>)>,
+    original: Option<String>, // Text as read, dropped once the rule is modified
+}
+
+impl OutputRule {
+    fn parse(line: &str) -> Option<OutputRule> {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with('>') {
+            return None;
+        }
+
+        // The action follows the query: find the "file" word followed by an absolute path
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let position = words
+            .windows(2)
+            .position(|pair| pair[0] == "file" && pair[1].starts_with('/'))?;
+        let query = words[..position].join(" ");
+        if query.is_empty() {
+            return None;
+        }
+
+        let options = words[position + 2..]
+            .iter()
+            .map(|option| match option.split_once('=') {
+                Some((key, value)) => (key.to_string(), Some(value.to_string())),
+                None => (option.to_string(), None),
+            })
+            .collect();
+
+        Some(OutputRule {
+            query,
+            path: words[position + 1].to_string(),
+            options,
+            original: Some(line.to_string()),
+        })
+    }
+
+    // Value of an option: None when absent, Some(None) for a flag such as `compress`
+    pub fn option(&self, key: &str) -> Option<Option<&str>> {
+        self.options
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_deref())
+    }
+
+    pub fn ttl(&self) -> Option<u32> {
+        self.option("ttl")?.and_then(|days| days.parse().ok())
+    }
+
+    // Sets an option in place (dropping repeated ones), or appends it
+    pub fn set(&mut self, key: &str, value: Option<&str>) {
+        let before = self.options.clone();
+        let value = value.map(|value| value.to_string());
+        let mut found = false;
+        self.options.retain_mut(|(name, current)| {
+            if name != key {
+                return true;
+            }
+            if found {
+                return false;
+            }
+            found = true;
+            *current = value.clone();
+            true
+        });
+        if !found {
+            self.options.push((key.to_string(), value));
+        }
+        if self.options != before {
+            self.original = None;
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        let before = self.options.len();
+        self.options.retain(|(name, _)| name != key);
+        if self.options.len() != before {
+            self.original = None;
+        }
+    }
+
+    // Options that contradict each other or the "retained for 365 days, no maximum size" rule
+    pub fn conflicts(&self) -> Vec<String> {
+        let mut conflicts = Vec::new();
+        if let Some(Some(limit)) = self.option("all_max") {
+            conflicts.push(format!("all_max={} caps the total size of the logs (no maximum size allowed)", limit));
+        }
+        for (index, (key, value)) in self.options.iter().enumerate() {
+            let repeated = self.options[..index].iter().find(|(name, _)| name == key);
+            if let Some((_, first)) = repeated.filter(|(_, first)| first != value) {
+                conflicts.push(format!(
+                    "{} is set twice ({} and {})",
+                    key,
+                    first.as_deref().unwrap_or("flag"),
+                    value.as_deref().unwrap_or("flag")
+                ));
+            }
+        }
+        if self.option("ttl").is_some() && self.ttl().is_none() {
+            conflicts.push("ttl is not a number of days".to_string());
+        }
+        conflicts
+    }
+
+    // How long and how much of the log is kept, e.g. "365 days, no maximum size"
+    pub fn retention(&self) -> String {
+        let days = match self.ttl() {
+            Some(days) => format!("{} days", days),
+            None => "no ttl (system default)".to_string(),
+        };
+        let size = match self.option("all_max") {
+            Some(Some(limit)) => format!("at most {} in total", limit),
+            _ => "no maximum size".to_string(),
+        };
+        format!("{}, {}", days, size)
+    }
+
+    fn render(&self) -> String {
+        if let Some(original) = &self.original {
+            return original.clone();
+        }
+        let mut words = vec![self.query.clone(), "file".to_string(), self.path.clone()];
+        words.extend(self.options.iter().map(|(key, value)| match value {
+            Some(value) => format!("{}={}", key, value),
+            None => key.clone(),
+        }));
+        words.join(" ")
+    }
+}
+
+pub struct AslConfig {
+    lines: Vec<Line>,
+}
+
+impl AslConfig {
+    pub fn load(path: &str) -> Result<AslConfig, String> {
+        std::fs::read_to_string(path)
+            .map(|content| AslConfig::parse(&content))
+            .map_err(|error| format!("Failed to read {}: {}", path, error))
+    }
+
+    pub fn parse(content: &str) -> AslConfig {
+        let lines = content
+            .lines()
+            .map(|line| match OutputRule::parse(line) {
+                Some(rule) => Line::Rule(rule),
+                None => Line::Other(line.to_string()),
+            })
+            .collect();
+        AslConfig { lines }
+    }
+
+    // Output rule writing to a log file
+    pub fn rule(&self, path: &str) -> Option<&OutputRule> {
+        self.lines.iter().find_map(|line| match line {
+            Line::Rule(rule) if rule.path == path => Some(rule),
+            _ => None,
+        })
+    }
+
+    pub fn rule_mut(&mut self, path: &str) -> Option<&mut OutputRule> {
+        self.lines.iter_mut().find_map(|line| match line {
+            Line::Rule(rule) if rule.path == path => Some(rule),
+            _ => None,
+        })
+    }
+
+    pub fn render(&self) -> String {
+        let mut content = String::new();
+        for line in &self.lines {
+            match line {
+                Line::Rule(rule) => content.push_str(&rule.render()),
+                Line::Other(text) => content.push_str(text),
+            }
+            content.push('\n');
+        }
+        content
+    }
+}
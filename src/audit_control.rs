@@ -0,0 +1,389 @@
+// Model of /etc/security/audit_control: `name:value` directives (dir, flags, minfree, naflags,
+// policy, filesz, expire-after), with comments, blank lines and ordering kept on rewrite.
+
+pub const PATH: &str = "/etc/security/audit_control";
+
+// Event classes defined in /etc/security/audit_class
+const AUDIT_CLASSES: [&str; 20] = [
+    "no", "fr", "fw", "fa", "fm", "fc", "fd", "cl", "pc", "nt", "ip", "ad", "lo", "ap", "io", "ex", "ot", "aa", "res", "all",
+];
+
+// Audit flags the benchmark requires (3.2)
+pub const REQUIRED_FLAGS: [&str; 7] = ["-fm", "ad", "-ex", "aa", "-fr", "lo", "-fw"];
+
+enum Line {
+    Directive { name: String, value: String },
+    Other(String), // Comment, blank line or text that is not a directive, written back unchanged
+}
+
+pub struct AuditControl {
+    lines: Vec<Line>,
+}
+
+impl AuditControl {
+    pub fn load(path: &str) -> Result<AuditControl, String> {
+        std::fs::read_to_string(path)
+            .map(|content| AuditControl::parse(&content))
+            .map_err(|error| format!("Failed to read {}: {}", path, error))
+    }
+
+    pub fn parse(content: &str) -> AuditControl {
+        let lines = content
+            .lines()
+            .map(|line| match line.split_once(':') {
+                Some((name, value)) if !line.starts_with('#') && is_name(name) => Line::Directive {
+                    name: name.to_string(),
+                    value: value.trim().to_string(),
+                },
+                _ => Line::Other(line.to_string()),
+            })
+            .collect();
+        AuditControl { lines }
+    }
+
+    // Value of the first directive with this name
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.lines.iter().find_map(|line| match line {
+            Line::Directive { name: found, value } if found == name => Some(value.as_str()),
+            _ => None,
+        })
+    }
+
+    // Audit trail directories, in order (the only directive that may be repeated)
+    pub fn dirs(&self) -> Vec<&str> {
+        self.lines
+            .iter()
+            .filter_map(|line| match line {
+                Line::Directive { name, value } if name == "dir" => Some(value.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn flags(&self) -> Vec<&str> {
+        list(self.get("flags"))
+    }
+
+    pub fn naflags(&self) -> Vec<&str> {
+        list(self.get("naflags"))
+    }
+
+    pub fn policy(&self) -> Vec<&str> {
+        list(self.get("policy"))
+    }
+
+    // Minimum free space on the audit volume, in percent
+    pub fn minfree(&self) -> Option<u32> {
+        self.get("minfree")?.parse().ok()
+    }
+
+    // Maximum size of a trail file, in bytes
+    pub fn filesz(&self) -> Option<u64> {
+        let value = self.get("filesz")?;
+        let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+        let (amount, unit) = value.split_at(split);
+        Some(amount.parse::<u64>().ok()? * unit_bytes(unit)?)
+    }
+
+    pub fn expire_after(&self) -> Option<Result<ExpireAfter, String>> {
+        self.get("expire-after").map(ExpireAfter::parse)
+    }
+
+    // Required flags the `flags:` directive does not cover
+    pub fn missing_flags(&self) -> Vec<&'static str> {
+        missing_flags(&self.flags())
+    }
+
+    // Values auditd would reject or misread
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.dirs().is_empty() {
+            problems.push("no 'dir:' directive".to_string());
+        }
+        for flag in self.flags().into_iter().chain(self.naflags()) {
+            if !AUDIT_CLASSES.contains(&flag.trim_start_matches(['+', '-', '^'])) {
+                problems.push(format!("unknown audit class '{}'", flag));
+            }
+        }
+        for policy in self.policy() {
+            if !["cnt", "ahlt", "argv", "arge"].contains(&policy) {
+                problems.push(format!("unknown policy '{}'", policy));
+            }
+        }
+        if self.get("minfree").is_some() && self.minfree().is_none_or(|percent| percent > 100) {
+            problems.push(format!("minfree '{}' is not a percentage", self.get("minfree").unwrap_or_default()));
+        }
+        match (self.get("filesz"), self.filesz()) {
+            (Some(value), None) => problems.push(format!("filesz '{}' is not a size", value)),
+            (_, Some(bytes)) if bytes != 0 && bytes < 512 * 1024 => {
+                problems.push("filesz is below the 512K auditd accepts".to_string())
+            }
+            _ => {}
+        }
+        if let Some(Err(error)) = self.expire_after() {
+            problems.push(format!("expire-after: {}", error));
+        }
+        problems
+    }
+
+    // Replaces the value of a directive where it stands, dropping duplicates, or appends it
+    pub fn set(&mut self, name: &str, value: &str) {
+        let mut found = false;
+        self.lines.retain_mut(|line| match line {
+            Line::Directive { name: current, value: current_value } if current == name => {
+                if found {
+                    return false;
+                }
+                found = true;
+                *current_value = value.to_string();
+                true
+            }
+            _ => true,
+        });
+        if !found {
+            self.lines.push(Line::Directive {
+                name: name.to_string(),
+                value: value.to_string(),
+            });
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut content = String::new();
+        for line in &self.lines {
+            match line {
+                Line::Directive { name, value } => content.push_str(&format!("{}:{}\n", name, value)),
+                Line::Other(text) => content.push_str(&format!("{}\n", text)),
+            }
+        }
+        content
+    }
+}
+
+fn is_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn list(value: Option<&str>) -> Vec<&str> {
+    value
+        .map(|value| value.split(',').map(|item| item.trim()).filter(|item| !item.is_empty()).collect())
+        .unwrap_or_default()
+}
+
+fn unit_bytes(unit: &str) -> Option<u64> {
+    match unit {
+        "" | "B" => Some(1),
+        "K" => Some(1024),
+        "M" => Some(1024 * 1024),
+        "G" => Some(1024 * 1024 * 1024),
+        _ => None,
+    }
+}
+
+// Required flags not covered by the given ones ("all" covers every flag)
+pub fn missing_flags(configured: &[&str]) -> Vec<&'static str> {
+    if configured.contains(&"all") {
+        return Vec::new();
+    }
+    REQUIRED_FLAGS
+        .iter()
+        .filter(|flag| !configured.contains(flag))
+        .copied()
+        .collect()
+}
+
+// One `expire-after` condition
+pub enum Retention {
+    Days(u64),      // Time condition, converted to days
+    Megabytes(u64), // Size condition, converted to megabytes
+}
+
+// `expire-after` value: conditions joined by OR/AND, e.g. "60d", "5G" or "60d OR 5G"
+pub struct ExpireAfter {
+    pub conditions: Vec<(String, Retention)>,
+}
+
+impl ExpireAfter {
+    pub fn parse(value: &str) -> Result<ExpireAfter, String> {
+        let mut conditions = Vec::new();
+        for word in value.split_whitespace() {
+            if word.eq_ignore_ascii_case("or") || word.eq_ignore_ascii_case("and") {
+                continue;
+            }
+            let split = word.find(|c: char| !c.is_ascii_digit()).unwrap_or(word.len());
+            let (amount, unit) = word.split_at(split);
+            let amount: u64 = amount
+                .parse()
+                .map_err(|_| format!("'{}' is not a number followed by a unit", word))?;
+            let retention = match unit {
+                "s" => Retention::Days(amount / 86400),
+                "h" => Retention::Days(amount / 24),
+                "d" => Retention::Days(amount),
+                "y" => Retention::Days(amount * 365),
+                _ => match unit_bytes(unit) {
+                    Some(bytes) if !unit.is_empty() => Retention::Megabytes(amount * bytes / (1024 * 1024)),
+                    _ => return Err(format!("'{}' has an unknown unit", word)),
+                },
+            };
+            conditions.push((word.to_string(), retention));
+        }
+        if conditions.is_empty() {
+            return Err("no retention condition".to_string());
+        }
+        Ok(ExpireAfter { conditions })
+    }
+
+    // First condition keeping less than the benchmark asks for (60 days, 5G), if any
+    pub fn shortfall(&self) -> Option<String> {
+        self.conditions.iter().find_map(|(text, retention)| match retention {
+            Retention::Days(days) if *days < 60 => Some(format!("'{}' keeps less than 60 days", text)),
+            Retention::Megabytes(megabytes) if *megabytes < 5 * 1024 => Some(format!("'{}' keeps less than 5G", text)),
+            _ => None,
+        })
+    }
+}
+
+// Why an `expire-after` value does not meet the benchmark, or None when it does (3.4)
+pub fn expire_after_error(value: &str) -> Option<String> {
+    match ExpireAfter::parse(value) {
+        Ok(expire_after) => expire_after.shortfall(),
+        Err(error) => Some(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Default file of macOS 12, with a site comment
+    const SAMPLE: &str = "\
+#
+# $P4: //depot/projects/trustedbsd/openbsm/etc/audit_control#8 $
+#
+dir:/var/audit
+flags:lo,aa
+minfree:5
+naflags:lo,aa
+policy:cnt,argv
+filesz:2M
+expire-after:10M
+superuser-set-sflags-mask:has_authenticated,has_console_access
+superuser-clear-sflags-mask:has_authenticated,has_console_access
+member-set-sflags-mask:
+member-clear-sflags-mask:has_authenticated
+";
+
+    #[test]
+    fn render_returns_the_file_unchanged() {
+        assert_eq!(AuditControl::parse(SAMPLE).render(), SAMPLE);
+    }
+
+    #[test]
+    fn reads_directives() {
+        let audit_control = AuditControl::parse(SAMPLE);
+        assert_eq!(audit_control.get("dir"), Some("/var/audit"));
+        assert_eq!(audit_control.get("member-set-sflags-mask"), Some(""));
+        assert_eq!(audit_control.get("missing"), None);
+        assert_eq!(audit_control.flags(), vec!["lo", "aa"]);
+        assert_eq!(audit_control.policy(), vec!["cnt", "argv"]);
+        assert_eq!(audit_control.minfree(), Some(5));
+        assert_eq!(audit_control.filesz(), Some(2 * 1024 * 1024));
+        assert!(audit_control.problems().is_empty());
+    }
+
+    #[test]
+    fn comments_are_not_directives() {
+        let audit_control = AuditControl::parse("#flags:all\nflags:lo\n");
+        assert_eq!(audit_control.get("flags"), Some("lo"));
+    }
+
+    #[test]
+    fn set_replaces_a_value_where_it_stands() {
+        let mut audit_control = AuditControl::parse(SAMPLE);
+        audit_control.set("flags", "-fm,ad,-ex,aa,-fr,lo,-fw");
+        let expected = SAMPLE.replace("flags:lo,aa\nminfree", "flags:-fm,ad,-ex,aa,-fr,lo,-fw\nminfree");
+        assert_eq!(audit_control.render(), expected);
+    }
+
+    #[test]
+    fn set_appends_a_missing_directive() {
+        let mut audit_control = AuditControl::parse("# comment\ndir:/var/audit\n");
+        audit_control.set("expire-after", "60d");
+        assert_eq!(audit_control.render(), "# comment\ndir:/var/audit\nexpire-after:60d\n");
+    }
+
+    #[test]
+    fn set_drops_duplicates() {
+        let mut audit_control = AuditControl::parse("flags:lo\n# second one\nflags:aa\nminfree:5\n");
+        assert_eq!(audit_control.get("flags"), Some("lo"));
+        audit_control.set("flags", "all");
+        assert_eq!(audit_control.render(), "flags:all\n# second one\nminfree:5\n");
+    }
+
+    #[test]
+    fn every_dir_is_kept() {
+        let audit_control = AuditControl::parse("dir:/var/audit\ndir:/Volumes/Audit\n");
+        assert_eq!(audit_control.dirs(), vec!["/var/audit", "/Volumes/Audit"]);
+    }
+
+    #[test]
+    fn missing_flags_of_the_benchmark() {
+        assert_eq!(AuditControl::parse(SAMPLE).missing_flags(), vec!["-fm", "ad", "-ex", "-fr", "-fw"]);
+        assert!(missing_flags(&REQUIRED_FLAGS).is_empty());
+        assert!(missing_flags(&["all"]).is_empty());
+        assert_eq!(AuditControl::parse("dir:/var/audit\n").missing_flags().len(), REQUIRED_FLAGS.len());
+    }
+
+    #[test]
+    fn problems_of_invalid_values() {
+        let audit_control = AuditControl::parse("flags:lo,zz\npolicy:cnt,none\nminfree:150\nfilesz:1K\nexpire-after:soon\n");
+        let problems = audit_control.problems();
+        assert_eq!(problems.len(), 6, "{:?}", problems);
+        assert!(problems.contains(&"no 'dir:' directive".to_string()));
+        assert!(problems.contains(&"unknown audit class 'zz'".to_string()));
+    }
+
+    fn conditions(value: &str) -> Vec<String> {
+        let expire_after = ExpireAfter::parse(value).expect("valid expire-after");
+        expire_after
+            .conditions
+            .iter()
+            .map(|(text, retention)| match retention {
+                Retention::Days(days) => format!("{}={}d", text, days),
+                Retention::Megabytes(megabytes) => format!("{}={}M", text, megabytes),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn expire_after_forms() {
+        assert_eq!(conditions("60d"), vec!["60d=60d"]);
+        assert_eq!(conditions("5G"), vec!["5G=5120M"]);
+        assert_eq!(conditions("60d OR 5G"), vec!["60d=60d", "5G=5120M"]);
+        assert_eq!(conditions("60d AND 5G"), vec!["60d=60d", "5G=5120M"]);
+        assert_eq!(conditions("1y or 1440h"), vec!["1y=365d", "1440h=60d"]);
+    }
+
+    #[test]
+    fn invalid_expire_after() {
+        assert!(ExpireAfter::parse("").is_err());
+        assert!(ExpireAfter::parse("OR").is_err());
+        assert!(ExpireAfter::parse("60").is_err());
+        assert!(ExpireAfter::parse("60w").is_err());
+        assert!(ExpireAfter::parse("d60").is_err());
+        assert!(expire_after_error("sixty days").is_some());
+    }
+
+    #[test]
+    fn expire_after_shortfall() {
+        assert_eq!(expire_after_error("60d"), None);
+        assert_eq!(expire_after_error("5G"), None);
+        assert_eq!(expire_after_error("60d OR 5G"), None);
+        assert_eq!(expire_after_error("60d AND 5G"), None);
+        assert_eq!(expire_after_error("1y"), None);
+        assert_eq!(expire_after_error("90d OR 10G"), None);
+        assert_eq!(expire_after_error("30d"), Some("'30d' keeps less than 60 days".to_string()));
+        assert_eq!(expire_after_error("10M"), Some("'10M' keeps less than 5G".to_string()));
+        assert_eq!(expire_after_error("60d OR 1G"), Some("'1G' keeps less than 5G".to_string()));
+    }
+}
@@ -0,0 +1,423 @@
+// Browser policies: Google Chrome and Microsoft Edge read them from their managed preferences
+// domain (the plist an MDM profile installs in /Library/Managed Preferences), Firefox from
+// distribution/policies.json inside its app bundle. Policies already set by the site are kept.
+
+use crate::plist::{Format, Value};
+use crate::runner::FileWrite;
+
+pub struct Browser {
+    pub name: &'static str,
+    app: &'static str,        // App bundle
+    pub domain: &'static str, // Preference domain, also the payload type of a profile
+}
+
+pub const CHROME: Browser = Browser {
+    name: "Google Chrome",
+    app: "/Applications/Google Chrome.app",
+    domain: "com.google.Chrome",
+};
+
+pub const EDGE: Browser = Browser {
+    name: "Microsoft Edge",
+    app: "/Applications/Microsoft Edge.app",
+    domain: "com.microsoft.Edge",
+};
+
+pub const FIREFOX: Browser = Browser {
+    name: "Firefox",
+    app: "/Applications/Firefox.app",
+    domain: "org.mozilla.firefox",
+};
+
+const MANAGED_PREFERENCES: &str = "/Library/Managed Preferences";
+
+fn flag(value: bool) -> Value {
+    Value::Bool(value)
+}
+
+fn dict(entries: &[(&str, Value)]) -> Value {
+    Value::Dict(entries.iter().map(|(key, value)| (key.to_string(), value.clone())).collect())
+}
+
+impl Browser {
+    fn is_firefox(&self) -> bool {
+        self.domain == FIREFOX.domain
+    }
+
+    // Policies applied, aligned with the CIS Google Chrome, Microsoft Edge and Mozilla Firefox benchmarks
+    pub fn policies(&self) -> Vec<(&'static str, Value)> {
+        if self.is_firefox() {
+            return vec![
+                ("DisableTelemetry", flag(true)),
+                ("DisableFirefoxStudies", flag(true)),
+                ("DisablePocket", flag(true)),
+                ("PasswordManagerEnabled", flag(false)),
+                ("OfferToSaveLogins", flag(false)),
+                ("AppAutoUpdate", flag(true)),
+                ("ExtensionUpdate", flag(true)),
+                ("SSLVersionMin", Value::String("tls1.2".to_string())),
+                (
+                    "EnableTrackingProtection",
+                    dict(&[("Value", flag(true)), ("Locked", flag(true)), ("Cryptomining", flag(true)), ("Fingerprinting", flag(true))]),
+                ),
+                (
+                    "Cookies",
+                    dict(&[("Behavior", Value::String("reject-tracker-and-partition-foreign".to_string())), ("Locked", flag(true))]),
+                ),
+                ("PopupBlocking", dict(&[("Default", flag(true)), ("Locked", flag(true))])),
+            ];
+        }
+
+        // Chromium policies shared by both browsers
+        let mut policies = vec![
+            ("BlockThirdPartyCookies", flag(true)),
+            ("PasswordManagerEnabled", flag(false)),
+            ("ImportSavedPasswords", flag(false)),
+            ("AutofillCreditCardEnabled", flag(false)),
+            ("AutofillAddressEnabled", flag(false)),
+            ("BackgroundModeEnabled", flag(false)),
+            ("SSLErrorOverrideAllowed", flag(false)),
+            ("DownloadRestrictions", Value::Integer(1)), // Block dangerous downloads
+            ("MetricsReportingEnabled", flag(false)),
+            ("SitePerProcess", flag(true)),
+            ("ComponentUpdatesEnabled", flag(true)),
+        ];
+        if self.domain == CHROME.domain {
+            policies.extend([
+                ("SafeBrowsingProtectionLevel", Value::Integer(1)), // Standard protection, cannot be turned off
+                ("URLKeyedAnonymizedDataCollectionEnabled", flag(false)),
+                ("RemoteAccessHostFirewallTraversal", flag(false)),
+            ]);
+        } else {
+            policies.extend([
+                ("SmartScreenEnabled", flag(true)),
+                ("SmartScreenPuaEnabled", flag(true)),
+                ("PreventSmartScreenPromptOverride", flag(true)),
+                ("PersonalizationReportingEnabled", flag(false)),
+            ]);
+        }
+        policies
+    }
+
+    pub fn is_installed(&self) -> bool {
+        std::path::Path::new(&crate::root::path(self.app)).is_dir()
+    }
+
+    // File holding the policies, on the target system
+    pub fn policy_file(&self) -> String {
+        if self.is_firefox() {
+            format!("{}/Contents/Resources/distribution/policies.json", self.app)
+        } else {
+            format!("{}/{}.plist", MANAGED_PREFERENCES, self.domain)
+        }
+    }
+
+    // Installed policies (Firefox: the "policies" object), None when there is no policy file
+    fn installed(&self) -> Result<Option<Value>, String> {
+        let path = crate::root::path(&self.policy_file());
+        if !std::path::Path::new(&path).exists() {
+            return Ok(None);
+        }
+        if !self.is_firefox() {
+            return crate::plist::load(&path).map(|(policies, _)| Some(policies));
+        }
+        let content = std::fs::read_to_string(&path).map_err(|error| format!("Failed to read {}: {}", path, error))?;
+        let document = parse_json(&content).map_err(|error| format!("Invalid JSON in {}: {}", path, error))?;
+        Ok(Some(document.get("policies").cloned().unwrap_or(Value::Dict(Vec::new()))))
+    }
+
+    // Policies that are missing or set to another value, e.g. "PasswordManagerEnabled = 1 (expected 0)"
+    pub fn differences(&self) -> Result<Vec<String>, String> {
+        let installed = self.installed()?.unwrap_or(Value::Dict(Vec::new()));
+        Ok(self
+            .policies()
+            .into_iter()
+            .filter_map(|(key, expected)| match installed.get(key) {
+                Some(value) if satisfies(value, &expected) => None,
+                Some(value) => Some(format!("{} = {} (expected {})", key, compact(value), compact(&expected))),
+                None => Some(format!("{} is not set", key)),
+            })
+            .collect())
+    }
+
+    // New content of the policy file, or None when every policy is already in place
+    pub fn policy_write(&self) -> Result<Option<FileWrite>, String> {
+        if self.differences()?.is_empty() {
+            return Ok(None);
+        }
+        let path = crate::root::path(&self.policy_file());
+        let existing = std::path::Path::new(&path).exists();
+        let mut policies = self.installed()?.unwrap_or(Value::Dict(Vec::new()));
+        for (key, value) in self.policies() {
+            // Keys the site added to a policy dictionary (e.g. cookie exceptions) are kept
+            let merged = match (policies.get(key), &value) {
+                (Some(current @ Value::Dict(_)), Value::Dict(entries)) => {
+                    let mut current = current.clone();
+                    for (name, setting) in entries {
+                        current.insert(name, setting.clone());
+                    }
+                    current
+                }
+                _ => value,
+            };
+            policies.insert(key, merged);
+        }
+
+        let content = if self.is_firefox() {
+            // Other top-level keys of policies.json are kept as they are
+            let mut document = if existing {
+                parse_json(&std::fs::read_to_string(&path).map_err(|error| error.to_string())?)?
+            } else {
+                Value::Dict(Vec::new())
+            };
+            document.insert("policies", policies);
+            to_json(&document).into_bytes()
+        } else {
+            let format = if existing { crate::plist::load(&path)?.1 } else { Format::Xml };
+            crate::plist::serialize(&policies, format)
+        };
+
+        Ok(Some(FileWrite {
+            path,
+            content,
+            ownership: if existing { None } else { Some((0o644, 0, 0)) }, // root:wheel, readable by the browser
+            validate: None,
+        }))
+    }
+}
+
+// Expected dictionaries only need their own keys (a site may add others, e.g. "Exceptions")
+fn satisfies(actual: &Value, expected: &Value) -> bool {
+    match (actual, expected) {
+        (Value::Dict(_), Value::Dict(entries)) => entries
+            .iter()
+            .all(|(key, value)| actual.get(key).is_some_and(|found| satisfies(found, value))),
+        _ => actual == expected,
+    }
+}
+
+fn compact(value: &Value) -> String {
+    value.defaults_text().split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+// policies.json, read into and written from the same value tree as property lists
+
+struct JsonReader<'a> {
+    text: &'a [u8],
+    position: usize,
+}
+
+impl JsonReader<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.text.get(self.position).is_some_and(|byte| byte.is_ascii_whitespace()) {
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.text.get(self.position) == Some(&byte) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err(format!("expected '{}' at byte {}", byte as char, self.position))
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value, String> {
+        if depth > 512 {
+            return Err("nesting is too deep".to_string());
+        }
+        self.skip_whitespace();
+        let rest = &self.text[self.position..];
+        match rest.first() {
+            Some(b'{') => {
+                self.position += 1;
+                let mut entries = Vec::new();
+                self.skip_whitespace();
+                if self.text.get(self.position) == Some(&b'}') {
+                    self.position += 1;
+                    return Ok(Value::Dict(entries));
+                }
+                loop {
+                    self.skip_whitespace();
+                    let key = self.string()?;
+                    self.expect(b':')?;
+                    entries.push((key, self.value(depth + 1)?));
+                    self.skip_whitespace();
+                    match self.text.get(self.position) {
+                        Some(b',') => self.position += 1,
+                        Some(b'}') => {
+                            self.position += 1;
+                            return Ok(Value::Dict(entries));
+                        }
+                        _ => return Err(format!("expected ',' or '}}' at byte {}", self.position)),
+                    }
+                }
+            }
+            Some(b'[') => {
+                self.position += 1;
+                let mut items = Vec::new();
+                self.skip_whitespace();
+                if self.text.get(self.position) == Some(&b']') {
+                    self.position += 1;
+                    return Ok(Value::Array(items));
+                }
+                loop {
+                    items.push(self.value(depth + 1)?);
+                    self.skip_whitespace();
+                    match self.text.get(self.position) {
+                        Some(b',') => self.position += 1,
+                        Some(b']') => {
+                            self.position += 1;
+                            return Ok(Value::Array(items));
+                        }
+                        _ => return Err(format!("expected ',' or ']' at byte {}", self.position)),
+                    }
+                }
+            }
+            Some(b'"') => self.string().map(Value::String),
+            _ if rest.starts_with(b"true") => {
+                self.position += 4;
+                Ok(Value::Bool(true))
+            }
+            _ if rest.starts_with(b"false") => {
+                self.position += 5;
+                Ok(Value::Bool(false))
+            }
+            _ if rest.starts_with(b"null") => Err("null values are not supported".to_string()),
+            _ => {
+                let length = rest
+                    .iter()
+                    .position(|byte| !(byte.is_ascii_digit() || b"+-.eE".contains(byte)))
+                    .unwrap_or(rest.len());
+                let number = std::str::from_utf8(&rest[..length]).unwrap_or("");
+                self.position += length;
+                if let Ok(integer) = number.parse() {
+                    return Ok(Value::Integer(integer));
+                }
+                number
+                    .parse()
+                    .map(Value::Real)
+                    .map_err(|_| format!("unexpected character at byte {}", self.position - length))
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        if self.text.get(self.position) != Some(&b'"') {
+            return Err(format!("expected a string at byte {}", self.position));
+        }
+        self.position += 1;
+        let mut bytes = Vec::new();
+        loop {
+            let byte = *self.text.get(self.position).ok_or("unterminated string")?;
+            self.position += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let escape = *self.text.get(self.position).ok_or("unterminated string")?;
+                    self.position += 1;
+                    let decoded = match escape {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => {
+                            let mut unit = self.hex4()?;
+                            // Surrogate pair
+                            if (0xd800..0xdc00).contains(&unit) && self.text[self.position..].starts_with(b"\\u") {
+                                self.position += 2;
+                                let low = self.hex4()?;
+                                unit = 0x10000 + ((unit - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff);
+                            }
+                            char::from_u32(unit).unwrap_or('\u{fffd}')
+                        }
+                        _ => return Err(format!("invalid escape at byte {}", self.position - 1)),
+                    };
+                    bytes.extend_from_slice(decoded.encode_utf8(&mut [0; 4]).as_bytes());
+                }
+                _ => bytes.push(byte),
+            }
+        }
+        String::from_utf8(bytes).map_err(|_| "string is not UTF-8".to_string())
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let digits = self.text.get(self.position..self.position + 4).ok_or("truncated \\u escape")?;
+        self.position += 4;
+        u32::from_str_radix(std::str::from_utf8(digits).unwrap_or(""), 16).map_err(|_| "invalid \\u escape".to_string())
+    }
+}
+
+fn parse_json(text: &str) -> Result<Value, String> {
+    let mut reader = JsonReader { text: text.as_bytes(), position: 0 };
+    let value = reader.value(0)?;
+    reader.skip_whitespace();
+    if reader.position != reader.text.len() {
+        return Err(format!("unexpected content at byte {}", reader.position));
+    }
+    Ok(value)
+}
+
+// Two-space indentation, like the examples in the Firefox policy documentation
+fn to_json(value: &Value) -> String {
+    let mut out = String::new();
+    write_json(value, 0, &mut out);
+    out.push('\n');
+    out
+}
+
+fn write_json(value: &Value, depth: usize, out: &mut String) {
+    let indent = "  ".repeat(depth + 1);
+    let closing = "  ".repeat(depth);
+    match value {
+        Value::Dict(entries) if entries.is_empty() => out.push_str("{}"),
+        Value::Dict(entries) => {
+            out.push_str("{\n");
+            for (index, (key, value)) in entries.iter().enumerate() {
+                out.push_str(&format!("{}{}: ", indent, json_string(key)));
+                write_json(value, depth + 1, out);
+                out.push_str(if index + 1 < entries.len() { ",\n" } else { "\n" });
+            }
+            out.push_str(&format!("{}}}", closing));
+        }
+        Value::Array(items) if items.is_empty() => out.push_str("[]"),
+        Value::Array(items) => {
+            out.push_str("[\n");
+            for (index, item) in items.iter().enumerate() {
+                out.push_str(&indent);
+                write_json(item, depth + 1, out);
+                out.push_str(if index + 1 < items.len() { ",\n" } else { "\n" });
+            }
+            out.push_str(&format!("{}]", closing));
+        }
+        Value::Bool(value) => out.push_str(if *value { "true" } else { "false" }),
+        Value::Integer(number) => out.push_str(&number.to_string()),
+        Value::Real(number) => out.push_str(&number.to_string()),
+        Value::String(text) => out.push_str(&json_string(text)),
+        // Not produced by policies.json, written in a readable form
+        Value::Data(_) | Value::Date(_) => out.push_str(&json_string(&value.defaults_text())),
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,1623 @@
+use crate::checks::{self, Check};
+use crate::commands::{self, BashCommand};
+use crate::os::{Release, EVERY_RELEASE};
+use crate::post_action::PostAction;
+
+// Structure to store a control of the benchmark: its remediation commands and read-only checks
+#[derive(Clone, Copy)]
+pub struct Control {
+    pub name: &'static str,                 // Control name (also the name of its command/check functions)
+    pub cis_id: &'static str,               // CIS recommendation number ("" when not numbered)
+    pub title: &'static str,                // Recommendation title
+    pub level: u8,                          // CIS profile level (1 or 2), 0 for controls outside the benchmark
+    pub assessment: Assessment,             // Whether the benchmark can assess the recommendation automatically
+    pub rationale: &'static str,            // Why the recommendation matters
+    pub tags: &'static [&'static str],      // Free-form tags used to select groups of controls
+    pub default_enabled: bool,              // Whether the control runs when it is not explicitly selected
+    pub requires: &'static [&'static str],  // Controls (names or globs) that must finish first when selected too
+    pub releases: &'static [Release],       // macOS releases (and their benchmark editions) the control applies to
+    pub commands: fn() -> Vec<BashCommand>, // Remediation commands
+    pub post_actions: fn() -> Vec<PostAction>, // Run after the selected controls when changes call for them
+    pub checks: fn() -> Vec<Check>,         // Read-only checks
+}
+
+// Assessment status of a recommendation in the benchmark
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Assessment {
+    Automated, // The state can be verified from the system
+    Manual,    // The benchmark expects a manual review
+}
+
+impl Assessment {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Assessment::Automated => "automated",
+            Assessment::Manual => "manual",
+        }
+    }
+}
+
+// Criteria used to pick controls from the catalog
+pub struct Selection {
+    pub controls: Vec<String>, // Control names, in the order they should run
+    pub sections: Vec<String>, // CIS sections, e.g. "2.4" or "2.4.*"
+    pub tags: Vec<String>,     // Tags, e.g. "per-user"
+    pub excludes: Vec<String>, // Control names, sections or tags to leave out
+    pub level: Option<u8>,     // Profile level: Level 1 keeps level 1 controls, Level 2 keeps both
+}
+
+impl Control {
+    // Remediation commands as they run against the target (see BashCommand::offline), for the
+    // users in scope when the control is per-user; none when the control does not apply to the
+    // target's release
+    pub fn remediation(&self) -> Vec<BashCommand> {
+        if self.not_applicable().is_some() {
+            return Vec::new();
+        }
+        (self.commands)()
+            .into_iter()
+            .filter(|command| command.target_user().is_none_or(|user| crate::users::in_scope(self.name, &user)))
+            .map(BashCommand::offline)
+            .collect()
+    }
+
+    // Checks of the control, for the users in scope when the control is per-user
+    pub fn audit_checks(&self) -> Vec<Check> {
+        if let Some(reason) = self.not_applicable() {
+            return vec![checks::not_applicable_check(self.name, self.title, reason)];
+        }
+        (self.checks)()
+            .into_iter()
+            .filter(|check| check.user().is_none_or(|user| crate::users::in_scope(self.name, user)))
+            .collect()
+    }
+
+    // Why the control does not apply to the release of the target, None when it does
+    pub fn not_applicable(&self) -> Option<String> {
+        let release = crate::os::release();
+        if self.releases.contains(&release) {
+            return None;
+        }
+        let releases: Vec<String> = self.releases.iter().map(|release| release.name()).collect();
+        Some(format!("Not part of the {} (applies to {})", release.benchmark(), releases.join(", ")))
+    }
+
+    // Top-level benchmark section, e.g. "2 System Preferences"
+    pub fn section(&self) -> &'static str {
+        section_name(self.cis_id)
+    }
+
+    // Matches "c13", "c13_disable_remote_apple_events" or a glob such as "c4*"
+    pub fn matches_name(&self, pattern: &str) -> bool {
+        if pattern.contains('*') {
+            return glob_match(pattern, self.name);
+        }
+        self.name == pattern || self.name.starts_with(&format!("{}_", pattern))
+    }
+
+    // Matches "2.4", "2.4.1" or a glob such as "2.4.*"
+    fn matches_section(&self, pattern: &str) -> bool {
+        if self.cis_id.is_empty() {
+            return false;
+        }
+        if pattern.contains('*') {
+            return glob_match(pattern, self.cis_id);
+        }
+        self.cis_id == pattern || self.cis_id.starts_with(&format!("{}.", pattern))
+    }
+
+    fn has_tag(&self, tag: &str) -> bool {
+        self.tags.contains(&tag)
+    }
+}
+
+// Name of the top-level benchmark section a recommendation belongs to
+pub fn section_name(cis_id: &str) -> &'static str {
+    match cis_id.split('.').next().unwrap_or("") {
+        "1" => "1 Install Updates, Patches and Additional Security Software",
+        "2" => "2 System Preferences",
+        "3" => "3 Logging and Auditing",
+        "4" => "4 Network Configurations",
+        "5" => "5 System Access, Authentication and Authorization",
+        "6" => "6 User Accounts and Environment",
+        "7" => "7 Additional Considerations",
+        _ => "Other",
+    }
+}
+
+// Minimal glob matching where '*' matches any sequence of characters
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == text,
+        Some((prefix, rest)) => {
+            let text = match text.strip_prefix(prefix) {
+                Some(text) => text,
+                None => return false,
+            };
+            if rest.is_empty() {
+                return true;
+            }
+            (0..=text.len())
+                .filter(|index| text.is_char_boundary(*index))
+                .any(|index| glob_match(rest, &text[index..]))
+        }
+    }
+}
+
+// Finds a single control by name (or by its "cN" prefix)
+pub fn find(name: &str) -> Option<Control> {
+    all().into_iter().find(|control| control.matches_name(name))
+}
+
+// Picks the controls to run. Controls named explicitly run in the order given,
+// followed by the ones matching a section or tag in catalog order.
+pub fn select(selection: &Selection) -> Result<Vec<Control>, String> {
+    let mut selected: Vec<Control> = Vec::new();
+    let contains = |selected: &[Control], name: &str| selected.iter().any(|control| control.name == name);
+
+    for pattern in &selection.controls {
+        let matching: Vec<Control> = all().into_iter().filter(|control| control.matches_name(pattern)).collect();
+        if matching.is_empty() {
+            return Err(format!("Unknown control '{}'", pattern));
+        }
+        for control in matching {
+            if !contains(&selected, control.name) {
+                selected.push(control);
+            }
+        }
+    }
+
+    // Without any filter, every control enabled by default is selected
+    let filtered = !selection.sections.is_empty() || !selection.tags.is_empty();
+    if filtered || selection.controls.is_empty() {
+        for control in all() {
+            let matches = !filtered
+                || selection.sections.iter().any(|pattern| control.matches_section(pattern))
+                || selection.tags.iter().any(|tag| control.has_tag(tag));
+            if control.default_enabled && matches && !contains(&selected, control.name) {
+                selected.push(control);
+            }
+        }
+    }
+
+    Ok(selected
+        .into_iter()
+        .filter(|control| {
+            !selection.excludes.iter().any(|pattern| {
+                control.matches_name(pattern) || control.matches_section(pattern) || control.has_tag(pattern)
+            })
+        })
+        .filter(|control| selection.level.is_none_or(|level| control.level <= level))
+        .collect())
+}
+
+// Every control of the benchmark, in execution order
+pub fn all() -> Vec<Control> {
+    vec![
+        Control {
+            name: "c1_enable_os_autoupdate",
+            cis_id: "1.6",
+            title: "Ensure Install of macOS Updates Is Enabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Installing macOS updates promptly closes known vulnerabilities before they can be exploited.",
+            tags: &["updates"],
+            default_enabled: false, // Automatic macOS updates are left for manual configuration
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c1_enable_os_autoupdate()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c1_enable_os_autoupdate()],
+        },
+        Control {
+            name: "c2_ensure_apple_software_is_current",
+            cis_id: "1.1",
+            title: "Ensure All Apple-provided Software Is Current",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Outdated software is the most common way known vulnerabilities are exploited.",
+            tags: &["updates"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c2_ensure_apple_software_is_current()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c2_ensure_apple_software_is_current()],
+        },
+        Control {
+            name: "c3_enable_auto_update",
+            cis_id: "1.2",
+            title: "Ensure Auto Update Is Enabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Checking for updates automatically lets the user know as soon as patches are available.",
+            tags: &["updates"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c3_enable_auto_update()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c3_enable_auto_update()],
+        },
+        Control {
+            name: "c4_enable_download_new_updates",
+            cis_id: "1.3",
+            title: "Ensure Download New Updates When Available Is Enabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Downloading updates in the background shortens the time between a patch release and its installation.",
+            tags: &["updates"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c4_enable_download_new_updates()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c4_enable_download_new_updates()],
+        },
+        Control {
+            name: "c5_enable_app_update_installation",
+            cis_id: "1.4",
+            title: "Ensure Installation of App Update Is Enabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "App Store applications receive security fixes that should be installed without user intervention.",
+            tags: &["updates"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c5_enable_app_update_installation()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c5_enable_app_update_installation()],
+        },
+        Control {
+            name: "c6_1_enable_system_data_files",
+            cis_id: "1.5.1",
+            title: "Ensure System Data Files Are Downloaded Automatically",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "XProtect and Gatekeeper data files keep the built-in malware protection current.",
+            tags: &["updates"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c6_1_enable_system_data_files()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c6_1_enable_system_data_files()],
+        },
+        Control {
+            name: "c6_2_enable_security_updates",
+            cis_id: "1.5.2",
+            title: "Ensure Security Updates Are Downloaded Automatically",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Security responses and system files fix actively exploited issues and must not wait for a manual install.",
+            tags: &["updates"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c6_2_enable_security_updates()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c6_2_enable_security_updates()],
+        },
+        Control {
+            name: "c7_1_disable_bluetooth",
+            cis_id: "2.1.1",
+            title: "Ensure Bluetooth is disabled if no devices are paired",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "An enabled Bluetooth radio without paired devices only widens the wireless attack surface.",
+            tags: &["bluetooth"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c7_1_disable_bluetooth()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c7_1_disable_bluetooth()],
+        },
+        Control {
+            name: "c7_2_restart_bluetooth_daemon",
+            cis_id: "2.1.1",
+            title: "Restart the Bluetooth daemon",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "bluetoothd only reads the controller power state when it starts.",
+            tags: &["bluetooth", "restart"],
+            default_enabled: true,
+            requires: &["c7_1"],
+            releases: EVERY_RELEASE,
+            commands: Vec::new,
+            post_actions: || vec![commands::c7_2_restart_bluetooth_daemon()],
+            checks: || vec![checks::c7_2_restart_bluetooth_daemon()],
+        },
+        Control {
+            name: "c8_show_bluetooth_status",
+            cis_id: "2.1.2",
+            title: "Ensure Show Bluetooth Status in Menu Bar is Enabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Showing the Bluetooth status lets users notice when the radio is on or a device connects.",
+            tags: &["bluetooth", "per-user"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: commands::c8_show_bluetooth_status,
+            post_actions: Vec::new,
+            checks: checks::c8_show_bluetooth_status,
+        },
+        Control {
+            name: "c9_1_set_timezone",
+            cis_id: "2.2.1",
+            title: "Ensure the Timezone is Set to the Organization Timezone",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Correct time is required for log correlation, Kerberos and certificate validation.",
+            tags: &["time"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c9_1_set_timezone()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c9_1_set_timezone()],
+        },
+        Control {
+            name: "c9_2_set_network_time_server",
+            cis_id: "2.2.1",
+            title: "Ensure the Network Time Server is Set to the Organization Time Server",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "A trusted time source keeps the clock synchronised with the rest of the organisation.",
+            tags: &["time"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c9_2_set_network_time_server()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c9_2_set_network_time_server()],
+        },
+        Control {
+            name: "c9_3_enable_network_time",
+            cis_id: "2.2.1",
+            title: "Ensure Using Network Time is Enabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Automatic time keeps audit records consistent across systems.",
+            tags: &["time"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c9_3_enable_network_time()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c9_3_enable_network_time()],
+        },
+        Control {
+            name: "c10_sync_time",
+            cis_id: "2.2.2",
+            title: "Ensure Time Is Set Within Appropriate Limits",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Clock drift breaks authentication protocols and makes audit trails unreliable.",
+            tags: &["time"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c10_sync_time()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c10_sync_time()],
+        },
+        Control {
+            name: "c11_set_screensaver_inactivity_interval",
+            cis_id: "2.3.1",
+            title: "Ensure an Inactivity Interval of 20 Minutes Or Less for the Screen Saver Is Enabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "A short inactivity interval limits the window in which an unattended session can be used.",
+            tags: &["screensaver", "per-user"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: commands::c11_set_screensaver_inactivity_interval,
+            post_actions: Vec::new,
+            checks: checks::c11_set_screensaver_inactivity_interval,
+        },
+        Control {
+            name: "c12_ensure_secure_screensaver_corners",
+            cis_id: "2.3.2",
+            title: "Ensure Screen Saver Corners Are Secure",
+            level: 2,
+            assessment: Assessment::Automated,
+            rationale: "A hot corner set to disable the screen saver would let an unattended session stay unlocked.",
+            tags: &["screensaver", "per-user"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: commands::c12_ensure_secure_screensaver_corners,
+            post_actions: Vec::new,
+            checks: checks::c12_ensure_secure_screensaver_corners,
+        },
+        Control {
+            name: "c13_disable_remote_apple_events",
+            cis_id: "2.4.1",
+            title: "Ensure Remote Apple Events is Disabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Remote Apple Events let other computers send commands to applications on this Mac.",
+            tags: &["sharing"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c13_disable_remote_apple_events()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c13_disable_remote_apple_events()],
+        },
+        Control {
+            name: "c14_disable_internet_sharing",
+            cis_id: "2.4.2",
+            title: "Ensure Internet Sharing is Disabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Internet Sharing turns the Mac into a router and bypasses network access controls.",
+            tags: &["sharing", "network"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c14_disable_internet_sharing()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c14_disable_internet_sharing()],
+        },
+        Control {
+            name: "c15_disable_screen_sharing",
+            cis_id: "2.4.3",
+            title: "Ensure Screen Sharing is Disabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Screen Sharing exposes the desktop over the network and should only be enabled when required.",
+            tags: &["sharing"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c15_disable_screen_sharing()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c15_disable_screen_sharing()],
+        },
+        Control {
+            name: "c16_disable_printer_sharing",
+            cis_id: "2.4.4",
+            title: "Ensure Printer Sharing is Disabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Shared printers advertise a network service that is rarely needed on a workstation.",
+            tags: &["sharing"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c16_disable_printer_sharing()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c16_disable_printer_sharing()],
+        },
+        Control {
+            name: "c17_disable_remote_login",
+            cis_id: "2.4.5",
+            title: "Ensure Remote Login is Disabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "SSH access exposes a remote shell and should only be enabled on systems managed over SSH.",
+            tags: &["sharing", "network"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c17_disable_remote_login()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c17_disable_remote_login()],
+        },
+        Control {
+            name: "c18_disable_dvd_cd_sharing",
+            cis_id: "2.4.6",
+            title: "Ensure DVD or CD Sharing is Disabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Sharing optical media opens a file sharing service to the network.",
+            tags: &["sharing"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c18_disable_dvd_cd_sharing()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c18_disable_dvd_cd_sharing()],
+        },
+        Control {
+            name: "c19_disable_file_sharing",
+            cis_id: "2.4.8",
+            title: "Ensure File Sharing is Disabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "File sharing exposes SMB and AFP services that can leak data or be attacked.",
+            tags: &["sharing"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c19_disable_file_sharing()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c19_disable_file_sharing()],
+        },
+        Control {
+            name: "c20_disable_remote_management",
+            cis_id: "2.4.9",
+            title: "Ensure Remote Management is Disabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Apple Remote Desktop grants full remote control and should only be enabled when managed centrally.",
+            tags: &["sharing"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c20_disable_remote_management()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c20_disable_remote_management()],
+        },
+        Control {
+            name: "c21_disable_content_caching",
+            cis_id: "2.4.10",
+            title: "Ensure Content Caching is Disabled",
+            level: 2,
+            assessment: Assessment::Automated,
+            rationale: "Content caching makes the Mac a network service that stores and serves data for other devices.",
+            tags: &["sharing", "network"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c21_disable_content_caching()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c21_disable_content_caching()],
+        },
+        Control {
+            name: "c22_disable_airdrop",
+            cis_id: "2.4.11",
+            title: "Ensure AirDrop is Disabled",
+            level: 2,
+            assessment: Assessment::Automated,
+            rationale: "AirDrop allows files to be exchanged with nearby devices outside of organisational controls.",
+            tags: &["sharing", "per-user"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: commands::c22_disable_airdrop,
+            post_actions: Vec::new,
+            checks: checks::c22_disable_airdrop,
+        },
+        Control {
+            name: "c23_disable_media_sharing",
+            cis_id: "2.4.12",
+            title: "Ensure Media Sharing is Disabled",
+            level: 2,
+            assessment: Assessment::Automated,
+            rationale: "Media sharing publishes the user's library to the local network.",
+            tags: &["sharing", "per-user"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: commands::c23_disable_media_sharing,
+            post_actions: Vec::new,
+            checks: checks::c23_disable_media_sharing,
+        },
+        Control {
+            name: "c24_disable_airplay_receiver",
+            cis_id: "2.4.13",
+            title: "Ensure AirPlay Receiver is Disabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "An AirPlay receiver listens for connections from other devices on the network.",
+            tags: &["sharing", "per-user"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: commands::c24_disable_airplay_receiver,
+            post_actions: Vec::new,
+            checks: checks::c24_disable_airplay_receiver,
+        },
+        Control {
+            name: "c25_enable_firewall",
+            cis_id: "2.5.2.1",
+            title: "Ensure Firewall is Enabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "The application firewall blocks unwanted incoming connections.",
+            tags: &["firewall", "network"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c25_enable_firewall()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c25_enable_firewall()],
+        },
+        Control {
+            name: "c26_enable_firewall_stealth_mode",
+            cis_id: "2.5.2.2",
+            title: "Ensure Firewall Stealth Mode is Enabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Stealth mode stops the Mac from answering probes such as ICMP ping, making it harder to discover.",
+            tags: &["firewall", "network"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c26_enable_firewall_stealth_mode()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c26_enable_firewall_stealth_mode()],
+        },
+        Control {
+            name: "c27_1_enable_location_services",
+            cis_id: "2.5.3",
+            title: "Ensure Location Services is Enabled",
+            level: 2,
+            assessment: Assessment::Automated,
+            rationale: "Location Services keep the time zone correct and allow a lost device to be located.",
+            tags: &["privacy"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c27_1_enable_location_services()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c27_1_enable_location_services()],
+        },
+        Control {
+            name: "c27_2_restart_location_services",
+            cis_id: "2.5.3",
+            title: "Restart Location Services",
+            level: 2,
+            assessment: Assessment::Automated,
+            rationale: "locationd only applies the new setting after a restart.",
+            tags: &["privacy", "restart"],
+            default_enabled: true,
+            requires: &["c27_1"],
+            releases: EVERY_RELEASE,
+            commands: Vec::new,
+            post_actions: || vec![commands::c27_2_restart_location_services()],
+            checks: || vec![checks::c27_2_restart_location_services()],
+        },
+        Control {
+            name: "c28_1_disable_diagnostic_data",
+            cis_id: "2.5.5",
+            title: "Ensure Sending Diagnostic and Usage Data to Apple is Disabled (Global Settings)",
+            level: 2,
+            assessment: Assessment::Automated,
+            rationale: "Diagnostic reports can contain sensitive information and should not leave the organisation.",
+            tags: &["privacy"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c28_1_disable_diagnostic_data()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c28_1_disable_diagnostic_data()],
+        },
+        Control {
+            name: "c28_2_disable_diagnostic_data_per_user",
+            cis_id: "2.5.5",
+            title: "Ensure Sending Diagnostic and Usage Data to Apple is Disabled (Per User)",
+            level: 2,
+            assessment: Assessment::Automated,
+            rationale: "Diagnostic reports can contain sensitive information and should not leave the organisation.",
+            tags: &["privacy", "per-user"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: commands::c28_2_disable_diagnostic_data_per_user,
+            post_actions: Vec::new,
+            checks: checks::c28_2_disable_diagnostic_data_per_user,
+        },
+        Control {
+            name: "c29_enable_limit_ad_tracking",
+            cis_id: "2.5.6",
+            title: "Ensure Limit Ad Tracking is Enabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Limiting ad tracking reduces the personal information shared with advertisers.",
+            tags: &["privacy", "per-user"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: commands::c29_enable_limit_ad_tracking,
+            post_actions: Vec::new,
+            checks: checks::c29_enable_limit_ad_tracking,
+        },
+        Control {
+            name: "c30_enable_gatekeeper",
+            cis_id: "2.5.7",
+            title: "Ensure Gatekeeper is Enabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Gatekeeper only allows signed and notarized software to run, blocking most malware.",
+            tags: &["gatekeeper"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c30_enable_gatekeeper()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c30_enable_gatekeeper()],
+        },
+        Control {
+            name: "c31_enable_custom_login_message",
+            cis_id: "2.5.8",
+            title: "Ensure a Custom Message for the Login Screen is Enabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "A login message states the acceptable use policy before anyone signs in.",
+            tags: &["login"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c31_enable_custom_login_message()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c31_enable_custom_login_message()],
+        },
+        Control {
+            name: "c32_ensure_admin_password_for_system_preferences",
+            cis_id: "2.5.9",
+            title: "Ensure an Administrator Password is Required to Access System-Wide Preferences",
+            level: 2,
+            assessment: Assessment::Automated,
+            rationale: "System-wide preferences change security settings and should require an administrator.",
+            tags: &["authorization"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c32_ensure_admin_password_for_system_preferences()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c32_ensure_admin_password_for_system_preferences()],
+        },
+        Control {
+            name: "c33_require_password_to_wake",
+            cis_id: "2.5.10",
+            title: "Ensure a Password is Required to Wake the Computer From Sleep or Screen Saver is Enabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Requiring a password on wake keeps an unattended session from being used by someone else.",
+            tags: &["screensaver", "login"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c33_require_password_to_wake()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c33_require_password_to_wake()],
+        },
+        Control {
+            name: "c34_configure_sleep_settings_based_on_cpu",
+            cis_id: "2.8.1",
+            title: "Ensure the OS is Not Active When Resuming from Sleep and Display Sleep, Configured Based on CPU Type",
+            level: 2,
+            assessment: Assessment::Automated,
+            rationale: "Sleep and hibernation settings control how long encryption keys stay in memory while unattended.",
+            tags: &["power"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c34_configure_sleep_settings_based_on_cpu()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c34_configure_sleep_settings_based_on_cpu()],
+        },
+        Control {
+            name: "c35_disable_wake_for_network_access",
+            cis_id: "2.8.2",
+            title: "Ensure Wake for Network Access is Disabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Wake for network access lets the Mac be woken remotely, outside of the user's control.",
+            tags: &["power", "network"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c35_disable_wake_for_network_access()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c35_disable_wake_for_network_access()],
+        },
+        Control {
+            name: "c36_disable_powernap_based_on_cpu",
+            cis_id: "2.8.3",
+            title: "Ensure Power Nap is Disabled for Intel Macs",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Power Nap lets the Mac do network activity while it appears to be asleep.",
+            tags: &["power"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c36_disable_powernap_based_on_cpu()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c36_disable_powernap_based_on_cpu()],
+        },
+        Control {
+            name: "c37_enable_security_auditing",
+            cis_id: "3.1",
+            title: "Ensure Security Auditing is Enabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "The audit daemon records security-relevant events needed to investigate incidents.",
+            tags: &["auditing"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c37_enable_security_auditing()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c37_enable_security_auditing()],
+        },
+        Control {
+            name: "c38_configure_auditing_flags",
+            cis_id: "3.2",
+            title: "Ensure Security Auditing Flags for User-Attributable Events Are Configured",
+            level: 2,
+            assessment: Assessment::Automated,
+            rationale: "The audit flags decide which user-attributable events are recorded.",
+            tags: &["auditing"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c38_configure_auditing_flags()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c38_configure_auditing_flags()],
+        },
+        Control {
+            name: "c39_configure_install_log_retention",
+            cis_id: "3.3",
+            title: "Ensure install.log is Retained for 365 or More Days and No Maximum Size",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "install.log is the record of software changes and must be kept long enough for investigations.",
+            tags: &["auditing", "logging"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c39_configure_install_log_retention()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c39_configure_install_log_retention()],
+        },
+        Control {
+            name: "c40_configure_auditing_retention",
+            cis_id: "3.4",
+            title: "Ensure Security Auditing Retention is Enabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Audit records must be retained long enough to support investigations.",
+            tags: &["auditing"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c40_configure_auditing_retention()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c40_configure_auditing_retention()],
+        },
+        Control {
+            name: "c41_1_chown_audit_control",
+            cis_id: "3.5",
+            title: "Ensure audit_control is owned by root:wheel",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Only root should be able to read or change the audit configuration and records.",
+            tags: &["auditing", "permissions"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c41_1_chown_audit_control()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c41_1_chown_audit_control()],
+        },
+        Control {
+            name: "c41_2_chmod_audit_control",
+            cis_id: "3.5",
+            title: "Ensure audit_control permissions are set to prevent access by others",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Only root should be able to read or change the audit configuration and records.",
+            tags: &["auditing", "permissions"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c41_2_chmod_audit_control()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c41_2_chmod_audit_control()],
+        },
+        Control {
+            name: "c41_3_chown_var_audit",
+            cis_id: "3.5",
+            title: "Ensure /var/audit is owned by root:wheel",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Only root should be able to read or change the audit configuration and records.",
+            tags: &["auditing", "permissions"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c41_3_chown_var_audit()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c41_3_chown_var_audit()],
+        },
+        Control {
+            name: "c41_4_chmod_var_audit",
+            cis_id: "3.5",
+            title: "Ensure /var/audit permissions are set to prevent access by others",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Only root should be able to read or change the audit configuration and records.",
+            tags: &["auditing", "permissions"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c41_4_chmod_var_audit()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c41_4_chmod_var_audit()],
+        },
+        Control {
+            name: "c42_1_enable_logging_mode",
+            cis_id: "3.6",
+            title: "Ensure Firewall Logging Mode is Enabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Firewall logs show blocked and allowed connections when investigating an incident.",
+            tags: &["firewall", "logging"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c42_1_enable_logging_mode()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c42_1_enable_logging_mode()],
+        },
+        Control {
+            name: "c42_2_configure_logging_detail",
+            cis_id: "3.6",
+            title: "Ensure Firewall Logging is Configured to Detail Mode",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Detailed firewall logs include the information needed to trace a connection.",
+            tags: &["firewall", "logging"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c42_2_configure_logging_detail()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c42_2_configure_logging_detail()],
+        },
+        Control {
+            name: "c43_disable_bonjour_advertising",
+            cis_id: "4.1",
+            title: "Ensure Bonjour Advertising Services is Disabled",
+            level: 2,
+            assessment: Assessment::Automated,
+            rationale: "Bonjour advertisements reveal the services offered by the Mac to the local network.",
+            tags: &["network"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c43_disable_bonjour_advertising()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c43_disable_bonjour_advertising()],
+        },
+        Control {
+            name: "c44_disable_http_server",
+            cis_id: "4.2",
+            title: "Ensure HTTP Server is Disabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "The built-in web server is not needed on a workstation and increases the attack surface.",
+            tags: &["network"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c44_disable_http_server()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c44_disable_http_server()],
+        },
+        Control {
+            name: "c45_disable_nfs_server",
+            cis_id: "4.3",
+            title: "Ensure NFS Server is Disabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "An NFS server exports local folders to the network.",
+            tags: &["network"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c45_disable_nfs_server()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c45_disable_nfs_server()],
+        },
+        Control {
+            name: "c46_secure_home_folders",
+            cis_id: "5.1.1",
+            title: "Ensure Home Folders Are Secure",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Other users must not be able to browse or read the content of a home folder.",
+            tags: &["permissions", "per-user"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: commands::c46_secure_home_folders,
+            post_actions: Vec::new,
+            checks: checks::c46_secure_home_folders,
+        },
+        Control {
+            name: "c47_enable_amfi",
+            cis_id: "5.1.3",
+            title: "Ensure Apple Mobile File Integrity (AMFI) is Enabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "AMFI enforces code signing; boot arguments that disable it allow unsigned code to run.",
+            tags: &["integrity"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c47_enable_amfi()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c47_enable_amfi()],
+        },
+        Control {
+            name: "c47_1_enable_library_validation",
+            cis_id: "5.1.4",
+            title: "Ensure Library Validation is Enabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Library validation stops applications from loading libraries that are not signed by Apple or the same team.",
+            tags: &["integrity"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c47_1_enable_library_validation()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c47_1_enable_library_validation()],
+        },
+        Control {
+            name: "c48_ensure_permissions_for_system_apps",
+            cis_id: "5.1.5",
+            title: "Ensure Appropriate Permissions Are Enabled for System Wide Applications",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "World-writable applications can be replaced or modified by any user.",
+            tags: &["permissions"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: commands::c48_ensure_permissions_for_system_apps,
+            post_actions: Vec::new,
+            checks: || vec![checks::c48_ensure_permissions_for_system_apps()],
+        },
+        Control {
+            name: "c49_fix_world_writable_system_files",
+            cis_id: "5.1.6",
+            title: "Ensure No World Writable Files Exist in the System Folder",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "World-writable system files can be modified by any user to escalate privileges.",
+            tags: &["permissions"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: commands::c49_fix_world_writable_system_files,
+            post_actions: Vec::new,
+            checks: || vec![checks::c49_fix_world_writable_system_files()],
+        },
+        Control {
+            name: "c50_fix_world_writable_library_files",
+            cis_id: "5.1.7",
+            title: "Ensure No World Writable Files Exist in the Library Folder",
+            level: 2,
+            assessment: Assessment::Automated,
+            rationale: "World-writable files in /Library can be modified by any user and affect every account.",
+            tags: &["permissions"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: commands::c50_fix_world_writable_library_files,
+            post_actions: Vec::new,
+            checks: || vec![checks::c50_fix_world_writable_library_files()],
+        },
+        Control {
+            name: "c51_configure_password_account_lockout",
+            cis_id: "5.2.1",
+            title: "Ensure Password Account Lockout Threshold is Configured",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Locking accounts after failed attempts slows down password guessing.",
+            tags: &["password"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c51_configure_password_account_lockout()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c51_configure_password_account_lockout()],
+        },
+        Control {
+            name: "c52_configure_password_min_length",
+            cis_id: "5.2.2",
+            title: "Ensure Password Minimum Length is Configured",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Longer passwords are harder to guess or brute force.",
+            tags: &["password"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c52_configure_password_min_length()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c52_configure_password_min_length()],
+        },
+        Control {
+            name: "c53_configure_password_requires_alpha",
+            cis_id: "5.2.3",
+            title: "Ensure Complex Password Must Contain Alphabetic Characters is Configured",
+            level: 2,
+            assessment: Assessment::Manual,
+            rationale: "Complexity requirements increase the number of possible passwords.",
+            tags: &["password"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c53_configure_password_requires_alpha()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c53_configure_password_requires_alpha()],
+        },
+        Control {
+            name: "c54_configure_password_requires_numeric",
+            cis_id: "5.2.4",
+            title: "Ensure Complex Password Must Contain Numeric Character is Configured",
+            level: 2,
+            assessment: Assessment::Manual,
+            rationale: "Complexity requirements increase the number of possible passwords.",
+            tags: &["password"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c54_configure_password_requires_numeric()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c54_configure_password_requires_numeric()],
+        },
+        Control {
+            name: "c55_configure_password_requires_symbol",
+            cis_id: "5.2.5",
+            title: "Ensure Complex Password Must Contain Special Character is Configured",
+            level: 2,
+            assessment: Assessment::Manual,
+            rationale: "Complexity requirements increase the number of possible passwords.",
+            tags: &["password"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c55_configure_password_requires_symbol()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c55_configure_password_requires_symbol()],
+        },
+        Control {
+            name: "c56_configure_password_requires_mixed_case",
+            cis_id: "5.2.6",
+            title: "Ensure Complex Password Must Contain Uppercase and Lowercase Characters is Configured",
+            level: 2,
+            assessment: Assessment::Manual,
+            rationale: "Complexity requirements increase the number of possible passwords.",
+            tags: &["password"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c56_configure_password_requires_mixed_case()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c56_configure_password_requires_mixed_case()],
+        },
+        Control {
+            name: "c57_configure_password_age",
+            cis_id: "5.2.7",
+            title: "Ensure Password Age is Configured",
+            level: 2,
+            assessment: Assessment::Automated,
+            rationale: "Expiring passwords limits how long a compromised password remains useful.",
+            tags: &["password"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c57_configure_password_age()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c57_configure_password_age()],
+        },
+        Control {
+            name: "c58_configure_password_history",
+            cis_id: "5.2.8",
+            title: "Ensure Password History is Configured",
+            level: 2,
+            assessment: Assessment::Automated,
+            rationale: "Password history stops users from cycling back to a previously compromised password.",
+            tags: &["password"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c58_configure_password_history()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c58_configure_password_history()],
+        },
+        Control {
+            name: "c59_configure_sudo_settings",
+            cis_id: "5.3",
+            title: "Ensure the Sudo Timeout Period Is Set to Zero and a Separate Timestamp Is Enabled for Each User/tty Combo",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Caching sudo credentials, or sharing them across terminals, lets other processes run commands as root without a password.",
+            tags: &["sudo"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c59_configure_sudo_settings()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c59_configure_sudo_settings()],
+        },
+        Control {
+            name: "c60_update_progress",
+            cis_id: "",
+            title: "Update Progress for Sudo Timeout and Separate Timestamp Configuration",
+            level: 0,
+            assessment: Assessment::Automated,
+            rationale: "Marks the end of the sudo configuration in the run output.",
+            tags: &["sudo"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c60_update_progress()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c60_update_progress()],
+        },
+        Control {
+            name: "c61_disable_root_account",
+            cis_id: "5.5",
+            title: "Ensure the 'root' Account is Disabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "A usable root account cannot be attributed to a person and is a high-value target.",
+            tags: &["accounts"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c61_disable_root_account()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c61_disable_root_account()],
+        },
+        Control {
+            name: "c62_disable_automatic_login",
+            cis_id: "5.6",
+            title: "Ensure Automatic Login is Disabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Automatic login gives anyone with physical access the user's session.",
+            tags: &["login", "accounts"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c62_disable_automatic_login()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c62_disable_automatic_login()],
+        },
+        Control {
+            name: "c63_prevent_admin_login_to_locked_session",
+            cis_id: "5.7",
+            title: "Ensure an Administrator Account Cannot Log in to Another User's Active and Locked Session",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "An administrator unlocking another user's session would gain access to that user's data.",
+            tags: &["login", "authorization"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c63_prevent_admin_login_to_locked_session()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c63_prevent_admin_login_to_locked_session()],
+        },
+        Control {
+            name: "c63_1_disable_fast_user_switching",
+            cis_id: "5.10",
+            title: "Ensure Fast User Switching is Disabled",
+            level: 2,
+            assessment: Assessment::Automated,
+            rationale: "Several simultaneous sessions make it harder to know who is using the Mac.",
+            tags: &["login", "accounts"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c63_1_disable_fast_user_switching()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c63_1_disable_fast_user_switching()],
+        },
+        Control {
+            name: "c64_remove_password_hints",
+            cis_id: "5.11",
+            title: "Ensure Users' Accounts Do Not Have a Password Hint",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Password hints help an attacker guess the password.",
+            tags: &["password", "accounts", "per-user"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: commands::c64_remove_password_hints,
+            post_actions: Vec::new,
+            checks: checks::c64_remove_password_hints,
+        },
+        Control {
+            name: "c65_enable_secure_keyboard_entry",
+            cis_id: "2.10",
+            title: "Ensure Secure Keyboard Entry in Terminal.app is Enabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Secure keyboard entry stops other applications from reading keystrokes typed into Terminal.",
+            tags: &["per-user"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: commands::c65_enable_secure_keyboard_entry,
+            post_actions: Vec::new,
+            checks: checks::c65_enable_secure_keyboard_entry,
+        },
+        Control {
+            name: "c66_enable_login_name_password_display",
+            cis_id: "6.1.1",
+            title: "Ensure Login Windows Displays as Name and Password is Enabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Listing the accounts on the login window gives away valid user names.",
+            tags: &["login"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c66_enable_login_name_password_display()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c66_enable_login_name_password_display()],
+        },
+        Control {
+            name: "c67_disable_password_hints",
+            cis_id: "6.1.2",
+            title: "Ensure Show Password Hints is Disabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Password hints shown on the login window help an attacker guess the password.",
+            tags: &["login", "password"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c67_disable_password_hints()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c67_disable_password_hints()],
+        },
+        Control {
+            name: "c68_disable_guest_account",
+            cis_id: "6.1.3",
+            title: "Ensure Guest Account is Disabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "The guest account allows anyone to log in without a password.",
+            tags: &["guest", "accounts"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c68_disable_guest_account()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c68_disable_guest_account()],
+        },
+        Control {
+            name: "c69_disable_guest_shared_folders_access",
+            cis_id: "6.1.4",
+            title: "Ensure Guest Access to Shared Folders is Disabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Guest access to shared folders allows unauthenticated access to data.",
+            tags: &["guest", "sharing"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c69_disable_guest_shared_folders_access()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c69_disable_guest_shared_folders_access()],
+        },
+        Control {
+            name: "c70_remove_guest_home_folder",
+            cis_id: "6.1.5",
+            title: "Ensure the Guest home Folder Does Not Exist",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "A leftover guest home folder can contain data from previous guest sessions.",
+            tags: &["guest", "accounts"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c70_remove_guest_home_folder()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c70_remove_guest_home_folder()],
+        },
+        Control {
+            name: "c71_enable_show_all_filename_extensions",
+            cis_id: "6.2",
+            title: "Ensure Show All Filename Extensions Setting is Enabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Visible extensions make it harder to disguise an executable as a document.",
+            tags: &["finder", "per-user"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: commands::c71_enable_show_all_filename_extensions,
+            post_actions: commands::c71_restart_finder,
+            checks: checks::c71_enable_show_all_filename_extensions,
+        },
+        Control {
+            name: "c72_disable_auto_open_safe_files",
+            cis_id: "7.2.1",
+            title: "Ensure Automatic Opening of Safe Files in Safari is Disabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Opening downloads automatically can run malicious content without the user's consent.",
+            tags: &["safari", "per-user"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: commands::c72_disable_auto_open_safe_files,
+            post_actions: Vec::new,
+            checks: checks::c72_disable_auto_open_safe_files,
+        },
+        Control {
+            name: "c73_enable_warn_about_fraudulent_websites",
+            cis_id: "7.2.4",
+            title: "Ensure Warn When Visiting A Fraudulent Website in Safari Is Enabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Safari warns before loading known phishing and malware sites.",
+            tags: &["safari", "per-user"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: commands::c73_enable_warn_about_fraudulent_websites,
+            post_actions: Vec::new,
+            checks: checks::c73_enable_warn_about_fraudulent_websites,
+        },
+        Control {
+            name: "c74_enable_cross_site_tracking_prevention",
+            cis_id: "7.2.5",
+            title: "Ensure Prevent Cross-site Tracking in Safari is Enabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Blocking cross-site tracking limits the data advertisers can collect.",
+            tags: &["safari", "per-user"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: commands::c74_enable_cross_site_tracking_prevention,
+            post_actions: Vec::new,
+            checks: checks::c74_enable_cross_site_tracking_prevention,
+        },
+        Control {
+            name: "c75_enable_hide_ip_address",
+            cis_id: "7.2.6",
+            title: "Ensure Hide IP Address in Safari is Enabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Hiding the IP address from trackers keeps them from following the user across websites.",
+            tags: &["safari", "per-user"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: commands::c75_enable_hide_ip_address,
+            post_actions: Vec::new,
+            checks: checks::c75_enable_hide_ip_address,
+        },
+        Control {
+            name: "c76_disable_private_click_measurement",
+            cis_id: "7.2.7",
+            title: "Ensure Private Click Measurement in Safari is Disabled",
+            level: 2,
+            assessment: Assessment::Automated,
+            rationale: "Private click measurement shares ad interaction data with websites.",
+            tags: &["safari", "per-user"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: commands::c76_disable_private_click_measurement,
+            post_actions: Vec::new,
+            checks: checks::c76_disable_private_click_measurement,
+        },
+        Control {
+            name: "c77_enable_show_full_website_address",
+            cis_id: "7.2.8",
+            title: "Ensure Show Full Website Address in Safari is Enabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "The full address makes spoofed and look-alike domains easier to spot.",
+            tags: &["safari", "per-user"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: commands::c77_enable_show_full_website_address,
+            post_actions: Vec::new,
+            checks: checks::c77_enable_show_full_website_address,
+        },
+        Control {
+            name: "c78_disable_bluetooth_sharing",
+            cis_id: "2.4.7",
+            title: "Ensure Bluetooth Sharing Is Disabled",
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "Bluetooth sharing allows files to be exchanged with nearby devices outside of organisational controls.",
+            tags: &["bluetooth", "sharing", "per-user"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: commands::c78_disable_bluetooth_sharing,
+            post_actions: Vec::new,
+            checks: checks::c78_disable_bluetooth_sharing,
+        },
+        Control {
+            name: "c79_set_login_window_banner",
+            cis_id: "5.8",
+            title: "Ensure a Login Window Banner Exists",
+            level: 2,
+            assessment: Assessment::Automated,
+            rationale: "A login banner states the acceptable use policy and supports legal action against misuse.",
+            tags: &["login"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c79_set_login_window_banner()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c79_set_login_window_banner()],
+        },
+        Control {
+            name: "c80_restart_wazuh_agent",
+            cis_id: "",
+            title: "Restart Wazuh Agent if the control file is found",
+            level: 0,
+            assessment: Assessment::Automated,
+            rationale: "Makes the Wazuh agent re-run its assessment with the new configuration.",
+            tags: &["wazuh", "restart"],
+            default_enabled: true,
+            requires: &["*"],
+            releases: EVERY_RELEASE,
+            commands: Vec::new,
+            post_actions: || vec![commands::c80_restart_wazuh_agent()],
+            checks: || vec![checks::c80_restart_wazuh_agent()],
+        },
+        Control {
+            name: "c81_configure_chrome_policies",
+            cis_id: "",
+            title: "Apply CIS-aligned Google Chrome policies",
+            level: 0,
+            assessment: Assessment::Automated,
+            rationale: "Browsers outside Safari are not covered by the macOS benchmark; their own benchmarks ask for safe browsing, blocked third-party cookies and no stored passwords or card data.",
+            tags: &["browser", "chrome"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c81_configure_chrome_policies()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c81_configure_chrome_policies()],
+        },
+        Control {
+            name: "c82_configure_edge_policies",
+            cis_id: "",
+            title: "Apply CIS-aligned Microsoft Edge policies",
+            level: 0,
+            assessment: Assessment::Automated,
+            rationale: "SmartScreen, blocked third-party cookies and no stored passwords or card data reduce phishing and credential theft in Edge.",
+            tags: &["browser", "edge"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c82_configure_edge_policies()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c82_configure_edge_policies()],
+        },
+        Control {
+            name: "c83_configure_firefox_policies",
+            cis_id: "",
+            title: "Apply CIS-aligned Firefox policies",
+            level: 0,
+            assessment: Assessment::Automated,
+            rationale: "Locked tracking protection and cookie rules, no telemetry or studies and no saved logins keep Firefox in line with the other browsers.",
+            tags: &["browser", "firefox"],
+            default_enabled: true,
+            requires: &[],
+            releases: EVERY_RELEASE,
+            commands: || vec![commands::c83_configure_firefox_policies()],
+            post_actions: Vec::new,
+            checks: || vec![checks::c83_configure_firefox_policies()],
+        },
+    ]
+}
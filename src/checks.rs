@@ -0,0 +1,1658 @@
+// Outcome of a read-only compliance check
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CheckStatus {
+    Pass,          // The system already matches the recommendation
+    Fail,          // The system does not match the recommendation
+    NotApplicable, // The recommendation does not apply to this host (or is an action, not a state)
+}
+
+// Structure to store checks
+pub struct Check {
+    name: &'static str,                                // Check name (same as the remediation command)
+    description: &'static str,                         // Recommendation being verified
+    user: Option<String>,                              // User evaluated by per-user checks
+    evaluate: Box<dyn Fn() -> (CheckStatus, String)>,  // Logic reading the current state
+}
+
+// Result of running a check
+pub struct CheckResult {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub user: Option<String>,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+impl Check {
+    // User a per-user check evaluates
+    pub fn user(&self) -> Option<&str> {
+        self.user.as_deref()
+    }
+
+    pub fn run(&self) -> CheckResult {
+        LIVE_QUERY.with(|live| live.set(false));
+        let (mut status, mut detail) = (self.evaluate)();
+
+        // With --root, a check that queried the running system says nothing about the target tree
+        if LIVE_QUERY.with(|live| live.get()) {
+            status = CheckStatus::NotApplicable;
+            detail = format!("Reads the running system, not the --root tree ({})", detail);
+        }
+
+        let marker = match status {
+            CheckStatus::Pass => "✅ PASS",
+            CheckStatus::Fail => "❌ FAIL",
+            CheckStatus::NotApplicable => "➖ N/A ",
+        };
+        match &self.user {
+            Some(user) => println!("{} {} [{}]: {}", marker, self.name, user, detail),
+            None => println!("{} {}: {}", marker, self.name, detail),
+        }
+
+        CheckResult {
+            name: self.name,
+            description: self.description,
+            user: self.user.clone(),
+            status,
+            detail,
+        }
+    }
+}
+
+fn pass(detail: String) -> (CheckStatus, String) {
+    (CheckStatus::Pass, detail)
+}
+
+fn fail(detail: String) -> (CheckStatus, String) {
+    (CheckStatus::Fail, detail)
+}
+
+fn not_applicable(detail: &str) -> (CheckStatus, String) {
+    (CheckStatus::NotApplicable, detail.to_string())
+}
+
+thread_local! {
+    // Set when the check being evaluated ran a command that does not read the --root tree
+    static LIVE_QUERY: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+fn note_query(args: &[&str]) {
+    if crate::root::is_offline() && !args.iter().any(|arg| crate::root::touches(arg)) {
+        LIVE_QUERY.with(|live| live.set(true));
+    }
+}
+
+// Runs a read-only command and returns its trimmed stdout, or None if it fails
+pub fn read_output(executable: &str, args: &[&str]) -> Option<String> {
+    note_query(args);
+    let output = crate::runner::run(executable, args).ok()?;
+    if !output.success() {
+        return None;
+    }
+    Some(output.stdout.trim().to_string())
+}
+
+// Same as read_output, but keeps stdout and stderr regardless of the exit status
+// (sysadminctl, sntp and dscl report their state on stderr)
+pub fn read_combined_output(executable: &str, args: &[&str]) -> Option<String> {
+    note_query(args);
+    let output = crate::runner::run(executable, args).ok()?;
+    Some(format!("{}{}", output.stdout, output.stderr).trim().to_string())
+}
+
+// Reads a system-wide preference: domains given as paths straight from the file, others with `defaults read`
+fn read_default(domain: &str, key: &str) -> Option<String> {
+    if domain.starts_with('/') {
+        return read_preference_file(&crate::root::path(&crate::plist::domain_file(domain)), key);
+    }
+    read_output("/usr/bin/defaults", &["read", domain, key])
+}
+
+// Value of a key in a plist file, printed the way `defaults read` prints it
+fn read_preference_file(path: &str, key: &str) -> Option<String> {
+    let (preferences, _) = crate::plist::load(path).ok()?;
+    preferences.get(key).map(|value| value.defaults_text())
+}
+
+// Plist file holding a user's preference domain, when it can be read without `defaults`: a
+// domain given as a path, or under --root any plain domain of the user's home
+fn user_preference_file(user: &str, current_host: bool, domain: &[&str]) -> Option<String> {
+    match domain {
+        [path] if path.starts_with('/') && !current_host => Some(crate::root::path(&crate::plist::domain_file(path))),
+        [name] if crate::root::is_offline() && !name.starts_with('-') && !name.starts_with('/') => {
+            let home = crate::root::path(&crate::users::home(user));
+            let file = crate::plist::domain_file(name);
+            if current_host {
+                // Without a ByHost file for the domain, the path does not exist and reads as not set
+                crate::plist::byhost_file(&home, name)
+                    .or_else(|| Some(format!("{}/Library/Preferences/ByHost/{}", home, file)))
+            } else {
+                Some(format!("{}/Library/Preferences/{}", home, file))
+            }
+        }
+        _ => None,
+    }
+}
+
+// Reads a preference in the context of a given user
+fn read_user_default(user: &str, current_host: bool, domain: &[&str], key: &str) -> Option<String> {
+    if let Some(path) = user_preference_file(user, current_host, domain) {
+        return read_preference_file(&path, key);
+    }
+
+    let mut args = vec!["-u", user, "/usr/bin/defaults"];
+    if current_host {
+        args.push("-currentHost");
+    }
+    args.push("read");
+    let domain: Vec<String> = domain.iter().map(|part| rooted(part)).collect();
+    args.extend(domain.iter().map(|part| part.as_str()));
+    args.push(key);
+    read_output("/usr/bin/sudo", &args)
+}
+
+// Preference domains given as absolute paths are files under --root, plain domains are not
+fn rooted(domain: &str) -> String {
+    if domain.starts_with('/') {
+        crate::root::path(domain)
+    } else {
+        domain.to_string()
+    }
+}
+
+// Compares a preference value against the expected one (booleans are read back as 1/0)
+fn compare_default(label: &str, value: Option<String>, expected: &str) -> (CheckStatus, String) {
+    match value {
+        Some(value) if value == expected => pass(format!("{} = {}", label, value)),
+        Some(value) => fail(format!("{} = {} (expected {})", label, value, expected)),
+        None => fail(format!("{} is not set (expected {})", label, expected)),
+    }
+}
+
+fn expect_default(domain: &str, key: &str, expected: &str) -> (CheckStatus, String) {
+    compare_default(&format!("{} {}", domain, key), read_default(domain, key), expected)
+}
+
+// Same as expect_default, but an absent key is also compliant
+fn expect_default_or_absent(domain: &str, key: &str, expected: &str) -> (CheckStatus, String) {
+    match read_default(domain, key) {
+        None => pass(format!("{} {} is not set", domain, key)),
+        value => compare_default(&format!("{} {}", domain, key), value, expected),
+    }
+}
+
+fn expect_user_default(user: &str, current_host: bool, domain: &[&str], key: &str, expected: &str) -> (CheckStatus, String) {
+    compare_default(
+        &format!("{} {}", domain.join(" "), key),
+        read_user_default(user, current_host, domain, key),
+        expected,
+    )
+}
+
+// Compares the output of a status command (e.g. `systemsetup -get...`) against an expected fragment
+fn expect_output_contains(executable: &str, args: &[&str], expected: &str) -> (CheckStatus, String) {
+    match read_combined_output(executable, args) {
+        Some(output) if output.contains(expected) => pass(output),
+        Some(output) => fail(format!("{} (expected '{}')", output, expected)),
+        None => fail(format!("Failed to run {}", executable)),
+    }
+}
+
+// Looks up a service in `launchctl print-disabled system`
+fn launchd_service_disabled(label: &str) -> (CheckStatus, String) {
+    let output = match read_output("/bin/launchctl", &["print-disabled", "system"]) {
+        Some(output) => output,
+        None => return fail("Failed to read launchd disabled services".to_string()),
+    };
+
+    let needle = format!("\"{}\"", label);
+    match output.lines().find(|line| line.contains(&needle)) {
+        Some(line) if line.contains("=> true") || line.contains("=> disabled") => {
+            pass(format!("{} is disabled", label))
+        }
+        Some(_) => fail(format!("{} is enabled", label)),
+        None => fail(format!("{} is not explicitly disabled", label)),
+    }
+}
+
+// Reads a numeric value from `pwpolicy -getglobalpolicy` (same keys used by -setglobalpolicy)
+fn read_global_policy(key: &str) -> Option<i64> {
+    let output = read_output("/usr/bin/pwpolicy", &["-n", "/Local/Default", "-getglobalpolicy"])?;
+    output
+        .split_whitespace()
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(name, _)| *name == key)
+        .and_then(|(_, value)| value.parse().ok())
+}
+
+fn expect_global_policy(key: &str, accept: impl Fn(i64) -> bool, requirement: &str) -> (CheckStatus, String) {
+    match read_global_policy(key) {
+        Some(value) if accept(value) => pass(format!("{}={}", key, value)),
+        Some(value) => fail(format!("{}={} (expected {})", key, value, requirement)),
+        None => fail(format!("{} is not set (expected {})", key, requirement)),
+    }
+}
+
+// Reads a numeric setting from `pmset -g` (e.g. "womp 0")
+fn read_pmset(key: &str) -> Option<i64> {
+    let output = read_output("/usr/bin/pmset", &["-g"])?;
+    output.lines().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        if parts.next()? == key {
+            parts.next()?.parse().ok()
+        } else {
+            None
+        }
+    })
+}
+
+fn expect_pmset(key: &str, accept: fn(i64) -> bool, requirement: &str) -> (CheckStatus, String) {
+    match read_pmset(key) {
+        Some(value) if accept(value) => pass(format!("{} {}", key, value)),
+        Some(value) => fail(format!("{} {} (expected {})", key, value, requirement)),
+        None => fail(format!("{} is not reported by pmset (expected {})", key, requirement)),
+    }
+}
+
+fn cpu_model() -> String {
+    read_output("/usr/sbin/sysctl", &["-n", "machdep.cpu.brand_string"]).unwrap_or_default()
+}
+
+// Verifies that a path is owned by root:wheel
+fn expect_root_wheel(path: &str) -> (CheckStatus, String) {
+    use std::os::unix::fs::MetadataExt;
+
+    match std::fs::metadata(crate::root::path(path)) {
+        Ok(metadata) if metadata.uid() == 0 && metadata.gid() == 0 => {
+            pass(format!("{} is owned by root:wheel", path))
+        }
+        Ok(metadata) => fail(format!("{} is owned by {}:{}", path, metadata.uid(), metadata.gid())),
+        Err(error) => fail(format!("Failed to read {}: {}", path, error)),
+    }
+}
+
+// Verifies that none of the given permission bits are set on a path
+fn expect_mode_without(path: &str, forbidden: u32, requirement: &str) -> (CheckStatus, String) {
+    use std::os::unix::fs::PermissionsExt;
+
+    match std::fs::metadata(crate::root::path(path)) {
+        Ok(metadata) => {
+            let mode = metadata.permissions().mode() & 0o7777;
+            if mode & forbidden == 0 {
+                pass(format!("{} mode is {:o}", path, mode))
+            } else {
+                fail(format!("{} mode is {:o} (expected {})", path, mode, requirement))
+            }
+        }
+        Err(error) => fail(format!("Failed to read {}: {}", path, error)),
+    }
+}
+
+fn load_audit_control() -> Result<crate::audit_control::AuditControl, String> {
+    crate::audit_control::AuditControl::load(&crate::root::path(crate::audit_control::PATH))
+}
+
+// Builds one check per user from a function evaluating a single user
+fn for_every_user(
+    name: &'static str,
+    description: &'static str,
+    evaluate: fn(&str) -> (CheckStatus, String),
+) -> Vec<Check> {
+    crate::users::human()
+        .into_iter()
+        .map(|user| Check {
+            name,
+            description,
+            user: Some(user.name.clone()),
+            evaluate: Box::new(move || evaluate(&user.name)),
+        })
+        .collect()
+}
+
+// Stand-in for the checks of a control that does not apply to the release of the target
+pub fn not_applicable_check(name: &'static str, description: &'static str, reason: String) -> Check {
+    Check {
+        name,
+        description,
+        user: None,
+        evaluate: Box::new(move || not_applicable(&reason)),
+    }
+}
+
+fn safari_domain(user: &str) -> String {
+    format!("{}/Library/Containers/com.apple.Safari/Data/Library/Preferences/com.apple.Safari", crate::users::home(user))
+}
+
+// Safari settings: in each user's container up to Ventura, from Sonoma in the managed preferences
+// every user inherits (see commands::SAFARI_MANAGED_PREFERENCES)
+fn safari_settings(name: &'static str, description: &'static str, settings: &'static [(&'static str, &'static str)]) -> Vec<Check> {
+    let all = |results: Vec<(CheckStatus, String)>| {
+        let details: Vec<String> = results.iter().map(|(_, detail)| detail.clone()).collect();
+        if results.iter().all(|(status, _)| *status == CheckStatus::Pass) {
+            pass(details.join(", "))
+        } else {
+            fail(details.join(", "))
+        }
+    };
+
+    if crate::os::at_least(crate::os::Release::Sonoma) {
+        let domain = crate::commands::SAFARI_MANAGED_PREFERENCES;
+        return vec![Check {
+            name,
+            description,
+            user: None,
+            evaluate: Box::new(move || all(settings.iter().map(|(key, expected)| expect_default(domain, key, expected)).collect())),
+        }];
+    }
+    crate::users::human()
+        .into_iter()
+        .map(|user| Check {
+            name,
+            description,
+            user: Some(user.name.clone()),
+            evaluate: Box::new(move || {
+                let domain = safari_domain(&user.name);
+                all(settings
+                    .iter()
+                    .map(|(key, expected)| expect_user_default(&user.name, false, &[&domain], key, expected))
+                    .collect())
+            }),
+        })
+        .collect()
+}
+
+pub fn c1_enable_os_autoupdate() -> Check {
+    Check {
+        name: "c1_enable_os_autoupdate",
+        description: "1.6 Ensure Install of macOS Updates Is Enabled",
+        user: None,
+        evaluate: Box::new(|| expect_default("/Library/Preferences/com.apple.SoftwareUpdate", "AutomaticallyInstallMacOSUpdates", "1")),
+    }
+}
+
+pub fn c2_ensure_apple_software_is_current() -> Check {
+    Check {
+        name: "c2_ensure_apple_software_is_current",
+        description: "1.1 Ensure All Apple-provided Software Is Current.",
+        user: None,
+        evaluate: Box::new(|| {
+            match read_combined_output("/usr/sbin/softwareupdate", &["-l"]) {
+                Some(output) if output.contains("No new software available") => {
+                    pass("No new software available.".to_string())
+                }
+                Some(output) => fail(output),
+                None => fail("Failed to run softwareupdate".to_string()),
+            }
+        }),
+    }
+}
+
+pub fn c3_enable_auto_update() -> Check {
+    Check {
+        name: "c3_enable_auto_update",
+        description: "1.2 Ensure Auto Update Is Enabled.",
+        user: None,
+        evaluate: Box::new(|| expect_default("/Library/Preferences/com.apple.SoftwareUpdate", "AutomaticCheckEnabled", "1")),
+    }
+}
+
+pub fn c4_enable_download_new_updates() -> Check {
+    Check {
+        name: "c4_enable_download_new_updates",
+        description: "1.3 Ensure Download New Updates When Available Is Enabled.",
+        user: None,
+        evaluate: Box::new(|| expect_default("/Library/Preferences/com.apple.SoftwareUpdate", "AutomaticDownload", "1")),
+    }
+}
+
+pub fn c5_enable_app_update_installation() -> Check {
+    Check {
+        name: "c5_enable_app_update_installation",
+        description: "1.4 Ensure Installation of App Update Is Enabled.",
+        user: None,
+        evaluate: Box::new(|| expect_default("/Library/Preferences/com.apple.commerce", "AutoUpdate", "1")),
+    }
+}
+
+pub fn c6_1_enable_system_data_files() -> Check {
+    Check {
+        name: "c6_1_enable_system_data_files",
+        description: "1.5.1 Ensure System Data Files Are Downloaded Automatically.",
+        user: None,
+        evaluate: Box::new(|| expect_default("/Library/Preferences/com.apple.SoftwareUpdate", "ConfigDataInstall", "1")),
+    }
+}
+
+pub fn c6_2_enable_security_updates() -> Check {
+    Check {
+        name: "c6_2_enable_security_updates",
+        description: "1.5.2 Ensure Security Updates Are Downloaded Automatically.",
+        user: None,
+        evaluate: Box::new(|| expect_default("/Library/Preferences/com.apple.SoftwareUpdate", "CriticalUpdateInstall", "1")),
+    }
+}
+
+pub fn c7_1_disable_bluetooth() -> Check {
+    Check {
+        name: "c7_1_disable_bluetooth",
+        description: "2.1.1 Ensure Bluetooth is disabled if no devices are paired.",
+        user: None,
+        evaluate: Box::new(|| expect_default("/Library/Preferences/com.apple.Bluetooth", "ControllerPowerState", "0")),
+    }
+}
+
+pub fn c7_2_restart_bluetooth_daemon() -> Check {
+    Check {
+        name: "c7_2_restart_bluetooth_daemon",
+        description: "2.1.1 Restart the Bluetooth daemon.",
+        user: None,
+        evaluate: Box::new(|| not_applicable("Service restart, no state to verify.")),
+    }
+}
+
+pub fn c8_show_bluetooth_status() -> Vec<Check> {
+    for_every_user(
+        "c8_show_bluetooth_status_for_everyuser",
+        "2.1.2 Ensure Show Bluetooth Status in Menu Bar is Enabled.",
+        |user| expect_user_default(user, true, &["com.apple.controlcenter.plist"], "Bluetooth", "18"),
+    )
+}
+
+pub fn c9_1_set_timezone() -> Check {
+    Check {
+        name: "c9_1_set_timezone",
+        description: "2.2.1 Ensure the Timezone is Set to the Organization Timezone.",
+        user: None,
+        evaluate: Box::new(|| expect_output_contains("/usr/sbin/systemsetup", &["-gettimezone"], &crate::config::current().timezone)),
+    }
+}
+
+pub fn c9_2_set_network_time_server() -> Check {
+    Check {
+        name: "c9_2_set_network_time_server",
+        description: "2.2.1 Ensure the Network Time Server is Set to the Organization Time Server.",
+        user: None,
+        evaluate: Box::new(|| expect_output_contains("/usr/sbin/systemsetup", &["-getnetworktimeserver"], &crate::config::current().time_server)),
+    }
+}
+
+pub fn c9_3_enable_network_time() -> Check {
+    Check {
+        name: "c9_3_enable_network_time",
+        description: "2.2.1 Ensure Using Network Time is Enabled.",
+        user: None,
+        evaluate: Box::new(|| expect_output_contains("/usr/sbin/systemsetup", &["-getusingnetworktime"], "Network Time: On")),
+    }
+}
+
+pub fn c10_sync_time() -> Check {
+    Check {
+        name: "c10_sync_time",
+        description: "2.2.2 Ensure Time Is Set Within Appropriate Limits.",
+        user: None,
+        evaluate: Box::new(|| {
+            // sntp prints the offset first, e.g. "+0.010226 +/- 0.047736 time.apple.com 17.253.4.253"
+            let output = read_combined_output("/usr/bin/sntp", &[crate::config::current().time_server.as_str()]).unwrap_or_default();
+            let offset = output
+                .lines()
+                .find_map(|line| line.split_whitespace().next()?.parse::<f64>().ok());
+
+            match offset {
+                Some(offset) if offset.abs() <= 270.0 => pass(format!("Clock offset is {} seconds", offset)),
+                Some(offset) => fail(format!("Clock offset is {} seconds (expected within 270)", offset)),
+                None => fail(format!("Failed to read clock offset: {}", output)),
+            }
+        }),
+    }
+}
+
+pub fn c11_set_screensaver_inactivity_interval() -> Vec<Check> {
+    for_every_user(
+        "c11_set_screensaver_inactivity_for_everyuser",
+        "2.3.1 Ensure an Inactivity Interval of 20 Minutes Or Less for the Screen Saver Is Enabled.",
+        |user| {
+            let idle = crate::config::current().screensaver_idle_time as i64;
+            match read_user_default(user, true, &["com.apple.screensaver"], "idleTime") {
+                Some(value) => match value.parse::<i64>() {
+                    Ok(seconds) if seconds > 0 && seconds <= idle => pass(format!("idleTime = {}", seconds)),
+                    _ => fail(format!("idleTime = {} (expected 1 to {} seconds)", value, idle)),
+                },
+                None => fail(format!("idleTime is not set (expected 1 to {} seconds)", idle)),
+            }
+        },
+    )
+}
+
+pub fn c12_ensure_secure_screensaver_corners() -> Vec<Check> {
+    for_every_user(
+        "c12_secure_screensaver_corners_for_everyuser",
+        "2.3.2 Ensure Screen Saver Corners Are Secure.",
+        |user| {
+            // Corner action 6 is "Disable Screen Saver"
+            let insecure: Vec<String> = ["wvous-tl-corner", "wvous-bl-corner", "wvous-tr-corner", "wvous-br-corner"]
+                .iter()
+                .filter(|corner| read_user_default(user, false, &["com.apple.dock"], corner).as_deref() == Some("6"))
+                .map(|corner| corner.to_string())
+                .collect();
+
+            if insecure.is_empty() {
+                pass("No hot corner disables the screen saver".to_string())
+            } else {
+                fail(format!("Hot corners disabling the screen saver: {}", insecure.join(", ")))
+            }
+        },
+    )
+}
+
+pub fn c13_disable_remote_apple_events() -> Check {
+    Check {
+        name: "c13_disable_remote_apple_events",
+        description: "2.4.1 Ensure Remote Apple Events is Disabled.",
+        user: None,
+        // From Sonoma the service is disabled with launchctl, systemsetup needs Full Disk Access
+        evaluate: Box::new(|| {
+            if crate::os::at_least(crate::os::Release::Sonoma) {
+                launchd_service_disabled("com.apple.AEServer")
+            } else {
+                expect_output_contains("/usr/sbin/systemsetup", &["-getremoteappleevents"], "Remote Apple Events: Off")
+            }
+        }),
+    }
+}
+
+pub fn c14_disable_internet_sharing() -> Check {
+    Check {
+        name: "c14_disable_internet_sharing",
+        description: "2.4.2 Ensure Internet Sharing is Disabled.",
+        user: None,
+        evaluate: Box::new(|| {
+            match read_default("/Library/Preferences/SystemConfiguration/com.apple.nat", "NAT") {
+                Some(value) if value.contains("Enabled = 1") => fail("Internet Sharing is enabled".to_string()),
+                Some(_) => pass("Internet Sharing is disabled".to_string()),
+                None => pass("Internet Sharing is not configured".to_string()),
+            }
+        }),
+    }
+}
+
+pub fn c15_disable_screen_sharing() -> Check {
+    Check {
+        name: "c15_disable_screen_sharing",
+        description: "2.4.3 Ensure Screen Sharing is Disabled.",
+        user: None,
+        evaluate: Box::new(|| launchd_service_disabled("com.apple.screensharing")),
+    }
+}
+
+pub fn c16_disable_printer_sharing() -> Check {
+    Check {
+        name: "c16_disable_printer_sharing",
+        description: "2.4.4 Ensure Printer Sharing is Disabled.",
+        user: None,
+        evaluate: Box::new(|| expect_output_contains("/usr/sbin/cupsctl", &[], "_share_printers=0")),
+    }
+}
+
+pub fn c17_disable_remote_login() -> Check {
+    Check {
+        name: "c17_disable_remote_login",
+        description: "2.4.5 Ensure Remote Login is Disabled.",
+        user: None,
+        evaluate: Box::new(|| expect_output_contains("/usr/sbin/systemsetup", &["-getremotelogin"], "Remote Login: Off")),
+    }
+}
+
+pub fn c18_disable_dvd_cd_sharing() -> Check {
+    Check {
+        name: "c18_disable_dvd_cd_sharing",
+        description: "2.4.6 Ensure DVD or CD Sharing is Disabled.",
+        user: None,
+        evaluate: Box::new(|| launchd_service_disabled("com.apple.ODSAgent")),
+    }
+}
+
+pub fn c19_disable_file_sharing() -> Check {
+    Check {
+        name: "c19_disable_file_sharing",
+        description: "2.4.8 Ensure File Sharing is Disabled.",
+        user: None,
+        evaluate: Box::new(|| launchd_service_disabled("com.apple.smbd")),
+    }
+}
+
+pub fn c20_disable_remote_management() -> Check {
+    Check {
+        name: "c20_disable_remote_management",
+        description: "2.4.9 Ensure Remote Management is Disabled.",
+        user: None,
+        evaluate: Box::new(|| match read_output("/usr/bin/pgrep", &["-x", "ARDAgent"]) {
+            Some(pid) => fail(format!("ARDAgent is running (pid {})", pid)),
+            None => pass("ARDAgent is not running".to_string()),
+        }),
+    }
+}
+
+pub fn c21_disable_content_caching() -> Check {
+    Check {
+        name: "c21_disable_content_caching",
+        description: "2.4.10 Ensure Content Caching is Disabled.",
+        user: None,
+        evaluate: Box::new(|| expect_output_contains("/usr/bin/AssetCacheManagerUtil", &["status"], "Activated: false")),
+    }
+}
+
+pub fn c22_disable_airdrop() -> Vec<Check> {
+    for_every_user(
+        "c22_disable_airdrop_for_everyuser",
+        "2.4.11 Ensure AirDrop is Disabled.",
+        |user| expect_user_default(user, false, &["com.apple.NetworkBrowser"], "DisableAirDrop", "1"),
+    )
+}
+
+pub fn c23_disable_media_sharing() -> Vec<Check> {
+    for_every_user(
+        "c23_disable_media_sharing_for_everyuser",
+        "2.4.12 Ensure Media Sharing is Disabled.",
+        |user| expect_user_default(user, false, &["com.apple.amp.mediasharingd"], "home-sharing-enabled", "0"),
+    )
+}
+
+pub fn c24_disable_airplay_receiver() -> Vec<Check> {
+    for_every_user(
+        "c24_disable_airplay_receiver_for_everyuser",
+        "2.4.13 Ensure AirPlay Receiver is Disabled.",
+        |user| expect_user_default(user, true, &["com.apple.controlcenter.plist"], "AirplayRecieverEnabled", "0"),
+    )
+}
+
+pub fn c25_enable_firewall() -> Check {
+    Check {
+        name: "c25_enable_firewall",
+        description: "2.5.2.1 Ensure Firewall is Enabled.",
+        user: None,
+        evaluate: Box::new(|| {
+            // Sequoia no longer keeps the firewall state in com.apple.alf
+            if crate::os::at_least(crate::os::Release::Sequoia) {
+                return expect_output_contains("/usr/libexec/ApplicationFirewall/socketfilterfw", &["--getglobalstate"], "enabled");
+            }
+            match read_default("/Library/Preferences/com.apple.alf", "globalstate") {
+                Some(value) if value == "1" || value == "2" => pass(format!("globalstate = {}", value)),
+                Some(value) => fail(format!("globalstate = {} (expected 1 or 2)", value)),
+                None => fail("globalstate is not set (expected 1 or 2)".to_string()),
+            }
+        }),
+    }
+}
+
+pub fn c26_enable_firewall_stealth_mode() -> Check {
+    Check {
+        name: "c26_enable_firewall_stealth_mode",
+        description: "2.5.2.2 Ensure Firewall Stealth Mode is Enabled.",
+        user: None,
+        evaluate: Box::new(|| expect_output_contains("/usr/libexec/ApplicationFirewall/socketfilterfw", &["--getstealthmode"], "enabled")),
+    }
+}
+
+pub fn c27_1_enable_location_services() -> Check {
+    Check {
+        name: "c27_1_enable_location_services",
+        description: "2.5.3 Ensure Location Services is Enabled (Part 1).",
+        user: None,
+        evaluate: Box::new(|| expect_default("/var/db/locationd/Library/Preferences/ByHost/com.apple.locationd", "LocationServicesEnabled", "1")),
+    }
+}
+
+pub fn c27_2_restart_location_services() -> Check {
+    Check {
+        name: "c27_2_restart_location_services",
+        description: "2.5.3 Ensure Location Services is Enabled (Part 2).",
+        user: None,
+        evaluate: Box::new(|| not_applicable("Service restart, no state to verify.")),
+    }
+}
+
+pub fn c28_1_disable_diagnostic_data() -> Check {
+    Check {
+        name: "c28_1_disable_diagnostic_data",
+        description: "2.5.5 Ensure Sending Diagnostic and Usage Data to Apple is Disabled (Global Settings).",
+        user: None,
+        evaluate: Box::new(|| expect_default("/Library/Application Support/CrashReporter/DiagnosticMessagesHistory.plist", "AutoSubmit", "0")),
+    }
+}
+
+pub fn c28_2_disable_diagnostic_data_per_user() -> Vec<Check> {
+    for_every_user(
+        "c28_2_disable_diagnostic_data_for_everyuser",
+        "2.5.5 Ensure Sending Diagnostic and Usage Data to Apple is Disabled (Per User).",
+        |user| {
+            let domain = format!("{}/Library/Preferences/com.apple.assistant.support", crate::users::home(user));
+            expect_user_default(user, false, &[&domain], "Siri Data Sharing Opt-In Status", "2")
+        },
+    )
+}
+
+pub fn c29_enable_limit_ad_tracking() -> Vec<Check> {
+    for_every_user(
+        "c29_enable_limit_ad_tracking_for_everyuser",
+        "2.5.6 Ensure Limit Ad Tracking is Enabled.",
+        |user| {
+            let domain = format!("{}/Library/Preferences/com.apple.Adlib.plist", crate::users::home(user));
+            expect_user_default(user, false, &[&domain], "allowApplePersonalizedAdvertising", "0")
+        },
+    )
+}
+
+pub fn c30_enable_gatekeeper() -> Check {
+    Check {
+        name: "c30_enable_gatekeeper",
+        description: "2.5.7 Ensure Gatekeeper is Enabled.",
+        user: None,
+        evaluate: Box::new(|| expect_output_contains("/usr/sbin/spctl", &["--status"], "assessments enabled")),
+    }
+}
+
+pub fn c31_enable_custom_login_message() -> Check {
+    Check {
+        name: "c31_enable_custom_login_message",
+        description: "2.5.8 Ensure a Custom Message for the Login Screen is Enabled.",
+        user: None,
+        evaluate: Box::new(|| match read_default("/Library/Preferences/com.apple.loginwindow", "LoginwindowText") {
+            Some(text) if !text.is_empty() => pass(format!("LoginwindowText = {}", text)),
+            _ => fail("LoginwindowText is not set".to_string()),
+        }),
+    }
+}
+
+pub fn c32_ensure_admin_password_for_system_preferences() -> Check {
+    Check {
+        name: "c32_ensure_admin_password_for_system_preferences",
+        description: "2.5.9 Ensure an Administrator Password is Required to Access System-Wide Preferences.",
+        user: None,
+        evaluate: Box::new(|| {
+            let output = read_combined_output("/usr/bin/security", &["authorizationdb", "read", "system.preferences"])
+                .unwrap_or_default();
+            // The right is compliant when <key>shared</key> is followed by <false/>
+            let shared = output
+                .split("<key>shared</key>")
+                .nth(1)
+                .map(|rest| rest.trim_start().starts_with("<false/>"));
+
+            match shared {
+                Some(true) => pass("system.preferences is not shared".to_string()),
+                Some(false) => fail("system.preferences is shared".to_string()),
+                None => fail("Failed to read the system.preferences right".to_string()),
+            }
+        }),
+    }
+}
+
+pub fn c33_require_password_to_wake() -> Check {
+    Check {
+        name: "c33_require_password_to_wake",
+        description: "2.5.10 Ensure a Password is Required to Wake the Computer From Sleep or Screen Saver is Enabled.",
+        user: None,
+        evaluate: Box::new(|| {
+            // sysadminctl reports e.g. "screenLock delay is 5 seconds" or "screenLock is immediate"
+            let output = read_combined_output("/usr/sbin/sysadminctl", &["-screenLock", "status"]).unwrap_or_default();
+            let delay = output
+                .split("screenLock delay is")
+                .nth(1)
+                .and_then(|rest| rest.split_whitespace().next())
+                .and_then(|value| value.parse::<i64>().ok());
+
+            if output.contains("immediate") {
+                pass("Screen lock is immediate".to_string())
+            } else {
+                match delay {
+                    Some(seconds) if seconds <= 5 => pass(format!("Screen lock delay is {} seconds", seconds)),
+                    Some(seconds) => fail(format!("Screen lock delay is {} seconds (expected 5 or less)", seconds)),
+                    None => fail(format!("Screen lock is not enabled: {}", output)),
+                }
+            }
+        }),
+    }
+}
+
+pub fn c34_configure_sleep_settings_based_on_cpu() -> Check {
+    Check {
+        name: "c34_configure_sleep_settings_based_on_cpu",
+        description: "2.8.1 Ensure the OS is Not Active When Resuming from Sleep and Display Sleep, Configured Based on CPU Type.",
+        user: None,
+        evaluate: Box::new(|| {
+            let cpu_model = cpu_model();
+            let results = if cpu_model.contains("Apple") {
+                vec![
+                    expect_pmset("sleep", |value| value <= 15, "15 or less"),
+                    expect_pmset("displaysleep", |value| value <= 15, "15 or less"),
+                    expect_pmset("hibernatemode", |value| value == 25, "25"),
+                ]
+            } else if cpu_model.contains("Intel") {
+                vec![
+                    expect_pmset("standbydelaylow", |value| value <= 900, "900 or less"),
+                    expect_pmset("standbydelayhigh", |value| value <= 900, "900 or less"),
+                    expect_pmset("highstandbythreshold", |value| value >= 90, "90 or more"),
+                    expect_pmset("destroyfvkeyonstandby", |value| value == 1, "1"),
+                    expect_pmset("hibernatemode", |value| value == 25, "25"),
+                ]
+            } else {
+                return not_applicable("CPU Model not identified");
+            };
+
+            let details: Vec<String> = results.iter().map(|(_, detail)| detail.clone()).collect();
+            if results.iter().all(|(status, _)| *status == CheckStatus::Pass) {
+                pass(details.join(", "))
+            } else {
+                fail(details.join(", "))
+            }
+        }),
+    }
+}
+
+pub fn c35_disable_wake_for_network_access() -> Check {
+    Check {
+        name: "c35_disable_wake_for_network_access",
+        description: "2.8.2 Ensure Wake for Network Access is Disabled.",
+        user: None,
+        evaluate: Box::new(|| expect_pmset("womp", |value| value == 0, "0")),
+    }
+}
+
+pub fn c36_disable_powernap_based_on_cpu() -> Check {
+    Check {
+        name: "c36_disable_powernap_for_intel",
+        description: "2.8.3 Ensure Power Nap is Disabled for Intel Macs.",
+        user: None,
+        evaluate: Box::new(|| {
+            if cpu_model().contains("Intel") {
+                expect_pmset("powernap", |value| value == 0, "0")
+            } else {
+                not_applicable("Power Nap check only applies to Intel CPUs.")
+            }
+        }),
+    }
+}
+
+pub fn c37_enable_security_auditing() -> Check {
+    Check {
+        name: "c37_enable_security_auditing",
+        description: "3.1 Ensure Security Auditing is Enabled.",
+        user: None,
+        evaluate: Box::new(|| match read_output("/bin/launchctl", &["list", "com.apple.auditd"]) {
+            Some(_) => pass("com.apple.auditd is loaded".to_string()),
+            None => fail("com.apple.auditd is not loaded".to_string()),
+        }),
+    }
+}
+
+pub fn c38_configure_auditing_flags() -> Check {
+    Check {
+        name: "c38_configure_auditing_flags",
+        description: "3.2 Ensure Security Auditing Flags for User-Attributable Events Are Configured.",
+        user: None,
+        evaluate: Box::new(|| {
+            let audit_control = match load_audit_control() {
+                Ok(audit_control) => audit_control,
+                Err(error) => return fail(error),
+            };
+            let flags = match audit_control.get("flags") {
+                Some(flags) => flags,
+                None => return fail("No 'flags:' line in /etc/security/audit_control".to_string()),
+            };
+
+            let missing = audit_control.missing_flags();
+            let problems = audit_control.problems();
+            if !missing.is_empty() {
+                fail(format!("flags:{} (missing {})", flags, missing.join(",")))
+            } else if !problems.is_empty() {
+                fail(format!("flags:{} ({})", flags, problems.join("; ")))
+            } else {
+                pass(format!("flags:{}", flags))
+            }
+        }),
+    }
+}
+
+pub fn c39_configure_install_log_retention() -> Check {
+    use crate::asl::{AslConfig, INSTALL_CONFIG, INSTALL_LOG, REQUIRED_TTL};
+
+    Check {
+        name: "c39_configure_install_log_retention",
+        description: "3.3 Ensure install.log is Retained for 365 or More Days and No Maximum Size.",
+        user: None,
+        evaluate: Box::new(|| {
+            let config = match AslConfig::load(&crate::root::path(INSTALL_CONFIG)) {
+                Ok(config) => config,
+                Err(error) => return fail(error),
+            };
+            let rule = match config.rule(INSTALL_LOG) {
+                Some(rule) => rule,
+                None => return fail(format!("No {} rule in {}", INSTALL_LOG, INSTALL_CONFIG)),
+            };
+
+            // The detail always carries the effective retention
+            let conflicts = rule.conflicts();
+            match rule.ttl() {
+                _ if !conflicts.is_empty() => fail(format!("{} ({})", rule.retention(), conflicts.join("; "))),
+                Some(days) if days >= REQUIRED_TTL => pass(format!("install.log is kept {}", rule.retention())),
+                _ => fail(format!("install.log is kept {} (expected {} days or more)", rule.retention(), REQUIRED_TTL)),
+            }
+        }),
+    }
+}
+
+pub fn c40_configure_auditing_retention() -> Check {
+    Check {
+        name: "c40_configure_auditing_retention",
+        description: "3.4 Ensure Security Auditing Retention is Enabled.",
+        user: None,
+        evaluate: Box::new(|| {
+            let audit_control = match load_audit_control() {
+                Ok(audit_control) => audit_control,
+                Err(error) => return fail(error),
+            };
+            let value = audit_control.get("expire-after").unwrap_or_default();
+            match audit_control.expire_after() {
+                Some(Ok(expire_after)) => match expire_after.shortfall() {
+                    None => pass(format!("expire-after:{}", value)),
+                    Some(error) => fail(format!("expire-after:{} ({})", value, error)),
+                },
+                Some(Err(error)) => fail(format!("expire-after:{} ({})", value, error)),
+                None => fail("No 'expire-after:' line in /etc/security/audit_control".to_string()),
+            }
+        }),
+    }
+}
+
+pub fn c41_1_chown_audit_control() -> Check {
+    Check {
+        name: "c41_1_chown_audit_control",
+        description: "3.5 Ensure audit_control is owned by root:wheel.",
+        user: None,
+        evaluate: Box::new(|| expect_root_wheel("/etc/security/audit_control")),
+    }
+}
+
+pub fn c41_2_chmod_audit_control() -> Check {
+    Check {
+        name: "c41_2_chmod_audit_control",
+        description: "3.5 Ensure audit_control permissions are set to prevent access by others.",
+        user: None,
+        evaluate: Box::new(|| expect_mode_without("/etc/security/audit_control", 0o006, "no access by others")),
+    }
+}
+
+pub fn c41_3_chown_var_audit() -> Check {
+    Check {
+        name: "c41_3_chown_var_audit",
+        description: "3.5 Ensure /var/audit is owned by root:wheel.",
+        user: None,
+        evaluate: Box::new(|| expect_root_wheel("/var/audit")),
+    }
+}
+
+pub fn c41_4_chmod_var_audit() -> Check {
+    Check {
+        name: "c41_4_chmod_var_audit",
+        description: "3.5 Ensure /var/audit permissions are set to prevent access by others.",
+        user: None,
+        evaluate: Box::new(|| expect_mode_without("/var/audit", 0o006, "no access by others")),
+    }
+}
+
+pub fn c42_1_enable_logging_mode() -> Check {
+    Check {
+        name: "c42_1_enable_logging_mode",
+        description: "3.6 Ensure Firewall Logging Mode is Enabled.",
+        user: None,
+        evaluate: Box::new(|| expect_output_contains("/usr/libexec/ApplicationFirewall/socketfilterfw", &["--getloggingmode"], "Log mode is on")),
+    }
+}
+
+pub fn c42_2_configure_logging_detail() -> Check {
+    Check {
+        name: "c42_2_configure_logging_detail",
+        description: "3.6 Ensure Firewall Logging is Configured to Detail Mode.",
+        user: None,
+        evaluate: Box::new(|| expect_output_contains("/usr/libexec/ApplicationFirewall/socketfilterfw", &["--getloggingopt"], "detail")),
+    }
+}
+
+pub fn c43_disable_bonjour_advertising() -> Check {
+    Check {
+        name: "c43_disable_bonjour_advertising",
+        description: "4.1 Ensure Bonjour Advertising Services is Disabled.",
+        user: None,
+        evaluate: Box::new(|| expect_default("/Library/Preferences/com.apple.mDNSResponder.plist", "NoMulticastAdvertisements", "1")),
+    }
+}
+
+pub fn c44_disable_http_server() -> Check {
+    Check {
+        name: "c44_disable_http_server",
+        description: "4.2 Ensure HTTP Server is Disabled.",
+        user: None,
+        evaluate: Box::new(|| {
+            // From Ventura the service is disabled rather than unloaded
+            if crate::os::at_least(crate::os::Release::Ventura) {
+                return launchd_service_disabled("org.apache.httpd");
+            }
+            match read_output("/bin/launchctl", &["list", "org.apache.httpd"]) {
+                Some(_) => fail("org.apache.httpd is loaded".to_string()),
+                None => pass("org.apache.httpd is not loaded".to_string()),
+            }
+        }),
+    }
+}
+
+pub fn c45_disable_nfs_server() -> Check {
+    Check {
+        name: "c45_disable_nfs_server",
+        description: "4.3 Ensure NFS Server is Disabled.",
+        user: None,
+        evaluate: Box::new(|| {
+            if std::path::Path::new(&crate::root::path("/etc/exports")).exists() {
+                return fail("/etc/exports exists".to_string());
+            }
+            launchd_service_disabled("com.apple.nfsd")
+        }),
+    }
+}
+
+pub fn c46_secure_home_folders() -> Vec<Check> {
+    for_every_user(
+        "c46_secure_home_folder_for_everyuser",
+        "5.1.1 Ensure Home Folders Are Secure.",
+        |user| expect_mode_without(&crate::users::home(user), 0o077, "no access by group or others"),
+    )
+}
+
+pub fn c47_enable_amfi() -> Check {
+    Check {
+        name: "c47_enable_amfi",
+        description: "5.1.3 Ensure Apple Mobile File Integrity (AMFI) is Enabled.",
+        user: None,
+        evaluate: Box::new(|| match read_output("/usr/sbin/nvram", &["boot-args"]) {
+            Some(value) if value.contains("amfi_get_out_of_my_way") => fail(value),
+            Some(value) => pass(value),
+            None => pass("boot-args is not set".to_string()),
+        }),
+    }
+}
+
+pub fn c47_1_enable_library_validation() -> Check {
+    Check {
+        name: "c47_1_enable_library_validation",
+        description: "5.1.4 Ensure Library Validation is Enabled.",
+        user: None,
+        evaluate: Box::new(|| expect_default_or_absent("/Library/Preferences/com.apple.security.libraryvalidation.plist", "DisableLibraryValidation", "0")),
+    }
+}
+
+// The remediation only produces commands for offending paths, so compliance means none are produced
+fn expect_no_remediation(commands: Vec<crate::commands::BashCommand>, label: &str) -> (CheckStatus, String) {
+    // The search itself failed
+    if let Some(message) = commands.iter().find_map(|command| command.message()) {
+        return fail(message);
+    }
+    if commands.is_empty() {
+        pass(format!("No {} found.", label))
+    } else {
+        fail(format!("{} {} found.", commands.len(), label))
+    }
+}
+
+pub fn c48_ensure_permissions_for_system_apps() -> Check {
+    Check {
+        name: "c48_fix_permissions_for_every_systemdwide_apps",
+        description: "5.1.5 Ensure Appropriate Permissions Are Enabled for System Wide Applications.",
+        user: None,
+        evaluate: Box::new(|| expect_no_remediation(crate::commands::c48_ensure_permissions_for_system_apps(), "world-writable applications")),
+    }
+}
+
+pub fn c49_fix_world_writable_system_files() -> Check {
+    Check {
+        name: "c49_fix_permissions_for_every_world_writable_system_files",
+        description: "5.1.6 Ensure No World Writable Files Exist in the System Folder.",
+        user: None,
+        evaluate: Box::new(|| expect_no_remediation(crate::commands::c49_fix_world_writable_system_files(), "world-writable System folders")),
+    }
+}
+
+pub fn c50_fix_world_writable_library_files() -> Check {
+    Check {
+        name: "c50_fix_permissions_every_world_writable_library_files",
+        description: "5.1.7 Ensure No World Writable Files Exist in the Library Folder.",
+        user: None,
+        evaluate: Box::new(|| expect_no_remediation(crate::commands::c50_fix_world_writable_library_files(), "world-writable Library folders")),
+    }
+}
+
+pub fn c51_configure_password_account_lockout() -> Check {
+    Check {
+        name: "c51_configure_password_account_lockout",
+        description: "5.2.1 Ensure Password Account Lockout Threshold is Configured.",
+        user: None,
+        evaluate: Box::new(|| {
+            let limit = crate::config::current().max_failed_login_attempts as i64;
+            expect_global_policy("maxFailedLoginAttempts", |value| value > 0 && value <= limit, &format!("1 to {}", limit))
+        }),
+    }
+}
+
+pub fn c52_configure_password_min_length() -> Check {
+    Check {
+        name: "c52_configure_password_min_length",
+        description: "5.2.2 Ensure Password Minimum Length is Configured.",
+        user: None,
+        evaluate: Box::new(|| {
+            let minimum = crate::config::current().min_password_length as i64;
+            expect_global_policy("minChars", |value| value >= minimum, &format!("{} or more", minimum))
+        }),
+    }
+}
+
+pub fn c53_configure_password_requires_alpha() -> Check {
+    Check {
+        name: "c53_configure_password_requires_alpha",
+        description: "5.2.3 Ensure Complex Password Must Contain Alphabetic Characters is Configured.",
+        user: None,
+        evaluate: Box::new(|| expect_global_policy("requiresAlpha", |value| value >= 1, "1 or more")),
+    }
+}
+
+pub fn c54_configure_password_requires_numeric() -> Check {
+    Check {
+        name: "c54_configure_password_requires_numeric",
+        description: "5.2.4 Ensure Complex Password Must Contain Numeric Character is Configured.",
+        user: None,
+        evaluate: Box::new(|| expect_global_policy("requiresNumeric", |value| value >= 1, "1 or more")),
+    }
+}
+
+pub fn c55_configure_password_requires_symbol() -> Check {
+    Check {
+        name: "c55_configure_password_requires_symbol",
+        description: "5.2.5 Ensure Complex Password Must Contain Special Character is Configured.",
+        user: None,
+        evaluate: Box::new(|| expect_global_policy("requiresSymbol", |value| value >= 1, "1 or more")),
+    }
+}
+
+pub fn c56_configure_password_requires_mixed_case() -> Check {
+    Check {
+        name: "c56_configure_password_requires_mixed_case",
+        description: "5.2.6 Ensure Complex Password Must Contain Uppercase and Lowercase Characters is Configured.",
+        user: None,
+        evaluate: Box::new(|| expect_global_policy("requiresMixedCase", |value| value >= 1, "1 or more")),
+    }
+}
+
+pub fn c57_configure_password_age() -> Check {
+    Check {
+        name: "c57_configure_password_age",
+        description: "5.2.7 Ensure Password Age is Configured.",
+        user: None,
+        // 365 days in minutes
+        evaluate: Box::new(|| expect_global_policy("maxMinutesUntilChangePassword", |value| value > 0 && value <= 525600, "1 to 525600 minutes")),
+    }
+}
+
+pub fn c58_configure_password_history() -> Check {
+    Check {
+        name: "c58_configure_password_history",
+        description: "5.2.8 Ensure Password History is Configured.",
+        user: None,
+        evaluate: Box::new(|| {
+            let minimum = crate::config::current().password_history as i64;
+            expect_global_policy("usingHistory", |value| value >= minimum, &format!("{} or more", minimum))
+        }),
+    }
+}
+
+pub fn c59_configure_sudo_settings() -> Check {
+    use crate::sudoers::{audit, ownership_problem, CIS_DROP_IN};
+
+    Check {
+        name: "c59_configure_sudo_settings",
+        description: "5.3 Ensure Sudo Timeout Period and Separate Timestamp are Configured.",
+        user: None,
+        evaluate: Box::new(|| {
+            let audit = audit();
+            let mut problems = audit.errors.clone();
+
+            // The last Defaults line wins, wherever it is
+            let timeout = match &audit.timestamp_timeout {
+                Some(setting) if setting.value == "0" => format!("timestamp_timeout=0 ({})", setting.at),
+                Some(setting) => {
+                    problems.push(format!("timestamp_timeout={} ({})", setting.value, setting.at));
+                    String::new()
+                }
+                None => {
+                    problems.push("timestamp_timeout is not set (sudo defaults to 5 minutes)".to_string());
+                    String::new()
+                }
+            };
+            // tty is already the default of sudo on macOS
+            let timestamp_type = match &audit.timestamp_type {
+                Some(setting) if setting.value == "tty" => format!("timestamp_type=tty ({})", setting.at),
+                Some(setting) => {
+                    problems.push(format!("timestamp_type={} ({})", setting.value, setting.at));
+                    String::new()
+                }
+                None => "timestamp_type=tty (default)".to_string(),
+            };
+
+            for entry in &audit.nopasswd {
+                problems.push(format!("NOPASSWD rule at {}: {}", entry.at, entry.value));
+            }
+            for entry in &audit.no_authenticate {
+                problems.push(format!("!authenticate at {}: {}", entry.at, entry.value));
+            }
+            let drop_in = crate::root::path(CIS_DROP_IN);
+            if std::path::Path::new(&drop_in).exists() {
+                problems.extend(ownership_problem(&drop_in));
+            }
+
+            if problems.is_empty() {
+                pass(format!("{}, {}", timeout, timestamp_type))
+            } else {
+                fail(problems.join("; "))
+            }
+        }),
+    }
+}
+
+pub fn c60_update_progress() -> Check {
+    Check {
+        name: "c60_update_progress",
+        description: "Update Progress for Sudo Timeout and Separate Timestamp Configuration.",
+        user: None,
+        evaluate: Box::new(|| not_applicable("Progress marker, no state to verify.")),
+    }
+}
+
+pub fn c61_disable_root_account() -> Check {
+    Check {
+        name: "c61_disable_root_account",
+        description: "5.5 Ensure the 'root' Account is Disabled.",
+        user: None,
+        evaluate: Box::new(|| {
+            let output = read_combined_output("/usr/bin/dscl", &[".", "-read", "/Users/root", "AuthenticationAuthority"])
+                .unwrap_or_default();
+            if output.contains("No such key: AuthenticationAuthority") {
+                pass("root has no AuthenticationAuthority".to_string())
+            } else {
+                fail("root has an AuthenticationAuthority".to_string())
+            }
+        }),
+    }
+}
+
+pub fn c62_disable_automatic_login() -> Check {
+    Check {
+        name: "c62_disable_automatic_login",
+        description: "5.6 Ensure Automatic Login is Disabled.",
+        user: None,
+        evaluate: Box::new(|| match read_default("/Library/Preferences/com.apple.loginwindow", "autoLoginUser") {
+            Some(user) => fail(format!("autoLoginUser = {}", user)),
+            None => pass("autoLoginUser is not set".to_string()),
+        }),
+    }
+}
+
+pub fn c63_prevent_admin_login_to_locked_session() -> Check {
+    Check {
+        name: "c63_prevent_admin_login_to_locked_session",
+        description: "5.7 Ensure an Administrator Account Cannot Log in to Another User's Active and Locked Session.",
+        user: None,
+        evaluate: Box::new(|| expect_output_contains("/usr/bin/security", &["authorizationdb", "read", "system.login.screensaver"], "use-login-window-ui")),
+    }
+}
+
+pub fn c63_1_disable_fast_user_switching() -> Check {
+    Check {
+        name: "c63_1_disable_fast_user_switching",
+        description: "5.10 Ensure Fast User Switching is Disabled.",
+        user: None,
+        evaluate: Box::new(|| expect_default("/Library/Preferences/.GlobalPreferences", "MultipleSessionEnabled", "0")),
+    }
+}
+
+pub fn c64_remove_password_hints() -> Vec<Check> {
+    for_every_user(
+        "c64_remove_password_hint_for_everyuser",
+        "5.11 Ensure User Accounts Do Not Have a Password Hint.",
+        |user| {
+            let output = read_combined_output("/usr/bin/dscl", &[".", "-read", &format!("/Users/{}", user), "hint"])
+                .unwrap_or_default();
+            if output.contains("No such key: hint") {
+                pass("No password hint".to_string())
+            } else {
+                fail("A password hint is set".to_string())
+            }
+        },
+    )
+}
+
+pub fn c65_enable_secure_keyboard_entry() -> Vec<Check> {
+    for_every_user(
+        "c65_enable_secure_keyboard_entry_for_everyuser",
+        "2.10 Ensure Secure Keyboard Entry in Terminal.app is Enabled.",
+        |user| expect_user_default(user, false, &["-app", "Terminal"], "SecureKeyboardEntry", "1"),
+    )
+}
+
+pub fn c66_enable_login_name_password_display() -> Check {
+    Check {
+        name: "c66_enable_login_name_password_display",
+        description: "6.1.1 Ensure Login Windows Displays as Name and Password is Enabled.",
+        user: None,
+        evaluate: Box::new(|| expect_default("/Library/Preferences/com.apple.loginwindow", "SHOWFULLNAME", "1")),
+    }
+}
+
+pub fn c67_disable_password_hints() -> Check {
+    Check {
+        name: "c67_disable_password_hints",
+        description: "6.1.2 Ensure Show Password Hints is Disabled.",
+        user: None,
+        evaluate: Box::new(|| expect_default("/Library/Preferences/com.apple.loginwindow", "RetriesUntilHint", "0")),
+    }
+}
+
+pub fn c68_disable_guest_account() -> Check {
+    Check {
+        name: "c68_disable_guest_account",
+        description: "6.1.3 Ensure Guest Account is Disabled.",
+        user: None,
+        evaluate: Box::new(|| expect_default("/Library/Preferences/com.apple.loginwindow", "GuestEnabled", "0")),
+    }
+}
+
+pub fn c69_disable_guest_shared_folders_access() -> Check {
+    Check {
+        name: "c69_disable_guest_shared_folders_access",
+        description: "6.1.4 Ensure Guest Access to Shared Folders is Disabled.",
+        user: None,
+        evaluate: Box::new(|| expect_output_contains("/usr/sbin/sysadminctl", &["-smbGuestAccess", "status"], "disabled")),
+    }
+}
+
+pub fn c70_remove_guest_home_folder() -> Check {
+    Check {
+        name: "c70_remove_guest_home_folder",
+        description: "6.1.5 Ensure the Guest home Folder Does Not Exist.",
+        user: None,
+        evaluate: Box::new(|| {
+            if std::path::Path::new(&crate::root::path("/Users/Guest")).exists() {
+                fail("/Users/Guest exists".to_string())
+            } else {
+                pass("/Users/Guest does not exist".to_string())
+            }
+        }),
+    }
+}
+
+pub fn c71_enable_show_all_filename_extensions() -> Vec<Check> {
+    let mut checks = vec![Check {
+        name: "c71_enable_show_all_extensions_root",
+        description: "6.2 Ensure Show All Filename Extensions Setting is Enabled for root.",
+        user: None,
+        evaluate: Box::new(|| expect_default("/var/root/Library/Preferences/.GlobalPreferences.plist", "AppleShowAllExtensions", "1")),
+    }];
+
+    checks.extend(for_every_user(
+        "c71_enable_show_all_extensions_for_everyuser",
+        "6.2 Ensure Show All Filename Extensions Setting is Enabled.",
+        |user| {
+            let domain = format!("{}/Library/Preferences/.GlobalPreferences.plist", crate::users::home(user));
+            expect_user_default(user, false, &[&domain], "AppleShowAllExtensions", "1")
+        },
+    ));
+
+    checks
+}
+
+pub fn c72_disable_auto_open_safe_files() -> Vec<Check> {
+    safari_settings(
+        "c72_disable_auto_open_safe_files_for_everyuser",
+        "7.2.1 Ensure Automatic Opening of Safe Files in Safari is Disabled.",
+        &[("AutoOpenSafeDownloads", "0")],
+    )
+}
+
+pub fn c73_enable_warn_about_fraudulent_websites() -> Vec<Check> {
+    safari_settings(
+        "c73_warn_about_fraudulent_websites_for_everyuser",
+        "7.2.4 Ensure Warn When Visiting A Fraudulent Website in Safari Is Enabled.",
+        &[("WarnAboutFraudulentWebsites", "1")],
+    )
+}
+
+pub fn c74_enable_cross_site_tracking_prevention() -> Vec<Check> {
+    safari_settings(
+        "c74_cross_site_tracking_prevention_for_everyuser",
+        "7.2.5 Ensure Prevent Cross-site Tracking in Safari is Enabled.",
+        &[
+            ("BlockStoragePolicy", "2"),
+            ("WebKitPreferences.storageBlockingPolicy", "1"),
+            ("WebKitStorageBlockingPolicy", "1"),
+        ],
+    )
+}
+
+pub fn c75_enable_hide_ip_address() -> Vec<Check> {
+    safari_settings(
+        "c75_enable_hide_ip_address_for_everyuser",
+        "7.2.6 Ensure Hide IP Address in Safari is Enabled.",
+        &[("WBSPrivacyProxyAvailabilityTraffic", "3300")],
+    )
+}
+
+pub fn c76_disable_private_click_measurement() -> Vec<Check> {
+    safari_settings(
+        "c76_disable_private_click_measurement_for_everyuser",
+        "7.2.7 Ensure Private Click Measurement in Safari is Disabled.",
+        &[("WebKitPreferences.privateClickMeasurementEnabled", "0")],
+    )
+}
+
+pub fn c77_enable_show_full_website_address() -> Vec<Check> {
+    safari_settings(
+        "c77_enable_full_website_address_for_everyuser",
+        "7.2.8 Ensure Show Full Website Address in Safari is Enabled.",
+        &[("ShowFullURLInSmartSearchField", "1")],
+    )
+}
+
+pub fn c78_disable_bluetooth_sharing() -> Vec<Check> {
+    let mut checks = vec![
+        Check {
+            name: "c78_disable_bluetooth_sharing_root",
+            description: "2.4.7 Ensure Bluetooth Sharing Is Disabled for root.",
+            user: None,
+            evaluate: Box::new(|| expect_default("/var/root/Library/Preferences/com.apple.Bluetooth", "PrefKeyServicesEnabled", "0")),
+        },
+        Check {
+            name: "c78_disable_bluetooth_sharing_root_byhost",
+            description: "2.4.7 Ensure Bluetooth Sharing Is Disabled for root (ByHost).",
+            user: None,
+            evaluate: Box::new(|| expect_default("/var/root/Library/Preferences/ByHost/com.apple.Bluetooth", "PrefKeyServicesEnabled", "0")),
+        },
+    ];
+
+    checks.extend(for_every_user(
+        "c78_disable_bluetooth_sharing_for_everyuser",
+        "2.4.7 Ensure Bluetooth Sharing Is Disabled.",
+        |user| expect_user_default(user, true, &["com.apple.Bluetooth"], "PrefKeyServicesEnabled", "0"),
+    ));
+
+    checks
+}
+
+pub fn c79_set_login_window_banner() -> Check {
+    Check {
+        name: "c79_set_login_window_banner",
+        description: "5.8 Ensure a Login Window Banner Exists.",
+        user: None,
+        evaluate: Box::new(|| {
+            let banner = ["/Library/Security/PolicyBanner.txt", "/Library/Security/PolicyBanner.rtf", "/Library/Security/PolicyBanner.rtfd"]
+                .iter()
+                .find(|path| std::path::Path::new(&crate::root::path(path)).exists());
+
+            match banner {
+                Some(path) => pass(format!("{} exists", path)),
+                None => fail("No PolicyBanner found in /Library/Security".to_string()),
+            }
+        }),
+    }
+}
+
+pub fn c80_restart_wazuh_agent() -> Check {
+    Check {
+        name: "c80_restart_wazuh_agent",
+        description: "Restart Wazuh Agent if the control file is found.",
+        user: None,
+        evaluate: Box::new(|| not_applicable("Service restart, no state to verify.")),
+    }
+}
+
+// Compares the installed policies of a browser with the ones the matching command applies
+fn expect_browser_policies(browser: &crate::browser::Browser) -> (CheckStatus, String) {
+    if !browser.is_installed() {
+        return not_applicable(&format!("{} is not installed", browser.name));
+    }
+    match browser.differences() {
+        Ok(differences) if differences.is_empty() => pass(format!(
+            "{} policies are set in {}",
+            browser.policies().len(),
+            browser.policy_file()
+        )),
+        Ok(differences) => fail(format!("{}: {}", browser.policy_file(), differences.join("; "))),
+        Err(error) => fail(error),
+    }
+}
+
+pub fn c81_configure_chrome_policies() -> Check {
+    Check {
+        name: "c81_configure_chrome_policies",
+        description: "Configure Google Chrome Security Policies.",
+        user: None,
+        evaluate: Box::new(|| expect_browser_policies(&crate::browser::CHROME)),
+    }
+}
+
+pub fn c82_configure_edge_policies() -> Check {
+    Check {
+        name: "c82_configure_edge_policies",
+        description: "Configure Microsoft Edge Security Policies.",
+        user: None,
+        evaluate: Box::new(|| expect_browser_policies(&crate::browser::EDGE)),
+    }
+}
+
+pub fn c83_configure_firefox_policies() -> Check {
+    Check {
+        name: "c83_configure_firefox_policies",
+        description: "Configure Firefox Security Policies.",
+        user: None,
+        evaluate: Box::new(|| expect_browser_policies(&crate::browser::FIREFOX)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{fixture, simulate, sw_vers, system_version};
+
+    fn status(check: &Check) -> CheckStatus {
+        check.run().status
+    }
+
+    const PRINT_DISABLED: &str = "$ /bin/launchctl print-disabled system\n";
+
+    #[test]
+    fn c13_reads_systemsetup_before_sonoma() {
+        simulate(&format!("{}$ /usr/sbin/systemsetup -getremoteappleevents\nRemote Apple Events: Off\n", sw_vers("13.6")));
+        assert_eq!(status(&c13_disable_remote_apple_events()), CheckStatus::Pass);
+
+        simulate(&format!("{}$ /usr/sbin/systemsetup -getremoteappleevents\nRemote Apple Events: On\n", sw_vers("13.6")));
+        assert_eq!(status(&c13_disable_remote_apple_events()), CheckStatus::Fail);
+    }
+
+    #[test]
+    fn c13_reads_launchd_from_sonoma() {
+        simulate(&format!("{}{}\t\"com.apple.AEServer\" => disabled\n", sw_vers("14.5"), PRINT_DISABLED));
+        assert_eq!(status(&c13_disable_remote_apple_events()), CheckStatus::Pass);
+
+        // systemsetup is not asked any more
+        simulate(&format!(
+            "{}{}\t\"com.apple.AEServer\" => enabled\n$ /usr/sbin/systemsetup -getremoteappleevents\nRemote Apple Events: Off\n",
+            sw_vers("14.5"),
+            PRINT_DISABLED
+        ));
+        assert_eq!(status(&c13_disable_remote_apple_events()), CheckStatus::Fail);
+    }
+
+    #[test]
+    fn c25_reads_com_apple_alf_before_sequoia() {
+        let (path, version) = system_version("14.5");
+        let alf = |state: &str| {
+            format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<plist version=\"1.0\">\n<dict>\n\t<key>globalstate</key>\n\t<integer>{}</integer>\n</dict>\n</plist>\n", state)
+        };
+        fixture("c25-on", &[(path, &version), ("/Library/Preferences/com.apple.alf.plist", &alf("1"))]);
+        assert_eq!(status(&c25_enable_firewall()), CheckStatus::Pass);
+
+        fixture("c25-off", &[(path, &version), ("/Library/Preferences/com.apple.alf.plist", &alf("0"))]);
+        assert_eq!(status(&c25_enable_firewall()), CheckStatus::Fail);
+    }
+
+    #[test]
+    fn c25_asks_socketfilterfw_from_sequoia() {
+        let socketfilterfw = "$ /usr/libexec/ApplicationFirewall/socketfilterfw --getglobalstate\n";
+        simulate(&format!("{}{}Firewall is enabled. (State = 1)\n", sw_vers("15.1"), socketfilterfw));
+        assert_eq!(status(&c25_enable_firewall()), CheckStatus::Pass);
+
+        simulate(&format!("{}{}Firewall is disabled. (State = 0)\n", sw_vers("15.1"), socketfilterfw));
+        assert_eq!(status(&c25_enable_firewall()), CheckStatus::Fail);
+    }
+
+    #[test]
+    fn c44_checks_the_loaded_daemon_before_ventura() {
+        simulate(&format!("{}$1 /bin/launchctl list org.apache.httpd\n", sw_vers("12.7")));
+        assert_eq!(status(&c44_disable_http_server()), CheckStatus::Pass);
+
+        simulate(&format!("{}$ /bin/launchctl list org.apache.httpd\n{{\n\t\"Label\" = \"org.apache.httpd\";\n}};\n", sw_vers("12.7")));
+        assert_eq!(status(&c44_disable_http_server()), CheckStatus::Fail);
+    }
+
+    #[test]
+    fn c44_checks_the_disabled_daemon_from_ventura() {
+        simulate(&format!("{}{}\t\"org.apache.httpd\" => true\n", sw_vers("13.0"), PRINT_DISABLED));
+        assert_eq!(status(&c44_disable_http_server()), CheckStatus::Pass);
+
+        // Not loaded, but not disabled either
+        simulate(&format!("{}{}$1 /bin/launchctl list org.apache.httpd\n", sw_vers("13.0"), PRINT_DISABLED));
+        assert_eq!(status(&c44_disable_http_server()), CheckStatus::Fail);
+    }
+
+    #[test]
+    fn safari_checks_read_the_managed_preferences_from_sonoma() {
+        let (path, version) = system_version("14.5");
+        let managed = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<plist version=\"1.0\">\n<dict>\n\
+                       \t<key>BlockStoragePolicy</key>\n\t<integer>2</integer>\n\
+                       \t<key>WebKitPreferences.storageBlockingPolicy</key>\n\t<integer>1</integer>\n\
+                       \t<key>WebKitStorageBlockingPolicy</key>\n\t<integer>1</integer>\n\
+                       \t<key>AutoOpenSafeDownloads</key>\n\t<true/>\n</dict>\n</plist>\n";
+        let file = format!("{}.plist", crate::commands::SAFARI_MANAGED_PREFERENCES);
+        fixture("safari-managed", &[(path, &version), (&file, managed)]);
+
+        let checks = c74_enable_cross_site_tracking_prevention();
+        assert_eq!(checks.len(), 1);
+        assert_eq!(checks[0].user(), None);
+        assert_eq!(status(&checks[0]), CheckStatus::Pass);
+        assert_eq!(status(&c72_disable_auto_open_safe_files()[0]), CheckStatus::Fail);
+        assert_eq!(status(&c77_enable_show_full_website_address()[0]), CheckStatus::Fail);
+    }
+}
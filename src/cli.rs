@@ -0,0 +1,250 @@
+use crate::catalog::Selection;
+use crate::report::ReportFormat;
+
+// What the program was asked to do
+pub enum Mode {
+    Apply,           // Apply the selected controls (default)
+    Audit,           // Only run the read-only checks
+    List,            // List the selected controls
+    Explain(String), // Describe a single control
+    Rollback,        // Restore the values recorded in a journal
+    ProfileExport,   // Write the preference-based controls as a configuration profile
+    Preflight,       // Check the host without running any control
+    Help,            // Print the usage
+}
+
+// Parsed command line
+pub struct Options {
+    pub mode: Mode,
+    pub selection: Selection,
+    pub dry_run: bool,                  // Print the plan instead of executing it
+    pub report: Option<ReportFormat>,   // Machine-readable report to write at the end
+    pub output: Option<String>,         // Path of the report or of the exported profile
+    pub journal: Option<String>,        // Rollback journal written by apply and read by rollback
+    pub no_journal: bool,               // Apply without recording the previous values
+    pub config: Option<String>,         // Organization settings file
+    pub simulate: Option<String>,       // Script of canned outputs replacing every spawned process
+    pub root: Option<String>,           // Filesystem root of the system to harden (mounted image, fixture)
+    pub split: bool,                    // One profile payload per preference domain
+    pub users: Vec<String>,             // Accounts the per-user controls act on (names or globs)
+    pub exclude_users: Vec<String>,     // Accounts the per-user controls leave out
+    pub console_user: bool,             // Per-user controls only act on the account logged in at the console
+    pub jobs: Option<usize>,            // Worker threads of an apply run (default: `jobs` of the configuration)
+}
+
+pub fn usage() -> &'static str {
+    "Usage: macos-cis-compliance [COMMAND] [OPTIONS]
+
+Commands:
+  apply              Apply the selected controls (default)
+  audit              Check the selected controls without changing anything
+  list               List the selected controls
+  explain <control>  Show what a control checks and the commands it runs
+  rollback           Restore the values recorded by previous apply runs
+  profile export     Write the selected preference settings as a configuration profile (.mobileconfig)
+  preflight          Check that the host can be hardened (also done before every apply)
+  help               Show this message
+
+Selection options (values may be comma separated or repeated):
+  -c, --control <names>  Controls to run, in the order given (e.g. c13,c17_disable_remote_login,c4*)
+  -s, --section <ids>    CIS sections (e.g. 2.4 or 2.4.*)
+  -t, --tag <tags>       Tags (e.g. per-user,safari)
+  -x, --exclude <items>  Controls, sections or tags to leave out
+  -l, --level <1|2>      CIS profile level (Level 2 includes the Level 1 controls)
+
+User options (controls acting on every user; values may be comma separated or repeated):
+      --user <names>         Only these accounts (e.g. alice,lab*)
+      --exclude-user <names> Leave these accounts out (e.g. kiosk,lab*)
+      --console-user         Only the account logged in at the console
+
+Configuration:
+      --config <path>    Organization settings (banner, timezone, password policy, audit flags...)
+      --root <path>      Filesystem root of the system to work on, e.g. a mounted disk image
+                         (commands and checks that act on the running system are skipped)
+      --simulate <path>  Run against a fake macOS: answer commands from a script instead of spawning them
+
+Apply options:
+      --dry-run          Print the plan without executing anything (also for rollback)
+      --journal <path>   Rollback journal to append to, or to restore from (default: cis-journal.txt)
+      --no-journal       Do not record the previous values
+  -j, --jobs <n>         Controls applied in parallel, 1 to 64 (default 1, or `jobs` in the configuration)
+
+Report options (apply and audit):
+      --report <format>  Write a report of the run: json, junit, html
+  -o, --output <path>    Report path (default: cis-<command>-report.<ext>)
+
+Profile options:
+  -o, --output <path>    Profile path (default: cis-compliance.mobileconfig)
+      --split            One payload per preference domain instead of a single Custom Settings payload"
+}
+
+pub fn parse(args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        mode: Mode::Apply,
+        selection: Selection {
+            controls: Vec::new(),
+            sections: Vec::new(),
+            tags: Vec::new(),
+            excludes: Vec::new(),
+            level: None,
+        },
+        dry_run: false,
+        report: None,
+        output: None,
+        journal: None,
+        no_journal: false,
+        config: None,
+        simulate: None,
+        root: None,
+        split: false,
+        users: Vec::new(),
+        exclude_users: Vec::new(),
+        console_user: false,
+        jobs: None,
+    };
+
+    let mut args = args.peekable();
+
+    // The subcommand is optional and always comes first
+    if let Some(first) = args.peek() {
+        let mode = match first.as_str() {
+            "apply" => Some(Mode::Apply),
+            "audit" => Some(Mode::Audit),
+            "list" => Some(Mode::List),
+            "rollback" => Some(Mode::Rollback),
+            "preflight" => Some(Mode::Preflight),
+            "help" | "-h" | "--help" => Some(Mode::Help),
+            "explain" => {
+                args.next();
+                match args.next() {
+                    Some(name) if !name.starts_with('-') => Some(Mode::Explain(name)),
+                    _ => return Err("'explain' expects a control name".to_string()),
+                }
+            }
+            "profile" => {
+                args.next();
+                match args.next().as_deref() {
+                    Some("export") => Some(Mode::ProfileExport),
+                    _ => return Err("'profile' expects 'export'".to_string()),
+                }
+            }
+            _ => None,
+        };
+        if let Some(mode) = mode {
+            if !matches!(mode, Mode::Explain(_) | Mode::ProfileExport) {
+                args.next();
+            }
+            options.mode = mode;
+        }
+    }
+
+    while let Some(arg) = args.next() {
+        // Accept both "--flag value" and "--flag=value"
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+            _ => (arg.clone(), None),
+        };
+
+        let target = match flag.as_str() {
+            "-c" | "--control" => &mut options.selection.controls,
+            "-s" | "--section" => &mut options.selection.sections,
+            "-t" | "--tag" => &mut options.selection.tags,
+            "-x" | "--exclude" => &mut options.selection.excludes,
+            "--user" => &mut options.users,
+            "--exclude-user" => &mut options.exclude_users,
+            "--dry-run" => {
+                options.dry_run = true;
+                continue;
+            }
+            "--no-journal" => {
+                options.no_journal = true;
+                continue;
+            }
+            "--split" => {
+                options.split = true;
+                continue;
+            }
+            "--console-user" => {
+                options.console_user = true;
+                continue;
+            }
+            "-h" | "--help" => {
+                options.mode = Mode::Help;
+                continue;
+            }
+            "--report" | "-o" | "--output" | "--journal" | "--config" | "--simulate" | "--root" | "-l" | "--level"
+            | "-j" | "--jobs" => {
+                let value = match inline_value.or_else(|| args.next()) {
+                    Some(value) => value,
+                    None => return Err(format!("'{}' expects a value", flag)),
+                };
+                match flag.as_str() {
+                    "--report" => match ReportFormat::parse(&value) {
+                        Some(format) => options.report = Some(format),
+                        None => return Err(format!("Unknown report format '{}'", value)),
+                    },
+                    "--journal" => options.journal = Some(value),
+                    "--config" => options.config = Some(value),
+                    "--simulate" => options.simulate = Some(value),
+                    "--root" => options.root = Some(value),
+                    "-j" | "--jobs" => match value.parse::<usize>() {
+                        Ok(jobs) if (1..=64).contains(&jobs) => options.jobs = Some(jobs),
+                        _ => return Err(format!("Invalid job count '{}' (expected 1 to 64)", value)),
+                    },
+                    "-l" | "--level" => match value.as_str() {
+                        "1" => options.selection.level = Some(1),
+                        "2" => options.selection.level = Some(2),
+                        _ => return Err(format!("Unknown level '{}' (expected 1 or 2)", value)),
+                    },
+                    _ => options.output = Some(value),
+                }
+                continue;
+            }
+            _ => return Err(format!("Unknown argument '{}'", arg)),
+        };
+
+        let value = match inline_value.or_else(|| args.next()) {
+            Some(value) => value,
+            None => return Err(format!("'{}' expects a value", flag)),
+        };
+        target.extend(
+            value
+                .split(',')
+                .map(|item| item.trim())
+                .filter(|item| !item.is_empty())
+                .map(|item| item.to_string()),
+        );
+    }
+
+    if options.dry_run && !matches!(options.mode, Mode::Apply | Mode::Rollback) {
+        return Err("'--dry-run' can only be used with 'apply' or 'rollback'".to_string());
+    }
+    if options.jobs.is_some() && (options.dry_run || !matches!(options.mode, Mode::Apply)) {
+        return Err("'--jobs' can only be used with 'apply' (without '--dry-run')".to_string());
+    }
+    if options.journal.is_some() && !matches!(options.mode, Mode::Apply | Mode::Rollback) {
+        return Err("'--journal' can only be used with 'apply' or 'rollback'".to_string());
+    }
+    if options.no_journal && (options.journal.is_some() || !matches!(options.mode, Mode::Apply)) {
+        return Err("'--no-journal' can only be used with 'apply', without '--journal'".to_string());
+    }
+
+    if options.report.is_some() && (options.dry_run || !matches!(options.mode, Mode::Apply | Mode::Audit)) {
+        return Err("'--report' can only be used with 'apply' (without '--dry-run') or 'audit'".to_string());
+    }
+    if options.output.is_some() && options.report.is_none() && !matches!(options.mode, Mode::ProfileExport) {
+        return Err("'--output' requires '--report'".to_string());
+    }
+    let scoped = !options.users.is_empty() || !options.exclude_users.is_empty() || options.console_user;
+    if scoped && !matches!(options.mode, Mode::Apply | Mode::Audit) {
+        return Err("'--user', '--exclude-user' and '--console-user' can only be used with 'apply' or 'audit'".to_string());
+    }
+    if options.console_user && (options.root.is_some() || !options.users.is_empty()) {
+        return Err("'--console-user' cannot be combined with '--root' or '--user'".to_string());
+    }
+    if options.split && !matches!(options.mode, Mode::ProfileExport) {
+        return Err("'--split' can only be used with 'profile export'".to_string());
+    }
+
+    Ok(options)
+}
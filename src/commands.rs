@@ -1,14 +1,47 @@
+use crate::plist::{Format, Value};
+use crate::post_action::PostAction;
+use crate::runner::FileWrite;
+
 // Structure to store commands
 pub struct BashCommand {
     name: &'static str,                     // Command name
     description: &'static str,              // Command description
     executable: &'static str,               // Path to the executable
     args: Vec<String>,                      // Fixed arguments
-    dynamic_args: Option<Box<dyn Fn() -> Vec<String>>>, // Logic for dynamic arguments
+    dynamic_args: Option<Box<dyn Fn() -> Vec<String> + Send + Sync>>, // Logic for dynamic arguments
+    write: Option<FileWrite>,               // File replaced natively instead of running the executable
+    policy: RunPolicy,                      // Input, timeout and retries of the process
+}
+
+// How the process of a command is run
+#[derive(Clone, Copy, Default)]
+pub struct RunPolicy {
+    pub stdin: Option<&'static str>,          // Written to stdin, for commands that ask for confirmation
+    pub timeout: Option<std::time::Duration>, // Killed when still running after it; None for `command_timeout`
+    pub retry: Option<Retry>,                 // For tools failing on transient network or server errors
+}
+
+// Runs of a failing command, with a wait doubling between them
+#[derive(Clone, Copy)]
+pub struct Retry {
+    pub attempts: u32,                 // Runs in total, the first one included
+    pub backoff: std::time::Duration, // Wait before the second run
 }
 
 impl BashCommand {
-    pub fn execute(&self) {
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    // Executable followed by the resolved arguments
+    pub fn argv(&self) -> Vec<String> {
+        let mut argv = vec![self.executable.to_string()];
+        argv.extend(self.resolved_args());
+        argv
+    }
+
+    // Fixed arguments followed by the dynamic ones, exactly as they are passed to the executable
+    pub fn resolved_args(&self) -> Vec<String> {
         let mut args = self.args.clone();
 
         // Adds dynamic arguments if available
@@ -16,56 +49,537 @@ impl BashCommand {
             args.extend(dynamic_fn());
         }
 
-        println!("============================================");
-        println!("Executing Command: '{}'", self.name);
-        println!("Description: {}", self.description);
-        println!("Executable: {}", self.executable);
-        println!("Arguments: {:?}", args);
-        println!("============================================");
-
-        // Check if the command needs input ("yes") for stdin
-        let needs_input = self.name == "c17_disable_remote_login";
-
-        let output = if needs_input {
-            // Send "yes" to stdin for this specific command
-            let mut child = std::process::Command::new(&self.executable)
-                .args(&args)
-                .stdin(std::process::Stdio::piped())
-                .stdout(std::process::Stdio::piped())
-                .stderr(std::process::Stdio::piped())
-                .spawn()
-                .expect("Failed to start the command");
-
-            // Write "yes" to stdin
-            if let Some(stdin) = child.stdin.as_mut() {
-                use std::io::Write;
-                stdin.write_all(b"yes\n").expect("Failed to write to stdin");
+        args
+    }
+
+    // User a per-user command acts on: the `sudo -u <user>` target, the owner of a path in a home
+    // folder or the account of a /Users/<user> directory record
+    pub fn target_user(&self) -> Option<String> {
+        let args = self.resolved_args();
+        if let Some(index) = args.iter().position(|arg| arg == "-u") {
+            return args.get(index + 1).cloned();
+        }
+        let paths = || args.iter().map(|arg| crate::root::strip(arg));
+        paths()
+            .find_map(crate::users::owner_of)
+            .map(|user| user.name.clone())
+            .or_else(|| {
+                paths()
+                    .filter_map(|path| path.strip_prefix("/Users/"))
+                    .find(|name| crate::users::human().iter().any(|user| user.name == *name))
+                    .map(|name| name.to_string())
+            })
+    }
+
+    // What the command changes, for the scheduler: two commands with the same resource never run
+    // at the same time. A preference domain (per user for plain domain names), a written file, or
+    // else the tool itself (every `pwpolicy` call edits the same policy, for example). None for
+    // informational commands and errors.
+    pub fn resource(&self) -> Option<String> {
+        if self.message().is_some() {
+            return None;
+        }
+        if let Some(write) = &self.write {
+            return Some(write.path.clone());
+        }
+        let argv = self.argv();
+        if let Some(change) = preference_change(&argv) {
+            return Some(match (change.domain.starts_with('/'), self.target_user()) {
+                (false, Some(user)) => format!("{} {}", user, change.domain),
+                _ => crate::plist::domain_file(&change.domain),
+            });
+        }
+        let program = match argv.iter().map(|arg| arg.as_str()).collect::<Vec<_>>().as_slice() {
+            ["sudo", "-u", _, program, ..] | ["sudo", program, ..] | [program, ..] => program.to_string(),
+            [] => return None,
+        };
+        Some(program.rsplit('/').next().unwrap_or(&program).to_string())
+    }
+
+    // With --root, commands that do not touch a file under the root would change the running
+    // system instead of the target tree, so they are skipped
+    pub fn acts_on_running_system(&self) -> bool {
+        crate::root::is_offline() && !self.resolved_args().iter().any(|arg| crate::root::touches(arg))
+    }
+
+    // Under --root, a `defaults write|delete` on a preference file becomes a native edit of the
+    // plist (keeping its binary or XML format): the host's `defaults` would go through its own
+    // preferences daemon, and does not exist off a Mac
+    pub fn offline(self) -> BashCommand {
+        if !crate::root::is_offline() || self.write.is_some() {
+            return self;
+        }
+        match preference_write(&self.argv()) {
+            Some(write) => file_command(self.name, self.description, write),
+            None => self,
+        }
+    }
+
+    // Text of an informational command ("already configured", "not found") or of an error met
+    // while building the command, None for a command that changes something
+    pub fn message(&self) -> Option<String> {
+        matches!(self.executable, "echo" | "error").then(|| self.args.join(" "))
+    }
+
+    // Timeout of the process: its own, or the `command_timeout` of the configuration
+    fn timeout(&self) -> Option<std::time::Duration> {
+        self.policy.timeout.or_else(crate::runner::default_timeout)
+    }
+
+    // Prints what would be executed, without spawning anything
+    pub fn plan(&self, step: usize) {
+        let args = self.resolved_args();
+
+        println!("[{}] {} - {}", step, self.name, self.description);
+        println!("    {}", shell_quote(self.executable, &args));
+        if let Some(input) = self.policy.stdin {
+            println!("    stdin: {:?}", input);
+        }
+        if let Some(timeout) = self.policy.timeout {
+            println!("    timeout: {}s", timeout.as_secs());
+        }
+        if let Some(retry) = self.policy.retry {
+            println!("    retried: {} runs in total, {}s apart and doubling", retry.attempts, retry.backoff.as_secs());
+        }
+        if self.message().is_none() && self.acts_on_running_system() {
+            println!("    skipped: acts on the running system, not on --root");
+        }
+        if let Some(write) = &self.write {
+            // Lines that appear only in the current file (-) or only in the new content (+)
+            let current = std::fs::read(&write.path).map(|bytes| readable(&bytes)).unwrap_or_default();
+            let content = readable(&write.content);
+            for line in current.lines().filter(|line| !content.lines().any(|new| new == *line)) {
+                println!("    - {}", line);
+            }
+            for line in content.lines().filter(|line| !current.lines().any(|old| old == *line)) {
+                println!("    + {}", line);
+            }
+            if let Some((mode, uid, gid)) = write.ownership {
+                println!("    mode {:o}, owner {}:{}", mode, uid, gid);
+            }
+            if let Some(validate) = &write.validate {
+                println!("    checked with: {} <new content>", validate.join(" "));
             }
+        }
+    }
 
-            child.wait_with_output().expect("Failed to read output")
-        } else {
-            // Regular execution for commands without stdin requirements
-            std::process::Command::new(&self.executable)
-                .args(&args)
-                .output()
-                .expect("Failed to execute the command")
+    pub fn execute(&self) -> ExecutionOutcome {
+        let args = self.resolved_args();
+
+        // Each block is printed at once so commands running in parallel do not mix their lines
+        println!(
+            "============================================\n\
+             Executing Command: '{}'\n\
+             Description: {}\n\
+             Executable: {}\n\
+             Arguments: {:?}\n\
+             ============================================",
+            self.name, self.description, self.executable, args
+        );
+
+        let started = std::time::Instant::now();
+        let mut outcome = ExecutionOutcome {
+            name: self.name,
+            description: self.description,
+            user: self.target_user(),
+            status: ExecutionStatus::Skipped,
+            changed: false,
+            timed_out: false,
+            attempts: 0,
+            exit_code: None,
+            stdout: String::new(),
+            stderr: String::new(),
+            duration: std::time::Duration::ZERO,
+            executable: self.executable,
+            args: args.clone(),
         };
 
-        // Process the output
-        if output.status.success() {
-            println!("--------------------------------------------");
-            println!("✅ Command '{}' executed successfully.", self.name);
-            println!("Output:\n{}", String::from_utf8_lossy(&output.stdout));
-            println!("--------------------------------------------");
+        // Informational commands ("already configured", "not found") only report a message
+        if self.executable == "echo" {
+            outcome.stdout = args.join(" ");
+            println!("➖ Command '{}' skipped: {}", self.name, outcome.stdout);
+            return outcome;
+        }
+        // The state the command depends on could not be read (a file, sysctl, find)
+        if self.executable == "error" {
+            outcome.status = ExecutionStatus::Failed;
+            outcome.stderr = args.join(" ");
+            eprintln!("❌ Error while executing '{}': {}", self.name, outcome.stderr);
+            return outcome;
+        }
+        if self.acts_on_running_system() {
+            outcome.stdout = "Acts on the running system, not on the --root tree.".to_string();
+            println!("➖ Command '{}' skipped: {}", self.name, outcome.stdout);
+            return outcome;
+        }
+
+        if let Some(write) = &self.write {
+            let result = crate::runner::current().write_file(write);
+            outcome.duration = started.elapsed();
+            match result {
+                Ok(()) => {
+                    outcome.status = ExecutionStatus::Applied;
+                    outcome.changed = true;
+                    outcome.exit_code = Some(0);
+                    outcome.stdout = format!("{} written", write.path);
+                    println!("✅ Command '{}' wrote {} in {:.2?}.", self.name, write.path, outcome.duration);
+                }
+                Err(error) => {
+                    outcome.status = ExecutionStatus::Failed;
+                    outcome.stderr = format!("Failed to write {}: {}", write.path, error);
+                    eprintln!("❌ Error while executing '{}': {}", self.name, outcome.stderr);
+                }
+            }
+            return outcome;
+        }
+
+        // A failed or stopped run is retried when the policy allows it, after a doubling wait
+        let runner = crate::runner::current();
+        let attempts = self.policy.retry.map_or(1, |retry| retry.attempts.max(1));
+        let mut backoff = self.policy.retry.map_or(std::time::Duration::ZERO, |retry| retry.backoff);
+        loop {
+            outcome.attempts += 1;
+            // The answer is sent to stdin for commands that ask for confirmation
+            let output = runner.run(self.executable, &args, self.policy.stdin, self.timeout());
+
+            // Process the output
+            match output {
+                Ok(output) => {
+                    outcome.status = if output.success() {
+                        ExecutionStatus::Applied
+                    } else {
+                        ExecutionStatus::Failed
+                    };
+                    outcome.changed = output.success();
+                    outcome.timed_out = output.timed_out;
+                    outcome.exit_code = output.code;
+                    outcome.stdout = output.stdout;
+                    outcome.stderr = output.stderr;
+                    if output.timed_out {
+                        let timeout = self.timeout().unwrap_or_default();
+                        outcome.stderr.push_str(&format!("Killed after {}s without finishing\n", timeout.as_secs()));
+                    }
+                }
+                Err(error) => {
+                    outcome.stderr = format!("Failed to start '{}': {}", self.executable, error);
+                    outcome.status = ExecutionStatus::Failed;
+                    break; // A missing executable will not appear on the next run
+                }
+            }
+            if outcome.status == ExecutionStatus::Applied || outcome.attempts >= attempts {
+                break;
+            }
+            eprintln!(
+                "🔁 Command '{}' {}, running it again in {}s (run {} of {})",
+                self.name,
+                if outcome.timed_out { "timed out" } else { "failed" },
+                backoff.as_secs(),
+                outcome.attempts + 1,
+                attempts
+            );
+            runner.pause(backoff);
+            backoff *= 2;
+        }
+        outcome.duration = started.elapsed();
+
+        if outcome.status == ExecutionStatus::Applied {
+            println!(
+                "--------------------------------------------\n\
+                 ✅ Command '{}' executed successfully in {:.2?}.\n\
+                 Output:\n{}\n\
+                 --------------------------------------------",
+                self.name, outcome.duration, outcome.stdout
+            );
         } else {
-            eprintln!("--------------------------------------------");
-            eprintln!("❌ Error while executing '{}'.", self.name);
-            eprintln!("Error Output:\n{}", String::from_utf8_lossy(&output.stderr));
-            eprintln!("--------------------------------------------");
+            eprintln!(
+                "--------------------------------------------\n\
+                 ❌ Error while executing '{}' (exit code {:?}).\n\
+                 Error Output:\n{}\n\
+                 --------------------------------------------",
+                self.name, outcome.exit_code, outcome.stderr
+            );
         }
+
+        outcome
+    }
+}
+
+// What happened to a command
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ExecutionStatus {
+    Applied, // The command ran and exited successfully
+    Failed,  // The command could not start or exited with an error
+    Skipped,       // Nothing was executed
+    NotApplicable, // The control does not apply to the release of the target
+}
+
+// Result of executing a command
+pub struct ExecutionOutcome {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub user: Option<String>,              // User a per-user command acted on
+    pub status: ExecutionStatus,
+    pub changed: bool,                     // Applied, and not known to have left everything as it was
+    pub timed_out: bool,                   // The last run was killed at the timeout
+    pub attempts: u32,                     // Runs of the process, more than one when it was retried
+    pub exit_code: Option<i32>,            // None when the command did not run or was killed by a signal
+    pub stdout: String,
+    pub stderr: String,
+    pub duration: std::time::Duration,
+    pub executable: &'static str,
+    pub args: Vec<String>,                 // Resolved arguments (fixed + dynamic)
+}
+
+impl ExecutionOutcome {
+    // Outcome of a control left out because it does not apply to the release of the target
+    pub fn not_applicable(name: &'static str, description: &'static str, reason: String) -> ExecutionOutcome {
+        ExecutionOutcome {
+            name,
+            description,
+            user: None,
+            status: ExecutionStatus::NotApplicable,
+            changed: false,
+            timed_out: false,
+            attempts: 0,
+            exit_code: None,
+            stdout: reason.clone(),
+            stderr: String::new(),
+            duration: std::time::Duration::ZERO,
+            executable: "echo",
+            args: vec![reason],
+        }
+    }
+
+    // Outcome of a command whose execution stopped with a panic (see scheduler::run)
+    pub fn failed(command: &BashCommand, reason: String) -> ExecutionOutcome {
+        ExecutionOutcome {
+            name: command.name,
+            description: command.description,
+            user: command.target_user(),
+            status: ExecutionStatus::Failed,
+            changed: false,
+            timed_out: false,
+            attempts: 0,
+            exit_code: None,
+            stdout: String::new(),
+            stderr: reason,
+            duration: std::time::Duration::ZERO,
+            executable: command.executable,
+            args: command.args.clone(),
+        }
+    }
+
+    // The command line that was run, ready to be pasted into a shell
+    pub fn command_line(&self) -> String {
+        shell_quote(self.executable, &self.args)
     }
 }
 
+// Renders a command line that can be pasted into a shell, quoting arguments when needed
+pub fn shell_quote(executable: &str, args: &[String]) -> String {
+    let quote = |arg: &str| {
+        let safe = !arg.is_empty()
+            && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:=,+@%".contains(c));
+        if safe {
+            arg.to_string()
+        } else {
+            format!("'{}'", arg.replace('\'', "'\\''"))
+        }
+    };
+
+    std::iter::once(executable)
+        .chain(args.iter().map(|arg| arg.as_str()))
+        .map(quote)
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+
+// Command replacing a whole file with new content, keeping its owner and mode
+fn file_write(name: &'static str, description: &'static str, path: String, content: String) -> BashCommand {
+    file_command(
+        name,
+        description,
+        FileWrite {
+            path,
+            content: content.into_bytes(),
+            ownership: None,
+            validate: None,
+        },
+    )
+}
+
+// Informational command, e.g. why nothing needs to be or could be done
+fn echo(name: &'static str, description: &'static str, text: String) -> BashCommand {
+    BashCommand {
+        name,
+        description,
+        executable: "echo",
+        args: vec![text],
+        dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
+    }
+}
+
+// Command reporting an error met while it was built, counted as a failure when executed
+fn error(name: &'static str, description: &'static str, text: String) -> BashCommand {
+    BashCommand {
+        executable: "error",
+        ..echo(name, description, text)
+    }
+}
+
+// Paths printed by a `find` run while the catalog is built; Err when it could not run to the end
+fn find_paths(args: &[&str]) -> Result<Vec<String>, String> {
+    let output = crate::runner::run("find", args).map_err(|error| format!("Failed to run find: {}", error))?;
+    if output.timed_out {
+        return Err("find was stopped at the timeout".to_string());
+    }
+    // find exits with 1 when some folders cannot be read, the paths it printed still count
+    Ok(output.stdout.lines().map(|line| line.to_string()).collect())
+}
+
+fn file_command(name: &'static str, description: &'static str, write: FileWrite) -> BashCommand {
+    BashCommand {
+        name,
+        description,
+        executable: "write",
+        args: vec![write.path.clone()],
+        dynamic_args: None,
+        write: Some(write),
+        policy: RunPolicy::default(),
+    }
+}
+
+// File content as text for the plan, binary property lists shown as XML
+fn readable(bytes: &[u8]) -> String {
+    match crate::plist::parse(bytes) {
+        Ok((value, Format::Binary)) => crate::plist::to_xml(&value),
+        _ => String::from_utf8_lossy(bytes).to_string(),
+    }
+}
+
+// Preference set or removed by a `[sudo [-u <user>]] defaults [-currentHost] write|delete` command
+pub struct PreferenceChange {
+    pub current_host: bool,
+    pub domain: String,       // Name or path (under --root), "-app <name>" resolved to com.apple.<name>
+    pub key: String,
+    pub value: Option<Value>, // None for `delete`
+}
+
+pub fn preference_change(argv: &[String]) -> Option<PreferenceChange> {
+    let argv: Vec<&str> = argv.iter().map(|arg| arg.as_str()).collect();
+    let argv = match argv.as_slice() {
+        ["sudo", "-u", _, rest @ ..] | ["sudo", rest @ ..] => rest,
+        all => all,
+    };
+    let (current_host, args) = match argv {
+        ["/usr/bin/defaults" | "defaults", "-currentHost", rest @ ..] => (true, rest),
+        ["/usr/bin/defaults" | "defaults", rest @ ..] => (false, rest),
+        _ => return None,
+    };
+    let (verb, domain, key, value) = match args {
+        [verb, "-app", name, key, value @ ..] => (*verb, format!("com.apple.{}", name), *key, value),
+        [verb, domain, key, value @ ..] if !domain.starts_with('-') => (*verb, domain.to_string(), *key, value),
+        _ => return None,
+    };
+    if key.starts_with('-') {
+        return None; // A value without a key (e.g. `write <domain> -int 1`) is rejected by defaults
+    }
+
+    let value = match (verb, value) {
+        ("write", value) => Some(defaults_value(value)?),
+        ("delete", []) => None,
+        _ => return None,
+    };
+    Some(PreferenceChange {
+        current_host,
+        domain,
+        key: key.to_string(),
+        value,
+    })
+}
+
+// `defaults write|delete` of a preference file, as a plist edit
+fn preference_write(argv: &[String]) -> Option<FileWrite> {
+    use std::os::unix::fs::MetadataExt;
+
+    let change = preference_change(argv).filter(|change| !change.current_host && change.domain.starts_with('/'))?;
+    let path = crate::plist::domain_file(&change.domain);
+    let existing = std::fs::metadata(&path).ok();
+    let (mut preferences, format) = match &existing {
+        Some(_) => crate::plist::load(&path).ok()?,
+        None => (Value::Dict(Vec::new()), Format::Xml),
+    };
+    match change.value {
+        Some(value) => preferences.insert(&change.key, value),
+        None if existing.is_some() => preferences.remove(&change.key),
+        None => return None,
+    }
+
+    // A new file belongs to the owner of its directory, private to users like the files cfprefsd creates
+    let ownership = match existing {
+        Some(_) => None,
+        None => {
+            let parent = std::fs::metadata(std::path::Path::new(&path).parent()?).ok()?;
+            Some((if parent.uid() == 0 { 0o644 } else { 0o600 }, parent.uid(), parent.gid()))
+        }
+    };
+    Some(FileWrite {
+        content: crate::plist::serialize(&preferences, format),
+        path,
+        ownership,
+        validate: None,
+    })
+}
+
+// Value given to `defaults write`, e.g. ["-bool", "true"] or ["-dict", "Enabled", "-int", "0"]
+fn defaults_value(args: &[&str]) -> Option<Value> {
+    match args {
+        ["-dict", pairs @ ..] => {
+            let mut entries = Vec::new();
+            let mut rest = pairs;
+            while let [key, tail @ ..] = rest {
+                let (value, used) = typed_value(tail)?;
+                entries.push((key.to_string(), value));
+                rest = &tail[used..];
+            }
+            Some(Value::Dict(entries))
+        }
+        ["-array", items @ ..] => {
+            let mut values = Vec::new();
+            let mut rest = items;
+            while !rest.is_empty() {
+                let (value, used) = typed_value(rest)?;
+                values.push(value);
+                rest = &rest[used..];
+            }
+            Some(Value::Array(values))
+        }
+        _ => match typed_value(args)? {
+            (value, used) if used == args.len() => Some(value),
+            _ => None,
+        },
+    }
+}
+
+// One scalar value and the number of arguments it took (a bare word is a string)
+fn typed_value(args: &[&str]) -> Option<(Value, usize)> {
+    let value = match args {
+        ["-bool" | "-boolean", value, ..] => match value.to_ascii_lowercase().as_str() {
+            "true" | "yes" | "1" => Value::Bool(true),
+            "false" | "no" | "0" => Value::Bool(false),
+            _ => return None,
+        },
+        ["-int" | "-integer", value, ..] => Value::Integer(value.parse().ok()?),
+        ["-float", value, ..] => Value::Real(value.parse().ok()?),
+        ["-string", value, ..] => Value::String(value.to_string()),
+        [value, ..] if !value.starts_with('-') => return Some((Value::String(value.to_string()), 1)),
+        _ => return None,
+    };
+    Some((value, 2))
+}
 
 pub fn c1_enable_os_autoupdate() -> BashCommand {
     BashCommand {
@@ -75,12 +589,14 @@ pub fn c1_enable_os_autoupdate() -> BashCommand {
         args: vec![
             "/usr/bin/defaults".to_string(), // First part of the command
             "write".to_string(), // Sub-command
-            "/Library/Preferences/com.apple.SoftwareUpdate".to_string(), // Preference to modify
+            crate::root::path("/Library/Preferences/com.apple.SoftwareUpdate"), // Preference to modify
             "AutomaticallyInstallMacOSUpdates".to_string(), // Key to modify
             "-bool".to_string(), // Boolean flag
             "true".to_string(), // Value to set
         ],
         dynamic_args: None, // No dynamic arguments
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
@@ -94,6 +610,13 @@ pub fn c2_ensure_apple_software_is_current() -> BashCommand {
             "-l".to_string(), // List available updates
         ],
         dynamic_args: None, // No dynamic arguments
+        write: None,
+        // The catalog server sometimes stops answering, leaving softwareupdate waiting forever
+        policy: RunPolicy {
+            stdin: None,
+            timeout: Some(std::time::Duration::from_secs(300)),
+            retry: Some(Retry { attempts: 3, backoff: std::time::Duration::from_secs(30) }),
+        },
     }
 }
 
@@ -105,12 +628,14 @@ pub fn c3_enable_auto_update() -> BashCommand {
         args: vec![
             "/usr/bin/defaults".to_string(), // First part of the command
             "write".to_string(), // Sub-command
-            "/Library/Preferences/com.apple.SoftwareUpdate".to_string(), // Preference to modify
+            crate::root::path("/Library/Preferences/com.apple.SoftwareUpdate"), // Preference to modify
             "AutomaticCheckEnabled".to_string(), // Key to modify
             "-bool".to_string(), // Boolean flag
             "true".to_string(), // Value to set
         ],
         dynamic_args: None, // No dynamic arguments
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
@@ -122,12 +647,14 @@ pub fn c4_enable_download_new_updates() -> BashCommand {
         args: vec![
             "/usr/bin/defaults".to_string(), // First part of the command
             "write".to_string(), // Sub-command
-            "/Library/Preferences/com.apple.SoftwareUpdate".to_string(), // Preference to modify
+            crate::root::path("/Library/Preferences/com.apple.SoftwareUpdate"), // Preference to modify
             "AutomaticDownload".to_string(), // Key to modify
             "-bool".to_string(), // Boolean flag
             "true".to_string(), // Value to set
         ],
         dynamic_args: None, // No dynamic arguments
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
@@ -139,12 +666,14 @@ pub fn c5_enable_app_update_installation() -> BashCommand {
         args: vec![
             "/usr/bin/defaults".to_string(), // First part of the command
             "write".to_string(), // Sub-command
-            "/Library/Preferences/com.apple.commerce".to_string(), // Preference to modify
+            crate::root::path("/Library/Preferences/com.apple.commerce"), // Preference to modify
             "AutoUpdate".to_string(), // Key to modify
             "-bool".to_string(), // Boolean flag
             "true".to_string(), // Value to set
         ],
         dynamic_args: None, // No dynamic arguments
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
@@ -156,12 +685,14 @@ pub fn c6_1_enable_system_data_files() -> BashCommand {
         args: vec![
             "/usr/bin/defaults".to_string(), // First command
             "write".to_string(), // Sub-command
-            "/Library/Preferences/com.apple.SoftwareUpdate".to_string(), // Preference to modify
+            crate::root::path("/Library/Preferences/com.apple.SoftwareUpdate"), // Preference to modify
             "ConfigDataInstall".to_string(), // Key to modify
             "-bool".to_string(), // Boolean flag
             "true".to_string(), // Value to set
         ],
         dynamic_args: None, // No dynamic arguments
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
@@ -173,36 +704,40 @@ pub fn c6_2_enable_security_updates() -> BashCommand {
         args: vec![
             "/usr/bin/defaults".to_string(), // First command
             "write".to_string(), // Sub-command
-            "/Library/Preferences/com.apple.SoftwareUpdate".to_string(), // Preference to modify
+            crate::root::path("/Library/Preferences/com.apple.SoftwareUpdate"), // Preference to modify
             "CriticalUpdateInstall".to_string(), // Key to modify
             "-bool".to_string(), // Boolean flag
             "true".to_string(), // Value to set
         ],
         dynamic_args: None, // No dynamic arguments
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
 pub fn c7_1_disable_bluetooth() -> BashCommand {
     BashCommand {
         name: "c7_1_disable_bluetooth", // Command name
-        description: "1.5.1 Ensure Bluetooth is disabled if no devices are paired.", // Command description
+        description: "2.1.1 Ensure Bluetooth is disabled if no devices are paired.", // Command description
         executable: "sudo", // Path to the executable
         args: vec![
             "/usr/bin/defaults".to_string(), // First part of the command
             "write".to_string(), // Sub-command
-            "/Library/Preferences/com.apple.Bluetooth".to_string(), // Preference to modify
+            crate::root::path("/Library/Preferences/com.apple.Bluetooth"), // Preference to modify
             "ControllerPowerState".to_string(), // Key to modify
             "-int".to_string(), // Integer flag
             "0".to_string(), // Value to set
         ],
         dynamic_args: None, // No dynamic arguments
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
-pub fn c7_2_restart_bluetooth_daemon() -> BashCommand {
-    BashCommand {
+pub fn c7_2_restart_bluetooth_daemon() -> PostAction {
+    let command = BashCommand {
         name: "c7_2_restart_bluetooth_daemon", // Command name
-        description: "1.5.2 Restart the Bluetooth daemon.", // Command description
+        description: "2.1.1 Restart the Bluetooth daemon.", // Command description
         executable: "sudo", // Path to the executable
         args: vec![
             "killall".to_string(), // Command to terminate the process
@@ -210,35 +745,19 @@ pub fn c7_2_restart_bluetooth_daemon() -> BashCommand {
             "bluetoothd".to_string(), // Target daemon
         ],
         dynamic_args: None, // No dynamic arguments
-    }
+        write: None,
+        policy: RunPolicy::default(),
+    };
+    // bluetoothd only reads the power state when it starts
+    PostAction { after: &["c7_1"], user: None, command }
 }
 
 pub fn c8_show_bluetooth_status() -> Vec<BashCommand> {
-    // Subfunction 1: Fetch the list of users
-    fn fetch_users() -> Vec<String> {
-        let users_dir = "/Users";
-        let name = std::fs::read_dir(users_dir)
-            .expect("Failed to read /Users directory")
-            .filter_map(|entry| entry.ok())
-            .filter_map(|entry| {
-                let name = entry.file_name().into_string().ok()?;
-                if name != "Shared" && name != ".localized" {
-                    Some(name)
-                } else {
-                    None
-                }
-            })
-            .collect();
-
-        println!("Users= {:?}", name);
-        return name
-    }
-
-    // Subfunction 2: Create and execute a BashCommand for a user
+    // Subfunction: Create and execute a BashCommand for a user
     fn create_bluetooth_status_command(username: &str) -> BashCommand {
         BashCommand {
-            name: &"c8_show_bluetooth_status_for_everyuser", // Concatenate strings directly
-            description: "1.5 Ensure Show Bluetooth Status in Menu Bar is Enabled.", // Command description
+            name: "c8_show_bluetooth_status_for_everyuser", // Concatenate strings directly
+            description: "2.1.2 Ensure Show Bluetooth Status in Menu Bar is Enabled.", // Command description
             executable: "sudo", // Path to the executable
             args: vec![
                 "-u".to_string(), // Switch user
@@ -252,41 +771,47 @@ pub fn c8_show_bluetooth_status() -> Vec<BashCommand> {
                 "18".to_string(),
             ],
             dynamic_args: None, // No dynamic arguments
+            write: None,
+            policy: RunPolicy::default(),
         }
     }
 
     // Generate commands for all users
-    fetch_users()
+    crate::users::human()
         .iter()
-        .map(|user| create_bluetooth_status_command(user))
+        .map(|user| create_bluetooth_status_command(&user.name))
         .collect()
 }
 
 pub fn c9_1_set_timezone() -> BashCommand {
     BashCommand {
         name: "c9_1_set_timezone", // Command name
-        description: "2.2.1 Ensure Set Timezone to America/Sao_Paulo.", // Command description
+        description: "2.2.1 Ensure the Timezone is Set to the Organization Timezone.", // Command description
         executable: "sudo", // Path to the executable
         args: vec![
             "/usr/sbin/systemsetup".to_string(),
             "-settimezone".to_string(),
-            "America/Sao_Paulo".to_string(),
+            crate::config::current().timezone.clone(), // America/Sao_Paulo unless configured
         ],
         dynamic_args: None, // No dynamic arguments
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
 pub fn c9_2_set_network_time_server() -> BashCommand {
     BashCommand {
         name: "c9_2_set_network_time_server", // Command name
-        description: "2.2.1 Ensure Set Network Time Server to time.apple.com.", // Command description
+        description: "2.2.1 Ensure the Network Time Server is Set to the Organization Time Server.", // Command description
         executable: "sudo", // Path to the executable
         args: vec![
             "/usr/sbin/systemsetup".to_string(),
             "-setnetworktimeserver".to_string(),
-            "time.apple.com".to_string(),
+            crate::config::current().time_server.clone(), // time.apple.com unless configured
         ],
         dynamic_args: None, // No dynamic arguments
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
@@ -301,6 +826,8 @@ pub fn c9_3_enable_network_time() -> BashCommand {
             "on".to_string(),
         ],
         dynamic_args: None, // No dynamic arguments
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
@@ -312,30 +839,20 @@ pub fn c10_sync_time() -> BashCommand {
         args: vec![
             "sntp".to_string(),
             "-sS".to_string(),
-            "time.apple.com".to_string(),
+            crate::config::current().time_server.clone(),
         ],
         dynamic_args: None, // No dynamic arguments
+        write: None,
+        // The time server may not answer right after the network comes up
+        policy: RunPolicy {
+            stdin: None,
+            timeout: Some(std::time::Duration::from_secs(30)),
+            retry: Some(Retry { attempts: 3, backoff: std::time::Duration::from_secs(5) }),
+        },
     }
 }
 
 pub fn c11_set_screensaver_inactivity_interval() -> Vec<BashCommand> {
-    // Subfunction: Fetch the list of users
-    fn fetch_users() -> Vec<String> {
-        let users_dir = "/Users";
-        std::fs::read_dir(users_dir)
-            .expect("Failed to read /Users directory")
-            .filter_map(|entry| entry.ok())
-            .filter_map(|entry| {
-                let name = entry.file_name().into_string().ok()?;
-                if name != "Shared" && name != ".localized" {
-                    Some(name)
-                } else {
-                    None
-                }
-            })
-            .collect()
-    }
-
     // Subfunction: Create a BashCommand for a specific user
     fn create_screensaver_command(username: &str) -> BashCommand {
         BashCommand {
@@ -351,37 +868,22 @@ pub fn c11_set_screensaver_inactivity_interval() -> Vec<BashCommand> {
                 "com.apple.screensaver".to_string(),
                 "idleTime".to_string(),
                 "-int".to_string(),
-                "600".to_string(), // 10 minutes in seconds
+                crate::config::current().screensaver_idle_time.to_string(), // 600 seconds (10 minutes) unless configured
             ],
             dynamic_args: None,
+            write: None,
+            policy: RunPolicy::default(),
         }
     }
 
     // Fetch the list of users and generate commands
-    fetch_users()
+    crate::users::human()
         .iter()
-        .map(|user| create_screensaver_command(user))
+        .map(|user| create_screensaver_command(&user.name))
         .collect()
 }
 
 pub fn c12_ensure_secure_screensaver_corners() -> Vec<BashCommand> {
-    // Subfunction: Fetch the list of users
-    fn fetch_users() -> Vec<String> {
-        let users_dir = "/Users";
-        std::fs::read_dir(users_dir)
-            .expect("Failed to read /Users directory")
-            .filter_map(|entry| entry.ok())
-            .filter_map(|entry| {
-                let name = entry.file_name().into_string().ok()?;
-                if name != "Shared" && name != ".localized" {
-                    Some(name)
-                } else {
-                    None
-                }
-            })
-            .collect()
-    }
-
     // Subfunctions: Create BashCommand for each corner
     fn configure_top_left_corner(username: &str) -> BashCommand {
         BashCommand {
@@ -399,12 +901,14 @@ pub fn c12_ensure_secure_screensaver_corners() -> Vec<BashCommand> {
                 "0".to_string(),
             ],
             dynamic_args: None,
+            write: None,
+            policy: RunPolicy::default(),
         }
     }
 
     fn configure_bottom_left_corner(username: &str) -> BashCommand {
         BashCommand {
-            name: "c12_secure_top_left_corner_for_everyuser",
+            name: "c12_secure_bottom_left_corner_for_everyuser",
             description: "2.3.2 Ensure Screen Saver Bottom-Left Corner Is Secure.",
             executable: "sudo",
             args: vec![
@@ -418,12 +922,14 @@ pub fn c12_ensure_secure_screensaver_corners() -> Vec<BashCommand> {
                 "0".to_string(),
             ],
             dynamic_args: None,
+            write: None,
+            policy: RunPolicy::default(),
         }
     }
 
     fn configure_top_right_corner(username: &str) -> BashCommand {
         BashCommand {
-            name: "c12_secure_top_left_corner_for_everyuser",
+            name: "c12_secure_top_right_corner_for_everyuser",
             description: "2.3.2 Ensure Screen Saver Top-Right Corner Is Secure.",
             executable: "sudo",
             args: vec![
@@ -437,12 +943,14 @@ pub fn c12_ensure_secure_screensaver_corners() -> Vec<BashCommand> {
                 "0".to_string(),
             ],
             dynamic_args: None,
+            write: None,
+            policy: RunPolicy::default(),
         }
     }
 
     fn configure_bottom_right_corner(username: &str) -> BashCommand {
         BashCommand {
-            name: "c12_secure_top_left_corner_for_everyuser",
+            name: "c12_secure_bottom_right_corner_for_everyuser",
             description: "2.3.2 Ensure Screen Saver Bottom-Right Corner Is Secure.",
             executable: "sudo",
             args: vec![
@@ -456,18 +964,20 @@ pub fn c12_ensure_secure_screensaver_corners() -> Vec<BashCommand> {
                 "0".to_string(),
             ],
             dynamic_args: None,
+            write: None,
+            policy: RunPolicy::default(),
         }
     }
 
     // Fetch the list of users and generate commands for all corners
-    fetch_users()
+    crate::users::human()
         .iter()
         .flat_map(|user| {
             vec![
-                configure_top_left_corner(user),
-                configure_bottom_left_corner(user),
-                configure_top_right_corner(user),
-                configure_bottom_right_corner(user),
+                configure_top_left_corner(&user.name),
+                configure_bottom_left_corner(&user.name),
+                configure_top_right_corner(&user.name),
+                configure_bottom_right_corner(&user.name),
             ]
         })
         .collect()
@@ -478,12 +988,23 @@ pub fn c13_disable_remote_apple_events() -> BashCommand {
         name: "c13_disable_remote_apple_events", // Command name
         description: "2.4.1 Ensure Remote Apple Events is Disabled.", // Command description
         executable: "sudo", // Path to the executable
-        args: vec![
-            "systemsetup".to_string(), // Command
-            "-setremoteappleevents".to_string(),
-            "off".to_string(),
-        ],
+        // From Sonoma systemsetup needs Full Disk Access, the service is disabled with launchctl
+        args: if crate::os::at_least(crate::os::Release::Sonoma) {
+            vec![
+                "launchctl".to_string(),
+                "disable".to_string(),
+                "system/com.apple.AEServer".to_string(),
+            ]
+        } else {
+            vec![
+                "systemsetup".to_string(), // Command
+                "-setremoteappleevents".to_string(),
+                "off".to_string(),
+            ]
+        },
         dynamic_args: None, // No dynamic arguments
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
@@ -495,7 +1016,7 @@ pub fn c14_disable_internet_sharing() -> BashCommand {
         args: vec![
             "defaults".to_string(),
             "write".to_string(),
-            "/Library/Preferences/SystemConfiguration/com.apple.nat".to_string(),
+            crate::root::path("/Library/Preferences/SystemConfiguration/com.apple.nat"),
             "NAT".to_string(),
             "-dict".to_string(),
             "Enabled".to_string(),
@@ -503,6 +1024,8 @@ pub fn c14_disable_internet_sharing() -> BashCommand {
             "0".to_string(),
         ],
         dynamic_args: None, // No dynamic arguments
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
@@ -517,6 +1040,8 @@ pub fn c15_disable_screen_sharing() -> BashCommand {
             "system/com.apple.screensharing".to_string(),
         ],
         dynamic_args: None, // No dynamic arguments
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
@@ -530,6 +1055,8 @@ pub fn c16_disable_printer_sharing() -> BashCommand {
             "--no-share-printers".to_string(),
         ],
         dynamic_args: None, // No dynamic arguments
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
@@ -544,6 +1071,11 @@ pub fn c17_disable_remote_login() -> BashCommand {
             "off".to_string(),
         ],
         dynamic_args: None, // No dynamic arguments
+        write: None,
+        policy: RunPolicy {
+            stdin: Some("yes\n"), // Confirms turning off Remote Login, which ends open SSH sessions
+            ..RunPolicy::default()
+        },
     }
 }
 
@@ -558,6 +1090,8 @@ pub fn c18_disable_dvd_cd_sharing() -> BashCommand {
             "system/com.apple.ODSAgent".to_string(),
         ],
         dynamic_args: None, // No dynamic arguments
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
@@ -572,6 +1106,8 @@ pub fn c19_disable_file_sharing() -> BashCommand {
             "system/com.apple.smbd".to_string(),
         ],
         dynamic_args: None, // No dynamic arguments
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
@@ -586,6 +1122,8 @@ pub fn c20_disable_remote_management() -> BashCommand {
             "-stop".to_string(),
         ],
         dynamic_args: None, // No dynamic arguments
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
@@ -599,27 +1137,12 @@ pub fn c21_disable_content_caching() -> BashCommand {
             "deactivate".to_string(),
         ],
         dynamic_args: None, // No dynamic arguments
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
 pub fn c22_disable_airdrop() -> Vec<BashCommand> {
-    // Fetch the list of users
-    fn fetch_users() -> Vec<String> {
-        let users_dir = "/Users";
-        std::fs::read_dir(users_dir)
-            .expect("Failed to read /Users directory")
-            .filter_map(|entry| entry.ok())
-            .filter_map(|entry| {
-                let name = entry.file_name().into_string().ok()?;
-                if name != "Shared" && name != ".localized" {
-                    Some(name)
-                } else {
-                    None
-                }
-            })
-            .collect()
-    }
-
     // Create BashCommand for each user
     fn create_airdrop_command(username: &str) -> BashCommand {
         BashCommand {
@@ -637,34 +1160,19 @@ pub fn c22_disable_airdrop() -> Vec<BashCommand> {
                 "true".to_string(),
             ],
             dynamic_args: None,
+            write: None,
+            policy: RunPolicy::default(),
         }
     }
 
     // Generate commands for all users
-    fetch_users()
+    crate::users::human()
         .iter()
-        .map(|user| create_airdrop_command(user))
+        .map(|user| create_airdrop_command(&user.name))
         .collect()
 }
 
 pub fn c23_disable_media_sharing() -> Vec<BashCommand> {
-    // Fetch the list of users
-    fn fetch_users() -> Vec<String> {
-        let users_dir = "/Users";
-        std::fs::read_dir(users_dir)
-            .expect("Failed to read /Users directory")
-            .filter_map(|entry| entry.ok())
-            .filter_map(|entry| {
-                let name = entry.file_name().into_string().ok()?;
-                if name != "Shared" && name != ".localized" {
-                    Some(name)
-                } else {
-                    None
-                }
-            })
-            .collect()
-    }
-
     // Create BashCommand for each user
     fn create_media_sharing_command(username: &str) -> BashCommand {
         BashCommand {
@@ -682,34 +1190,19 @@ pub fn c23_disable_media_sharing() -> Vec<BashCommand> {
                 "0".to_string(),
             ],
             dynamic_args: None,
+            write: None,
+            policy: RunPolicy::default(),
         }
     }
 
     // Generate commands for all users
-    fetch_users()
+    crate::users::human()
         .iter()
-        .map(|user| create_media_sharing_command(user))
+        .map(|user| create_media_sharing_command(&user.name))
         .collect()
 }
 
 pub fn c24_disable_airplay_receiver() -> Vec<BashCommand> {
-    // Fetch the list of users
-    fn fetch_users() -> Vec<String> {
-        let users_dir = "/Users";
-        std::fs::read_dir(users_dir)
-            .expect("Failed to read /Users directory")
-            .filter_map(|entry| entry.ok())
-            .filter_map(|entry| {
-                let name = entry.file_name().into_string().ok()?;
-                if name != "Shared" && name != ".localized" {
-                    Some(name)
-                } else {
-                    None
-                }
-            })
-            .collect()
-    }
-
     // Create BashCommand for each user
     fn create_airplay_receiver_command(username: &str) -> BashCommand {
         BashCommand {
@@ -728,13 +1221,15 @@ pub fn c24_disable_airplay_receiver() -> Vec<BashCommand> {
                 "false".to_string(),
             ],
             dynamic_args: None,
+            write: None,
+            policy: RunPolicy::default(),
         }
     }
 
     // Generate commands for all users
-    fetch_users()
+    crate::users::human()
         .iter()
-        .map(|user| create_airplay_receiver_command(user))
+        .map(|user| create_airplay_receiver_command(&user.name))
         .collect()
 }
 
@@ -743,14 +1238,26 @@ pub fn c25_enable_firewall() -> BashCommand {
         name: "c25_enable_firewall",
         description: "2.5.2.1 Ensure Firewall is Enabled.",
         executable: "sudo",
-        args: vec![
-            "/usr/bin/defaults".to_string(),
-            "write".to_string(),
-            "/Library/Preferences/com.apple.alfglobalstate".to_string(),
-            "-int".to_string(),
-            "1".to_string(),
-        ],
+        // Sequoia no longer reads the firewall state from com.apple.alf
+        args: if crate::os::at_least(crate::os::Release::Sequoia) {
+            vec![
+                "/usr/libexec/ApplicationFirewall/socketfilterfw".to_string(),
+                "--setglobalstate".to_string(),
+                "on".to_string(),
+            ]
+        } else {
+            vec![
+                "/usr/bin/defaults".to_string(),
+                "write".to_string(),
+                crate::root::path("/Library/Preferences/com.apple.alf"),
+                "globalstate".to_string(),
+                "-int".to_string(),
+                "1".to_string(),
+            ]
+        },
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
@@ -765,6 +1272,8 @@ pub fn c26_enable_firewall_stealth_mode() -> BashCommand {
             "on".to_string(),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
@@ -776,17 +1285,19 @@ pub fn c27_1_enable_location_services() -> BashCommand {
         args: vec![
             "/usr/bin/defaults".to_string(),
             "write".to_string(),
-            "/var/db/locationd/Library/Preferences/ByHost/com.apple.locationd".to_string(),
+            crate::root::path("/var/db/locationd/Library/Preferences/ByHost/com.apple.locationd"),
             "LocationServicesEnabled".to_string(),
             "-bool".to_string(),
             "true".to_string(),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
-pub fn c27_2_restart_location_services() -> BashCommand {
-    BashCommand {
+pub fn c27_2_restart_location_services() -> PostAction {
+    let command = BashCommand {
         name: "c27_2_restart_location_services",
         description: "2.5.3 Ensure Location Services is Enabled (Part 2).",
         executable: "sudo",
@@ -797,7 +1308,10 @@ pub fn c27_2_restart_location_services() -> BashCommand {
             "system/com.apple.locationd".to_string(),
         ],
         dynamic_args: None,
-    }
+        write: None,
+        policy: RunPolicy::default(),
+    };
+    PostAction { after: &["c27_1"], user: None, command }
 }
 
 pub fn c28_1_disable_diagnostic_data() -> BashCommand {
@@ -808,33 +1322,18 @@ pub fn c28_1_disable_diagnostic_data() -> BashCommand {
         args: vec![
             "/usr/bin/defaults".to_string(),
             "write".to_string(),
-            "/Library/Application Support/CrashReporter/DiagnosticMessagesHistory.plist".to_string(),
+            crate::root::path("/Library/Application Support/CrashReporter/DiagnosticMessagesHistory.plist"),
             "AutoSubmit".to_string(),
             "-bool".to_string(),
             "false".to_string(),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
 pub fn c28_2_disable_diagnostic_data_per_user() -> Vec<BashCommand> {
-    // Fetch users
-    fn fetch_users() -> Vec<String> {
-        let users_dir = "/Users";
-        std::fs::read_dir(users_dir)
-            .expect("Failed to read /Users directory")
-            .filter_map(|entry| entry.ok())
-            .filter_map(|entry| {
-                let name = entry.file_name().into_string().ok()?;
-                if name != "Shared" && name != ".localized" {
-                    Some(name)
-                } else {
-                    None
-                }
-            })
-            .collect()
-    }
-
     // Create commands for each user
     fn create_diagnostic_data_command(username: &str) -> BashCommand {
         BashCommand {
@@ -846,39 +1345,24 @@ pub fn c28_2_disable_diagnostic_data_per_user() -> Vec<BashCommand> {
                 username.to_string(),
                 "/usr/bin/defaults".to_string(),
                 "write".to_string(),
-                format!("/Users/{}/Library/Preferences/com.apple.assistant.support", username),
-                "'Siri Data Sharing Opt-In Status'".to_string(),
+                crate::root::path(&format!("{}/Library/Preferences/com.apple.assistant.support", crate::users::home(username))),
+                "Siri Data Sharing Opt-In Status".to_string(),
                 "-int".to_string(),
                 "2".to_string(),
             ],
             dynamic_args: None,
+            write: None,
+            policy: RunPolicy::default(),
         }
     }
 
-    fetch_users()
+    crate::users::human()
         .iter()
-        .map(|user| create_diagnostic_data_command(user))
+        .map(|user| create_diagnostic_data_command(&user.name))
         .collect()
 }
 
 pub fn c29_enable_limit_ad_tracking() -> Vec<BashCommand> {
-    // Fetch users
-    fn fetch_users() -> Vec<String> {
-        let users_dir = "/Users";
-        std::fs::read_dir(users_dir)
-            .expect("Failed to read /Users directory")
-            .filter_map(|entry| entry.ok())
-            .filter_map(|entry| {
-                let name = entry.file_name().into_string().ok()?;
-                if name != "Shared" && name != ".localized" {
-                    Some(name)
-                } else {
-                    None
-                }
-            })
-            .collect()
-    }
-
     // Create commands for each user
     fn create_ad_tracking_command(username: &str) -> BashCommand {
         BashCommand {
@@ -890,18 +1374,20 @@ pub fn c29_enable_limit_ad_tracking() -> Vec<BashCommand> {
                 username.to_string(),
                 "/usr/bin/defaults".to_string(),
                 "write".to_string(),
-                format!("/Users/{}/Library/Preferences/com.apple.Adlib.plist", username),
+                crate::root::path(&format!("{}/Library/Preferences/com.apple.Adlib.plist", crate::users::home(username))),
                 "allowApplePersonalizedAdvertising".to_string(),
                 "-bool".to_string(),
                 "false".to_string(),
             ],
             dynamic_args: None,
+            write: None,
+            policy: RunPolicy::default(),
         }
     }
 
-    fetch_users()
+    crate::users::human()
         .iter()
-        .map(|user| create_ad_tracking_command(user))
+        .map(|user| create_ad_tracking_command(&user.name))
         .collect()
 }
 
@@ -915,6 +1401,8 @@ pub fn c30_enable_gatekeeper() -> BashCommand {
             "--master-enable".to_string(),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
@@ -926,11 +1414,13 @@ pub fn c31_enable_custom_login_message() -> BashCommand {
         args: vec![
             "/usr/bin/defaults".to_string(),
             "write".to_string(),
-            "/Library/Preferences/com.apple.loginwindow".to_string(),
+            crate::root::path("/Library/Preferences/com.apple.loginwindow"),
             "LoginwindowText".to_string(),
-            "Access for authorized personnel only. \\nThis system is being monitored.".to_string(),
+            crate::config::current().login_window_text.clone(),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
@@ -944,13 +1434,15 @@ pub fn c32_ensure_admin_password_for_system_preferences() -> BashCommand {
             r#"/usr/bin/sudo /usr/bin/security authorizationdb read system.preferences > /$HOME/Desktop/system.preferences.plist 2>&1 && /usr/bin/sudo plutil -replace shared -bool false "$HOME/Desktop/system.preferences.plist" && /usr/bin/sudo /usr/bin/security authorizationdb write system.preferences < /$HOME/Desktop/system.preferences.plist 2>&1"#.to_string(),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
 pub fn c33_require_password_to_wake() -> BashCommand {
     BashCommand {
         name: "c33_require_password_to_wake",
-        description: "2.5.9 Ensure a Password is Required to Wake the Computer From Sleep or Screen Saver is Enabled.",
+        description: "2.5.10 Ensure a Password is Required to Wake the Computer From Sleep or Screen Saver is Enabled.",
         executable: "sudo",
         args: vec![
             "/usr/sbin/sysadminctl".to_string(),
@@ -959,6 +1451,8 @@ pub fn c33_require_password_to_wake() -> BashCommand {
             "seconds".to_string(),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
@@ -987,6 +1481,8 @@ fi
             "#.to_string(),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
@@ -1002,30 +1498,44 @@ pub fn c35_disable_wake_for_network_access() -> BashCommand {
             "0".to_string(),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
 pub fn c36_disable_powernap_based_on_cpu() -> BashCommand {
     // Identifica o modelo de CPU
-    let output = std::process::Command::new("/usr/sbin/sysctl")
-        .arg("-n")
-        .arg("machdep.cpu.brand_string")
-        .output()
-        .expect("Failed to retrieve CPU model");
+    let output = match crate::runner::run("/usr/sbin/sysctl", &["-n", "machdep.cpu.brand_string"]) {
+        Ok(output) if output.success() => output,
+        failed => {
+            let reason = match failed {
+                Err(error) => error.to_string(),
+                Ok(output) if output.timed_out => "sysctl was stopped at the timeout".to_string(),
+                Ok(output) => format!("sysctl exited with {:?} {}", output.code, output.stderr.trim()),
+            };
+            return error(
+                "c36_disable_powernap_for_intel",
+                "2.8.3 Ensure Power Nap is Disabled for Intel Macs.",
+                format!("Failed to retrieve the CPU model: {}", reason),
+            );
+        }
+    };
 
-    let cpu_model = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    let cpu_model = output.stdout.trim().to_string();
 
     // Lógica de decisão com base no modelo de CPU
     if cpu_model.contains("Apple") {
-        return BashCommand {
+        BashCommand {
             name: "c36_disable_powernap_for_intel",
             description: "2.8.3 Ensure Power Nap is Disabled for Intel Macs.",
             executable: "echo",
             args: vec!["No action required for Apple CPUs.".to_string()],
             dynamic_args: None,
+            write: None,
+            policy: RunPolicy::default(),
         }
     } else if cpu_model.contains("Intel") {
-        return BashCommand {
+        BashCommand {
             name: "c36_disable_powernap_for_intel",
             description: "2.8.3 Ensure Power Nap is Disabled for Intel Macs.",
             executable: "sudo",
@@ -1036,14 +1546,18 @@ pub fn c36_disable_powernap_based_on_cpu() -> BashCommand {
                 "0".to_string(),
             ],
             dynamic_args: None,
+            write: None,
+            policy: RunPolicy::default(),
         }
     } else {
-        return BashCommand {
+        BashCommand {
             name: "c36_disable_powernap_for_intel",
             description: "2.8.3 Ensure Power Nap is Disabled for Intel Macs.",
             executable: "echo",
             args: vec!["No action required for Apple CPUs.".to_string()],
             dynamic_args: None,
+            write: None,
+            policy: RunPolicy::default(),
         }
     }
 }
@@ -1060,240 +1574,198 @@ pub fn c37_enable_security_auditing() -> BashCommand {
             "/System/Library/LaunchDaemons/com.apple.auditd.plist".to_string(),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
 pub fn c38_configure_auditing_flags() -> BashCommand {
-    let file_path = "/etc/security/audit_control";
+    let name = "c38_configure_auditing_flags";
+    let description = "3.2 Ensure Security Auditing Flags for User-Attributable Events Are Configured.";
+    let file_path = crate::root::path(crate::audit_control::PATH);
 
-    // Abrir o arquivo e ler o conteúdo
-    let content = std::fs::read_to_string(file_path).expect("Failed to read the file.");
-
-    // Procurar a linha que começa com "flags:"
-    let target_line = content
-        .lines()
-        .find(|line| line.starts_with("flags:"))
-        .map(|line| line.to_string());
-
-    let target_line = match target_line {
-        Some(line) => line,
-        None => {
-            return BashCommand {
-                name: "c38_configure_auditing_flags",
-                description: "3.2 Ensure Security Auditing Flags for User-Attributable Events Are Configured.",
-                executable: "echo",
-                args: vec!["Target line starting with 'flags:' not found in the file.".to_string()],
-                dynamic_args: None,
-            }; // Nada a fazer se as flags já estão configuradas
-        }
+    let mut audit_control = match crate::audit_control::AuditControl::load(&file_path) {
+        Ok(audit_control) => audit_control,
+        Err(text) => return error(name, description, text),
     };
 
-    // Nova configuração de flags
-    let required_flags = "flags:-fm,ad,-ex,aa,-fr,lo,-fw";
+    // Configured flags that are missing are added, flags set by the site are kept
+    let mut flags: Vec<String> = audit_control.flags().iter().map(|flag| flag.to_string()).collect();
+    let missing: Vec<String> = crate::config::current()
+        .audit_flags
+        .split(',')
+        .map(|flag| flag.trim().to_string())
+        .filter(|flag| !flags.contains(flag))
+        .collect();
 
-    // Verificar se as flags já estão configuradas corretamente
-    if target_line == required_flags {
+    if missing.is_empty() {
         return BashCommand {
-            name: "c38_configure_auditing_flags",
-            description: "3.2 Ensure Security Auditing Flags for User-Attributable Events Are Configured.",
+            name,
+            description,
             executable: "echo",
             args: vec!["The auditing flags are already configured correctly.".to_string()],
             dynamic_args: None,
-        }; // Nada a fazer se as flags já estão configuradas
+            write: None,
+            policy: RunPolicy::default(),
+        };
     }
 
-    // Retornar o comando BashCommand
-    BashCommand {
-        name: "c38_configure_auditing_flags",
-        description: "3.2 Ensure Security Auditing Flags for User-Attributable Events Are Configured.",
-        executable: "sudo",
-        args: vec![
-            "sed".to_string(),
-            "-i".to_string(),
-            "''".to_string(),
-            format!("s/^flags:.*$/{}/", required_flags),
-            file_path.to_string(),
-        ],
-        dynamic_args: None,
-    }
+    flags.extend(missing);
+    audit_control.set("flags", &flags.join(","));
+    file_write(name, description, file_path, audit_control.render())
 }
 
 pub fn c39_configure_install_log_retention() -> BashCommand {
-    let file_path = "/etc/asl/com.apple.install";
-
-    // Abrir o arquivo e ler o conteúdo
-    let content = std::fs::read_to_string(file_path).expect("Failed to read the file.");
-
-    // Procurar a linha que contém "* file /var/log/install.log"
-    let target_line = content
-        .lines()
-        .find(|line| line.contains("* file /var/log/install.log"))
-        .map(|line| line.to_string());
-
-    let target_line = match target_line {
-        Some(line) => line,
-        None => {
-            return BashCommand {
-                name: "c39_configure_install_log_retention",
-                description: "3.3 Ensure install.log is Retained for 365 or More Days and No Maximum Size.",
-                executable: "echo",
-                args: vec!["Target line not found in the file.".to_string()],
-                dynamic_args: None,
-            }; // Nada a fazer se as flags já estão configuradas
-        }
+    use crate::asl::{AslConfig, INSTALL_CONFIG, INSTALL_LOG, REQUIRED_TTL};
+
+    let name = "c39_configure_install_log_retention";
+    let description = "3.3 Ensure install.log is Retained for 365 or More Days and No Maximum Size.";
+    let file_path = crate::root::path(INSTALL_CONFIG);
+    let message = |text: String| BashCommand {
+        name,
+        description,
+        executable: "echo",
+        args: vec![text],
+        dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     };
 
-    // Verificar as flags existentes
-    let required_flags = vec!["rotate=seq", "compress", "file_max=50M", "size_only", "ttl=365"];
-    let mut missing_flags = vec![];
+    let mut config = match AslConfig::load(&file_path) {
+        Ok(config) => config,
+        Err(text) => return error(name, description, text),
+    };
+    let rule = match config.rule_mut(INSTALL_LOG) {
+        Some(rule) => rule,
+        None => return message(format!("No {} rule in {}.", INSTALL_LOG, INSTALL_CONFIG)),
+    };
 
-    for flag in &required_flags {
-        if !target_line.contains(flag) {
-            missing_flags.push(flag.to_string());
-        }
+    // A year of logs and no limit on their total size
+    if rule.ttl().is_none_or(|days| days < REQUIRED_TTL) {
+        rule.set("ttl", Some(&REQUIRED_TTL.to_string()));
     }
+    rule.remove("all_max");
 
-    if missing_flags.is_empty() {
-        println!("All required flags are already present.");
-        return BashCommand {
-            name: "c39_configure_install_log_retention",
-            description: "3.3 Ensure install.log is Retained for 365 or More Days and No Maximum Size.",
-            executable: "echo",
-            args: vec!["All required flags are already present.".to_string()],
-            dynamic_args: None,
-        }; // Nada a fazer se as flags já estão configuradas
+    // Rotation options of the benchmark remediation, added when missing; values set by the
+    // site are kept (the first one when an option is repeated)
+    for (key, default) in [("rotate", Some("seq")), ("compress", None), ("file_max", Some("50M")), ("size_only", None)] {
+        let current = rule.option(key).map(|value| value.map(|value| value.to_string()));
+        match current {
+            Some(value) => rule.set(key, value.as_deref()),
+            None => rule.set(key, default),
+        }
     }
 
-    // Adicionar flags ausentes à linha
-    let mut updated_line = target_line.clone();
-    for flag in &missing_flags {
-        updated_line = format!("{} {}", updated_line, flag);
-    }
-
-    // Retornar o comando BashCommand
-    BashCommand {
-        name: "c39_configure_install_log_retention",
-        description: "3.3 Ensure install.log is Retained for 365 or More Days and No Maximum Size.",
-        executable: "sudo",
-        args: vec![
-            "sed".to_string(),
-            "-i".to_string(),
-            "''".to_string(),
-            format!(
-                "'s|^.*file /var/log/install.log.*$|{}|'",
-                updated_line
-            ),
-            file_path.to_string(),
-        ],
-        dynamic_args: None,
+    let content = config.render();
+    if std::fs::read_to_string(&file_path).is_ok_and(|current| current == content) {
+        return message("install.log retention is already configured.".to_string());
     }
+    file_write(name, description, file_path, content)
 }
 
 pub fn c40_configure_auditing_retention() -> BashCommand {
-    let file_path = "/etc/security/audit_control";
-
-    // Abrir o arquivo e ler o conteúdo
-    let content = std::fs::read_to_string(file_path).expect("Failed to read the file.");
-
-    // Procurar a linha que começa com "expire-after:"
-    let target_line = content
-        .lines()
-        .find(|line| line.starts_with("expire-after:"))
-        .map(|line| line.to_string());
-
-    let required_setting = "expire-after:60d";
-
-    if let Some(line) = target_line {
-        if line == required_setting {
-            return BashCommand {
-                name: "c40_configure_auditing_retention",
-                description: "3.2 Ensure Security Auditing Retention is Enabled.",
-                executable: "echo",
-                args: vec!["Auditing retention already configured.".to_string()],
-                dynamic_args: None,
-            };
-        }
-    }
+    let name = "c40_configure_auditing_retention";
+    let description = "3.4 Ensure Security Auditing Retention is Enabled.";
+    let file_path = crate::root::path(crate::audit_control::PATH);
+    let required = &crate::config::current().audit_expire_after;
+
+    let mut audit_control = match crate::audit_control::AuditControl::load(&file_path) {
+        Ok(audit_control) => audit_control,
+        Err(text) => return error(name, description, text),
+    };
 
-    BashCommand {
-        name: "c40_configure_auditing_retention",
-        description: "3.2 Ensure Security Auditing Retention is Enabled.",
-        executable: "sudo",
-        args: vec![
-            "sed".to_string(),
-            "-i".to_string(),
-            "''".to_string(),
-            "s|^expire-after:.*$|expire-after:60d|".to_string(),
-            file_path.to_string(),
-        ],
-        dynamic_args: None,
+    // A value keeping at least what the benchmark asks for (e.g. "1y" or "90d OR 10G") is kept
+    if audit_control
+        .get("expire-after")
+        .is_some_and(|value| crate::audit_control::expire_after_error(value).is_none())
+    {
+        return BashCommand {
+            name,
+            description,
+            executable: "echo",
+            args: vec!["Auditing retention already configured.".to_string()],
+            dynamic_args: None,
+            write: None,
+            policy: RunPolicy::default(),
+        };
     }
+
+    audit_control.set("expire-after", required);
+    file_write(name, description, file_path, audit_control.render())
 }
 
 pub fn c41_1_chown_audit_control() -> BashCommand {
     BashCommand {
         name: "c41_1_chown_audit_control",
-        description: "Ensure audit_control is owned by root:wheel.",
+        description: "3.5 Ensure audit_control is owned by root:wheel.",
         executable: "sudo",
         args: vec![
             "/usr/sbin/chown".to_string(),
             "-R".to_string(),
             "root:wheel".to_string(),
-            "/etc/security/audit_control".to_string(),
+            crate::root::path("/etc/security/audit_control"),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
 pub fn c41_2_chmod_audit_control() -> BashCommand {
     BashCommand {
         name: "c41_2_chmod_audit_control",
-        description: "Ensure audit_control permissions are set to prevent access by others.",
+        description: "3.5 Ensure audit_control permissions are set to prevent access by others.",
         executable: "sudo",
         args: vec![
             "/bin/chmod".to_string(),
             "-R".to_string(),
             "o-rw".to_string(),
-            "/etc/security/audit_control".to_string(),
+            crate::root::path("/etc/security/audit_control"),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
 pub fn c41_3_chown_var_audit() -> BashCommand {
     BashCommand {
         name: "c41_3_chown_var_audit",
-        description: "Ensure /var/audit is owned by root:wheel.",
+        description: "3.5 Ensure /var/audit is owned by root:wheel.",
         executable: "sudo",
         args: vec![
             "/usr/sbin/chown".to_string(),
             "-R".to_string(),
             "root:wheel".to_string(),
-            "/var/audit/".to_string(),
+            crate::root::path("/var/audit/"),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
 pub fn c41_4_chmod_var_audit() -> BashCommand {
     BashCommand {
         name: "c41_4_chmod_var_audit",
-        description: "Ensure /var/audit permissions are set to prevent access by others.",
+        description: "3.5 Ensure /var/audit permissions are set to prevent access by others.",
         executable: "sudo",
         args: vec![
             "/bin/chmod".to_string(),
             "-R".to_string(),
             "o-rw".to_string(),
-            "/var/audit/".to_string(),
+            crate::root::path("/var/audit/"),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
 pub fn c42_1_enable_logging_mode() -> BashCommand {
     BashCommand {
         name: "c42_1_enable_logging_mode",
-        description: "Ensure Logging Mode is Enabled.",
+        description: "3.6 Ensure Firewall Logging Mode is Enabled.",
         executable: "sudo",
         args: vec![
             "/usr/libexec/ApplicationFirewall/socketfilterfw".to_string(),
@@ -1301,13 +1773,15 @@ pub fn c42_1_enable_logging_mode() -> BashCommand {
             "on".to_string(),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
 pub fn c42_2_configure_logging_detail() -> BashCommand {
     BashCommand {
         name: "c42_2_configure_logging_detail",
-        description: "Ensure Logging is Configured to Detail Mode.",
+        description: "3.6 Ensure Firewall Logging is Configured to Detail Mode.",
         executable: "sudo",
         args: vec![
             "/usr/libexec/ApplicationFirewall/socketfilterfw".to_string(),
@@ -1315,6 +1789,8 @@ pub fn c42_2_configure_logging_detail() -> BashCommand {
             "detail".to_string(),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
@@ -1326,12 +1802,14 @@ pub fn c43_disable_bonjour_advertising() -> BashCommand {
         args: vec![
             "/usr/bin/defaults".to_string(),
             "write".to_string(),
-            "/Library/Preferences/com.apple.mDNSResponder.plist".to_string(),
+            crate::root::path("/Library/Preferences/com.apple.mDNSResponder.plist"),
             "NoMulticastAdvertisements".to_string(),
             "-bool".to_string(),
             "true".to_string(),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
@@ -1340,13 +1818,24 @@ pub fn c44_disable_http_server() -> BashCommand {
         name: "c44_disable_http_server",
         description: "4.2 Ensure HTTP Server is Disabled.",
         executable: "sudo",
-        args: vec![
-            "/bin/launchctl".to_string(),
-            "unload".to_string(),
-            "-w".to_string(),
-            "/System/Library/LaunchDaemons/org.apache.httpd.plist".to_string(),
-        ],
+        // From Ventura the daemon is disabled rather than unloaded
+        args: if crate::os::at_least(crate::os::Release::Ventura) {
+            vec![
+                "/bin/launchctl".to_string(),
+                "disable".to_string(),
+                "system/org.apache.httpd".to_string(),
+            ]
+        } else {
+            vec![
+                "/bin/launchctl".to_string(),
+                "unload".to_string(),
+                "-w".to_string(),
+                "/System/Library/LaunchDaemons/org.apache.httpd.plist".to_string(),
+            ]
+        },
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
@@ -1361,32 +1850,17 @@ pub fn c45_disable_nfs_server() -> BashCommand {
             "system/com.apple.nfsd".to_string(),
             "&&".to_string(),
             "/bin/rm".to_string(),
-            "/etc/exports".to_string(),
+            crate::root::path("/etc/exports"),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
 pub fn c46_secure_home_folders() -> Vec<BashCommand> {
-    // Buscar os usuários
-    fn fetch_users() -> Vec<String> {
-        let users_dir = "/Users";
-        std::fs::read_dir(users_dir)
-            .expect("Failed to read /Users directory")
-            .filter_map(|entry| entry.ok())
-            .filter_map(|entry| {
-                let name = entry.file_name().into_string().ok()?;
-                if name != "Shared" && name != ".localized" {
-                    Some(name)
-                } else {
-                    None
-                }
-            })
-            .collect()
-    }
-
     // Criar comandos para cada usuário
-    fetch_users()
+    crate::users::human()
         .iter()
         .map(|user| BashCommand {
             name: "c46_secure_home_folder_for_everyuser",
@@ -1396,9 +1870,11 @@ pub fn c46_secure_home_folders() -> Vec<BashCommand> {
                 "/bin/chmod".to_string(),
                 "-R".to_string(),
                 "og-rwx".to_string(),
-                format!("/Users/{}", user),
+                crate::root::path(&user.home),
             ],
             dynamic_args: None,
+            write: None,
+            policy: RunPolicy::default(),
         })
         .collect()
 }
@@ -1413,54 +1889,50 @@ pub fn c47_enable_amfi() -> BashCommand {
             "boot-args=".to_string(),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
 pub fn c47_1_enable_library_validation() -> BashCommand {
     BashCommand {
         name: "c47_1_enable_library_validation",
-        description: "Ensure Library Validation is Enabled.",
+        description: "5.1.4 Ensure Library Validation is Enabled.",
         executable: "sudo",
         args: vec![
             "/usr/bin/defaults".to_string(),
             "write".to_string(),
-            "/Library/Preferences/com.apple.security.libraryvalidation.plist".to_string(),
+            crate::root::path("/Library/Preferences/com.apple.security.libraryvalidation.plist"),
             "DisableLibraryValidation".to_string(),
             "-bool".to_string(),
             "false".to_string(),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
 pub fn c48_ensure_permissions_for_system_apps() -> Vec<BashCommand> {
-    // Subfunção para buscar aplicativos no diretório /Applications
-    fn fetch_apps() -> Vec<String> {
-        use std::process::Command;
+    let name = "c48_fix_permissions_for_every_systemdwide_apps";
+    let description = "5.1.5 Ensure Appropriate Permissions Are Enabled for System Wide Applications.";
 
-        let output = Command::new("find")
-            .args(["/Applications", "-iname", "*.app", "-type", "d", "-perm", "-2"])
-            .output()
-            .expect("Failed to find applications with world-writable permissions.");
-
-        let apps = String::from_utf8_lossy(&output.stdout)
-            .lines()
-            .map(|line| line.to_string())
-            .collect::<Vec<String>>();
-
-        if apps.is_empty() {
-            println!("No world-writable applications found.");
+    // Subfunção para buscar aplicativos no diretório /Applications
+    let apps = match find_paths(&[&crate::root::path("/Applications"), "-iname", "*.app", "-type", "d", "-perm", "-2"]) {
+        Ok(apps) => apps,
+        Err(text) => {
+            return vec![error(name, description, format!("Failed to find applications with world-writable permissions: {}", text))]
         }
-
-        apps
+    };
+    if apps.is_empty() {
+        println!("No world-writable applications found.");
     }
 
     // Criar comandos para cada aplicativo
-    fetch_apps()
-        .iter()
+    apps.iter()
         .map(|app| BashCommand {
-            name: "c48_fix_permissions_for_every_systemdwide_apps",
-            description: "Ensure Appropriate Permissions Are Enabled for System Wide Applications.",
+            name,
+            description,
             executable: "sudo",
             args: vec![
                 "/bin/chmod".to_string(),
@@ -1469,44 +1941,38 @@ pub fn c48_ensure_permissions_for_system_apps() -> Vec<BashCommand> {
                 app.to_string(),
             ],
             dynamic_args: None,
+            write: None,
+            policy: RunPolicy::default(),
         })
         .collect()
 }
 
 pub fn c49_fix_world_writable_system_files() -> Vec<BashCommand> {
+    let name = "c49_fix_permissions_for_every_world_writable_system_files";
+    let description = "5.1.6 Ensure No World Writable Files Exist in the System Folder.";
+
     // Buscar diretórios com permissões inadequadas no System Folder
-    fn find_world_writable_system_files() -> Vec<String> {
-        use std::process::Command;
-
-        let output = Command::new("find")
-            .args([
-                "/System/Volumes/Data/System",
-                "-type",
-                "d",
-                "-perm",
-                "-2",
-                "-exec",
-                "grep",
-                "-v",
-                "Drop Box",
-                "{}",
-                ";",
-            ])
-            .output()
-            .expect("Failed to find world-writable files in the System folder.");
-
-        String::from_utf8_lossy(&output.stdout)
-            .lines()
-            .map(|line| line.to_string())
-            .collect()
-    }
+    let paths = match find_paths(&[
+        &crate::root::path("/System/Volumes/Data/System"),
+        "-type",
+        "d",
+        "-perm",
+        "-2",
+        "-not",
+        "-path",
+        "*Drop Box*", // Shared drop boxes are world-writable by design
+        "-print",
+    ]) {
+        Ok(paths) => paths,
+        Err(text) => return vec![error(name, description, format!("Failed to find world-writable files in the System folder: {}", text))],
+    };
 
     // Criar comandos para corrigir permissões
-    find_world_writable_system_files()
+    paths
         .iter()
         .map(|path| BashCommand {
-            name: "c49_fix_permissions_for_every_world_writable_system_files",
-            description: "5.1.6 Ensure No World Writable Files Exist in the System Folder.",
+            name,
+            description,
             executable: "sudo",
             args: vec![
                 "/bin/chmod".to_string(),
@@ -1515,46 +1981,41 @@ pub fn c49_fix_world_writable_system_files() -> Vec<BashCommand> {
                 path.clone(),
             ],
             dynamic_args: None,
+            write: None,
+            policy: RunPolicy::default(),
         })
         .collect()
 }
 
 pub fn c50_fix_world_writable_library_files() -> Vec<BashCommand> {
+    let name = "c50_fix_permissions_every_world_writable_library_files";
+    let description = "5.1.7 Ensure No World Writable Files Exist in the Library Folder.";
+
     // Buscar diretórios com permissões inadequadas no Library Folder
-    fn find_world_writable_library_files() -> Vec<String> {
-        use std::process::Command;
-
-        let output = Command::new("find")
-            .args([
-                "/System/Volumes/Data/Library",
-                "-type",
-                "d",
-                "-perm",
-                "-2",
-                "-exec",
-                "grep",
-                "-v",
-                "Caches",
-                "-v",
-                "/Preferences/Audio/Data",
-                "{}",
-                ";",
-            ])
-            .output()
-            .expect("Failed to find world-writable files in the Library folder.");
-
-        String::from_utf8_lossy(&output.stdout)
-            .lines()
-            .map(|line| line.to_string())
-            .collect()
-    }
+    let paths = match find_paths(&[
+        &crate::root::path("/System/Volumes/Data/Library"),
+        "-type",
+        "d",
+        "-perm",
+        "-2",
+        "-not",
+        "-path",
+        "*Caches*", // Left out by the benchmark, like the audio data below
+        "-not",
+        "-path",
+        "*/Preferences/Audio/Data*",
+        "-print",
+    ]) {
+        Ok(paths) => paths,
+        Err(text) => return vec![error(name, description, format!("Failed to find world-writable files in the Library folder: {}", text))],
+    };
 
     // Criar comandos para corrigir permissões
-    find_world_writable_library_files()
+    paths
         .iter()
         .map(|path| BashCommand {
-            name: "c50_fix_permissions_every_world_writable_library_files",
-            description: "5.1.7 Ensure No World Writable Files Exist in the Library Folder.",
+            name,
+            description,
             executable: "sudo",
             args: vec![
                 "/bin/chmod".to_string(),
@@ -1563,6 +2024,8 @@ pub fn c50_fix_world_writable_library_files() -> Vec<BashCommand> {
                 path.clone(),
             ],
             dynamic_args: None,
+            write: None,
+            policy: RunPolicy::default(),
         })
         .collect()
 }
@@ -1577,25 +2040,29 @@ pub fn c51_configure_password_account_lockout() -> BashCommand {
             "-n".to_string(),
             "/Local/Default".to_string(),
             "-setglobalpolicy".to_string(),
-            "maxFailedLoginAttempts=5".to_string(),
+            format!("maxFailedLoginAttempts={}", crate::config::current().max_failed_login_attempts),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
 pub fn c52_configure_password_min_length() -> BashCommand {
     BashCommand {
         name: "c52_configure_password_min_length",
-        description: "5.2.1 Ensure Password Minimum Length is Configured.",
+        description: "5.2.2 Ensure Password Minimum Length is Configured.",
         executable: "sudo",
         args: vec![
             "/usr/bin/pwpolicy".to_string(),
             "-n".to_string(),
             "/Local/Default".to_string(),
             "-setglobalpolicy".to_string(),
-            "minChars=12".to_string(),
+            format!("minChars={}", crate::config::current().min_password_length),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
@@ -1612,6 +2079,8 @@ pub fn c53_configure_password_requires_alpha() -> BashCommand {
             "requiresAlpha=1".to_string(),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
@@ -1628,6 +2097,8 @@ pub fn c54_configure_password_requires_numeric() -> BashCommand {
             "requiresNumeric=2".to_string(),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
@@ -1644,6 +2115,8 @@ pub fn c55_configure_password_requires_symbol() -> BashCommand {
             "requiresSymbol=1".to_string(),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
@@ -1660,6 +2133,8 @@ pub fn c56_configure_password_requires_mixed_case() -> BashCommand {
             "requiresMixedCase=1".to_string(),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
@@ -1676,6 +2151,8 @@ pub fn c57_configure_password_age() -> BashCommand {
             "maxMinutesUntilChangePassword=259200".to_string(),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
@@ -1689,23 +2166,33 @@ pub fn c58_configure_password_history() -> BashCommand {
             "-n".to_string(),
             "/Local/Default".to_string(),
             "-setglobalpolicy".to_string(),
-            "usingHistory=15".to_string(),
+            format!("usingHistory={}", crate::config::current().password_history),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
 pub fn c59_configure_sudo_settings() -> BashCommand {
-    BashCommand {
-        name: "c59_configure_sudo_settings",
-        description: "Ensure Sudo Timeout Period and Separate Timestamp are Configured.",
-        executable: "sh",
-        args: vec![
-            "-c".to_string(),
-            "{ echo 'Defaults timestamp_timeout=0'; echo 'Defaults timestamp_type=tty'; } | sudo tee /etc/sudoers.d/10_cissudoconfiguration > /dev/null".to_string(),
-        ],
-        dynamic_args: None,
+    let name = "c59_configure_sudo_settings";
+    let description = "5.3 Ensure Sudo Timeout Period and Separate Timestamp are Configured.";
+    let file_path = crate::root::path(crate::sudoers::CIS_DROP_IN);
+
+    if crate::sudoers::drop_in_problem(&file_path, crate::sudoers::CIS_SETTINGS).is_none() {
+        return BashCommand {
+            name,
+            description,
+            executable: "echo",
+            args: vec!["The sudo settings are already configured.".to_string()],
+            dynamic_args: None,
+            write: None,
+            policy: RunPolicy::default(),
+        };
     }
+
+    // Checked with visudo before it replaces anything, then installed 0440 root:wheel
+    file_command(name, description, crate::sudoers::drop_in(file_path, crate::sudoers::CIS_SETTINGS))
 }
 
 pub fn c60_update_progress() -> BashCommand {
@@ -1715,6 +2202,8 @@ pub fn c60_update_progress() -> BashCommand {
         executable: "echo",
         args: vec!["Progress updated.".to_string()],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
@@ -1728,6 +2217,8 @@ pub fn c61_disable_root_account() -> BashCommand {
             "-d".to_string(),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
@@ -1739,10 +2230,12 @@ pub fn c62_disable_automatic_login() -> BashCommand {
         args: vec![
             "/usr/bin/defaults".to_string(),
             "delete".to_string(),
-            "/Library/Preferences/com.apple.loginwindow".to_string(),
+            crate::root::path("/Library/Preferences/com.apple.loginwindow"),
             "autoLoginUser".to_string(),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
@@ -1759,87 +2252,61 @@ pub fn c63_prevent_admin_login_to_locked_session() -> BashCommand {
             "use-login-window-ui".to_string(),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
 pub fn c63_1_disable_fast_user_switching() -> BashCommand {
     BashCommand {
         name: "c63_1_disable_fast_user_switching",
-        description: "Ensure Fast User Switching is Disabled.",
+        description: "5.10 Ensure Fast User Switching is Disabled.",
         executable: "sudo",
         args: vec![
             "/usr/bin/defaults".to_string(),
             "write".to_string(),
-            "/Library/Preferences/.GlobalPreferences".to_string(),
+            crate::root::path("/Library/Preferences/.GlobalPreferences"),
             "MultipleSessionEnabled".to_string(),
             "-bool".to_string(),
             "false".to_string(),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
 pub fn c64_remove_password_hints() -> Vec<BashCommand> {
-    fn fetch_users() -> Vec<String> {
-        let users_dir = "/Users";
-        std::fs::read_dir(users_dir)
-            .expect("Failed to read /Users directory")
-            .filter_map(|entry| entry.ok())
-            .filter_map(|entry| {
-                let name = entry.file_name().into_string().ok()?;
-                if name != "Shared" && name != ".localized" {
-                    Some(name)
-                } else {
-                    None
-                }
-            })
-            .collect()
-    }
-
-    fetch_users()
+    crate::users::human()
         .iter()
         .map(|user| BashCommand {
             name: "c64_remove_password_hint_for_everyuser",
-            description: "Ensure User Accounts Do Not Have a Password Hint.",
+            description: "5.11 Ensure User Accounts Do Not Have a Password Hint.",
             executable: "sudo",
             args: vec![
                 "/usr/bin/dscl".to_string(),
                 ".".to_string(),
                 "-delete".to_string(),
-                format!("/Users/{}", user),
+                crate::root::path(&format!("/Users/{}", user.name)), // Directory record, not the home folder
                 "hint".to_string(),
             ],
             dynamic_args: None,
+            write: None,
+            policy: RunPolicy::default(),
         })
         .collect()
 }
 
 pub fn c65_enable_secure_keyboard_entry() -> Vec<BashCommand> {
-    fn fetch_users() -> Vec<String> {
-        let users_dir = "/Users";
-        std::fs::read_dir(users_dir)
-            .expect("Failed to read /Users directory")
-            .filter_map(|entry| entry.ok())
-            .filter_map(|entry| {
-                let name = entry.file_name().into_string().ok()?;
-                if name != "Shared" && name != ".localized" {
-                    Some(name)
-                } else {
-                    None
-                }
-            })
-            .collect()
-    }
-
-    fetch_users()
+    crate::users::human()
         .iter()
         .map(|user| BashCommand {
             name: "c65_enable_secure_keyboard_entry_for_everyuser",
-            description: "Ensure Secure Keyboard Entry in Terminal.app is Enabled.",
+            description: "2.10 Ensure Secure Keyboard Entry in Terminal.app is Enabled.",
             executable: "sudo",
             args: vec![
                 "-u".to_string(),
-                user.to_string(),
+                user.name.clone(),
                 "/usr/bin/defaults".to_string(),
                 "write".to_string(),
                 "-app".to_string(),
@@ -1849,6 +2316,8 @@ pub fn c65_enable_secure_keyboard_entry() -> Vec<BashCommand> {
                 "true".to_string(),
             ],
             dynamic_args: None,
+            write: None,
+            policy: RunPolicy::default(),
         })
         .collect()
 }
@@ -1856,58 +2325,64 @@ pub fn c65_enable_secure_keyboard_entry() -> Vec<BashCommand> {
 pub fn c66_enable_login_name_password_display() -> BashCommand {
     BashCommand {
         name: "c66_enable_login_name_password_display",
-        description: "Ensure Login Windows Displays as Name and Password is Enabled.",
+        description: "6.1.1 Ensure Login Windows Displays as Name and Password is Enabled.",
         executable: "sudo",
         args: vec![
             "/usr/bin/defaults".to_string(),
             "write".to_string(),
-            "/Library/Preferences/com.apple.loginwindow".to_string(),
+            crate::root::path("/Library/Preferences/com.apple.loginwindow"),
             "SHOWFULLNAME".to_string(),
             "-bool".to_string(),
             "true".to_string(),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
 pub fn c67_disable_password_hints() -> BashCommand {
     BashCommand {
         name: "c67_disable_password_hints",
-        description: "Ensure Show Password Hints is Disabled.",
+        description: "6.1.2 Ensure Show Password Hints is Disabled.",
         executable: "sudo",
         args: vec![
             "/usr/bin/defaults".to_string(),
             "write".to_string(),
-            "/Library/Preferences/com.apple.loginwindow".to_string(),
+            crate::root::path("/Library/Preferences/com.apple.loginwindow"),
             "RetriesUntilHint".to_string(),
             "-int".to_string(),
             "0".to_string(),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
 pub fn c68_disable_guest_account() -> BashCommand {
     BashCommand {
         name: "c68_disable_guest_account",
-        description: "Ensure Guest Account is Disabled.",
+        description: "6.1.3 Ensure Guest Account is Disabled.",
         executable: "sudo",
         args: vec![
             "/usr/bin/defaults".to_string(),
             "write".to_string(),
-            "/Library/Preferences/com.apple.loginwindow".to_string(),
+            crate::root::path("/Library/Preferences/com.apple.loginwindow"),
             "GuestEnabled".to_string(),
             "-bool".to_string(),
             "false".to_string(),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
 pub fn c69_disable_guest_shared_folders_access() -> BashCommand {
     BashCommand {
         name: "c69_disable_guest_shared_folders_access",
-        description: "Ensure Guest Access to Shared Folders is Disabled.",
+        description: "6.1.4 Ensure Guest Access to Shared Folders is Disabled.",
         executable: "sudo",
         args: vec![
             "/usr/sbin/sysadminctl".to_string(),
@@ -1915,6 +2390,8 @@ pub fn c69_disable_guest_shared_folders_access() -> BashCommand {
             "off".to_string(),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
@@ -1926,29 +2403,15 @@ pub fn c70_remove_guest_home_folder() -> BashCommand {
         args: vec![
             "/bin/rm".to_string(),
             "-R".to_string(),
-            "/Users/Guest".to_string(),
+            crate::root::path("/Users/Guest"),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
 pub fn c71_enable_show_all_filename_extensions() -> Vec<BashCommand> {
-    fn fetch_users() -> Vec<String> {
-        let users_dir = "/Users";
-        std::fs::read_dir(users_dir)
-            .expect("Failed to read /Users directory")
-            .filter_map(|entry| entry.ok())
-            .filter_map(|entry| {
-                let name = entry.file_name().into_string().ok()?;
-                if name != "Shared" && name != ".localized" {
-                    Some(name)
-                } else {
-                    None
-                }
-            })
-            .collect()
-    }
-
     let mut commands = vec![BashCommand {
         name: "c71_enable_show_all_extensions_root",
         description: "6.2 Ensure Show All Filename Extensions Setting is Enabled for root.",
@@ -1956,15 +2419,17 @@ pub fn c71_enable_show_all_filename_extensions() -> Vec<BashCommand> {
         args: vec![
             "defaults".to_string(),
             "write".to_string(),
-            "/var/root/Library/Preferences/.GlobalPreferences.plist".to_string(),
+            crate::root::path("/var/root/Library/Preferences/.GlobalPreferences.plist"),
             "AppleShowAllExtensions".to_string(),
             "-bool".to_string(),
             "true".to_string(),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }];
 
-    let user_commands = fetch_users()
+    let user_commands = crate::users::human()
         .iter()
         .map(|user| BashCommand {
             name: "c71_enable_show_all_extensions_for_everyuser",
@@ -1972,48 +2437,79 @@ pub fn c71_enable_show_all_filename_extensions() -> Vec<BashCommand> {
             executable: "sudo",
             args: vec![
                 "-u".to_string(),
-                user.to_string(),
+                user.name.clone(),
                 "/usr/bin/defaults".to_string(),
                 "write".to_string(),
-                format!("/Users/{}/Library/Preferences/.GlobalPreferences.plist", user),
+                crate::root::path(&format!("{}/Library/Preferences/.GlobalPreferences.plist", user.home)),
                 "AppleShowAllExtensions".to_string(),
                 "-bool".to_string(),
                 "true".to_string(),
             ],
             dynamic_args: None,
+            write: None,
+            policy: RunPolicy::default(),
         })
         .collect::<Vec<BashCommand>>();
 
     commands.extend(user_commands);
-    commands.push(BashCommand {
-        name: "c71_kill_finder",
-        description: "Restart Finder to apply Show All Extensions.",
-        executable: "sudo",
-        args: vec!["killall".to_string(), "Finder".to_string()],
-        dynamic_args: None,
-    });
-
     commands
 }
 
+// Finder of a user whose setting changed is restarted to apply Show All Extensions
+pub fn c71_restart_finder() -> Vec<PostAction> {
+    crate::users::human()
+        .iter()
+        .map(|user| PostAction {
+            after: &["c71"],
+            user: Some(user.name.clone()),
+            command: BashCommand {
+                name: "c71_kill_finder",
+                description: "Restart Finder to apply Show All Extensions.",
+                executable: "sudo",
+                args: vec!["killall".to_string(), "-u".to_string(), user.name.clone(), "Finder".to_string()],
+                dynamic_args: None,
+                write: None,
+                policy: RunPolicy::default(),
+            },
+        })
+        .collect()
+}
+
+// From Sonoma the Safari container can only be written with Full Disk Access: the settings go to
+// Safari's computer-level managed preferences instead, which every user inherits (the same plist
+// a profile installs, see `profile export`)
+pub const SAFARI_MANAGED_PREFERENCES: &str = "/Library/Managed Preferences/com.apple.Safari";
+
+fn safari_managed(description: &'static str, settings: &[(&'static str, &str, &str, &str)]) -> Vec<BashCommand> {
+    settings
+        .iter()
+        .map(|(name, key, kind, value)| BashCommand {
+            name,
+            description,
+            executable: "sudo",
+            args: vec![
+                "/usr/bin/defaults".to_string(),
+                "write".to_string(),
+                crate::root::path(SAFARI_MANAGED_PREFERENCES),
+                key.to_string(),
+                kind.to_string(),
+                value.to_string(),
+            ],
+            dynamic_args: None,
+            write: None,
+            policy: RunPolicy::default(),
+        })
+        .collect()
+}
+
 pub fn c72_disable_auto_open_safe_files() -> Vec<BashCommand> {
-    fn fetch_users() -> Vec<String> {
-        let users_dir = "/Users";
-        std::fs::read_dir(users_dir)
-            .expect("Failed to read /Users directory")
-            .filter_map(|entry| entry.ok())
-            .filter_map(|entry| {
-                let name = entry.file_name().into_string().ok()?;
-                if name != "Shared" && name != ".localized" {
-                    Some(name)
-                } else {
-                    None
-                }
-            })
-            .collect()
+    if crate::os::at_least(crate::os::Release::Sonoma) {
+        return safari_managed(
+            "7.2.1 Ensure Automatic Opening of Safe Files in Safari is Disabled.",
+            &[("c72_disable_auto_open_safe_files_for_everyuser", "AutoOpenSafeDownloads", "-bool", "false")],
+        );
     }
-
-    fetch_users()
+    crate::users::human()
         .iter()
         .map(|user| BashCommand {
             name: "c72_disable_auto_open_safe_files_for_everyuser",
@@ -2021,35 +2517,27 @@ pub fn c72_disable_auto_open_safe_files() -> Vec<BashCommand> {
             executable: "sudo",
             args: vec![
                 "-u".to_string(),
-                user.to_string(),
+                user.name.clone(),
                 "/usr/bin/defaults".to_string(),
                 "read".to_string(),
-                format!("/Users/{}/Library/Containers/com.apple.Safari/Data/Library/Preferences/com.apple.Safari", user),
+                crate::root::path(&format!("{}/Library/Containers/com.apple.Safari/Data/Library/Preferences/com.apple.Safari", user.home)),
                 "AutoOpenSafeDownloads".to_string(),
             ],
             dynamic_args: None,
+            write: None,
+            policy: RunPolicy::default(),
         })
         .collect()
 }
 
 pub fn c73_enable_warn_about_fraudulent_websites() -> Vec<BashCommand> {
-    fn fetch_users() -> Vec<String> {
-        let users_dir = "/Users";
-        std::fs::read_dir(users_dir)
-            .expect("Failed to read /Users directory")
-            .filter_map(|entry| entry.ok())
-            .filter_map(|entry| {
-                let name = entry.file_name().into_string().ok()?;
-                if name != "Shared" && name != ".localized" {
-                    Some(name)
-                } else {
-                    None
-                }
-            })
-            .collect()
+    if crate::os::at_least(crate::os::Release::Sonoma) {
+        return safari_managed(
+            "7.2.4 Ensure Warn When Visiting A Fraudulent Website in Safari Is Enabled.",
+            &[("c73_warn_about_fraudulent_websites_for_everyuser", "WarnAboutFraudulentWebsites", "-bool", "true")],
+        );
     }
-
-    fetch_users()
+    crate::users::human()
         .iter()
         .map(|user| BashCommand {
             name: "c73_warn_about_fraudulent_websites_for_everyuser",
@@ -2057,35 +2545,31 @@ pub fn c73_enable_warn_about_fraudulent_websites() -> Vec<BashCommand> {
             executable: "sudo",
             args: vec![
                 "-u".to_string(),
-                user.to_string(),
+                user.name.clone(),
                 "/usr/bin/defaults".to_string(),
                 "read".to_string(),
-                format!("/Users/{}/Library/Containers/com.apple.Safari/Data/Library/Preferences/com.apple.Safari", user),
+                crate::root::path(&format!("{}/Library/Containers/com.apple.Safari/Data/Library/Preferences/com.apple.Safari", user.home)),
                 "WarnAboutFraudulentWebsites".to_string(),
             ],
             dynamic_args: None,
+            write: None,
+            policy: RunPolicy::default(),
         })
         .collect()
 }
 
 pub fn c74_enable_cross_site_tracking_prevention() -> Vec<BashCommand> {
-    fn fetch_users() -> Vec<String> {
-        let users_dir = "/Users";
-        std::fs::read_dir(users_dir)
-            .expect("Failed to read /Users directory")
-            .filter_map(|entry| entry.ok())
-            .filter_map(|entry| {
-                let name = entry.file_name().into_string().ok()?;
-                if name != "Shared" && name != ".localized" {
-                    Some(name)
-                } else {
-                    None
-                }
-            })
-            .collect()
+    if crate::os::at_least(crate::os::Release::Sonoma) {
+        return safari_managed(
+            "7.2.5 Ensure Prevent Cross-site Tracking in Safari is Enabled.",
+            &[
+                ("c74_block_storage_policy_for_everyuser", "BlockStoragePolicy", "-int", "2"),
+                ("c74_storage_blocking_policy_for_everyuser", "WebKitPreferences.storageBlockingPolicy", "-int", "1"),
+                ("c74_webkit_storage_blocking_policy_for_everyuser", "WebKitStorageBlockingPolicy", "-int", "1"),
+            ],
+        );
     }
-
-    fetch_users()
+    crate::users::human()
         .iter()
         .flat_map(|user| {
             vec![
@@ -2095,13 +2579,15 @@ pub fn c74_enable_cross_site_tracking_prevention() -> Vec<BashCommand> {
                     executable: "sudo",
                     args: vec![
                         "-u".to_string(),
-                        user.to_string(),
+                        user.name.clone(),
                         "/usr/bin/defaults".to_string(),
                         "read".to_string(),
-                        format!("/Users/{}/Library/Containers/com.apple.Safari/Data/Library/Preferences/com.apple.Safari", user),
+                        crate::root::path(&format!("{}/Library/Containers/com.apple.Safari/Data/Library/Preferences/com.apple.Safari", user.home)),
                         "BlockStoragePolicy".to_string(),
                     ],
                     dynamic_args: None,
+                    write: None,
+                    policy: RunPolicy::default(),
                 },
                 BashCommand {
                     name: "c74_storage_blocking_policy_for_everyuser",
@@ -2109,13 +2595,15 @@ pub fn c74_enable_cross_site_tracking_prevention() -> Vec<BashCommand> {
                     executable: "sudo",
                     args: vec![
                         "-u".to_string(),
-                        user.to_string(),
+                        user.name.clone(),
                         "/usr/bin/defaults".to_string(),
                         "read".to_string(),
-                        format!("/Users/{}/Library/Containers/com.apple.Safari/Data/Library/Preferences/com.apple.Safari", user),
+                        crate::root::path(&format!("{}/Library/Containers/com.apple.Safari/Data/Library/Preferences/com.apple.Safari", user.home)),
                         "WebKitPreferences.storageBlockingPolicy".to_string(),
                     ],
                     dynamic_args: None,
+                    write: None,
+                    policy: RunPolicy::default(),
                 },
                 BashCommand {
                     name: "c74_webkit_storage_blocking_policy_for_everyuser",
@@ -2123,75 +2611,59 @@ pub fn c74_enable_cross_site_tracking_prevention() -> Vec<BashCommand> {
                     executable: "sudo",
                     args: vec![
                         "-u".to_string(),
-                        user.to_string(),
+                        user.name.clone(),
                         "/usr/bin/defaults".to_string(),
                         "read".to_string(),
-                        format!("/Users/{}/Library/Containers/com.apple.Safari/Data/Library/Preferences/com.apple.Safari", user),
+                        crate::root::path(&format!("{}/Library/Containers/com.apple.Safari/Data/Library/Preferences/com.apple.Safari", user.home)),
                         "WebKitStorageBlockingPolicy".to_string(),
                     ],
                     dynamic_args: None,
+                    write: None,
+                    policy: RunPolicy::default(),
                 },
             ]
         })
         .collect()
 }
 
-pub fn c75_disable_automatic_opening_of_safe_files() -> Vec<BashCommand> {
-    fn fetch_users() -> Vec<String> {
-        let users_dir = "/Users";
-        std::fs::read_dir(users_dir)
-            .expect("Failed to read /Users directory")
-            .filter_map(|entry| entry.ok())
-            .filter_map(|entry| {
-                let name = entry.file_name().into_string().ok()?;
-                if name != "Shared" && name != ".localized" {
-                    Some(name)
-                } else {
-                    None
-                }
-            })
-            .collect()
+pub fn c75_enable_hide_ip_address() -> Vec<BashCommand> {
+    if crate::os::at_least(crate::os::Release::Sonoma) {
+        return safari_managed(
+            "7.2.6 Ensure Hide IP Address in Safari is Enabled.",
+            &[("c75_enable_hide_ip_address_for_everyuser", "WBSPrivacyProxyAvailabilityTraffic", "-int", "3300")],
+        );
     }
-
-    fetch_users()
+    crate::users::human()
         .iter()
         .map(|user| BashCommand {
-            name: "c75_disable_auto_open_safe_files_for_everyuser",
-            description: "7.2.6 Ensure Automatic Opening of Safe Files in Safari is Disabled.",
+            name: "c75_enable_hide_ip_address_for_everyuser",
+            description: "7.2.6 Ensure Hide IP Address in Safari is Enabled.",
             executable: "sudo",
             args: vec![
                 "-u".to_string(),
-                user.to_string(),
+                user.name.clone(),
                 "/usr/bin/defaults".to_string(),
                 "write".to_string(),
-                format!("/Users/{}/Library/Containers/com.apple.Safari/Data/Library/Preferences/com.apple.Safari", user),
+                crate::root::path(&format!("{}/Library/Containers/com.apple.Safari/Data/Library/Preferences/com.apple.Safari", user.home)),
                 "WBSPrivacyProxyAvailabilityTraffic".to_string(),
                 "-int".to_string(),
                 "3300".to_string(),
             ],
             dynamic_args: None,
+            write: None,
+            policy: RunPolicy::default(),
         })
         .collect()
 }
 
 pub fn c76_disable_private_click_measurement() -> Vec<BashCommand> {
-    fn fetch_users() -> Vec<String> {
-        let users_dir = "/Users";
-        std::fs::read_dir(users_dir)
-            .expect("Failed to read /Users directory")
-            .filter_map(|entry| entry.ok())
-            .filter_map(|entry| {
-                let name = entry.file_name().into_string().ok()?;
-                if name != "Shared" && name != ".localized" {
-                    Some(name)
-                } else {
-                    None
-                }
-            })
-            .collect()
+    if crate::os::at_least(crate::os::Release::Sonoma) {
+        return safari_managed(
+            "7.2.7 Ensure Private Click Measurement in Safari is Disabled.",
+            &[("c76_disable_private_click_measurement_for_everyuser", "WebKitPreferences.privateClickMeasurementEnabled", "-bool", "false")],
+        );
     }
-
-    fetch_users()
+    crate::users::human()
         .iter()
         .map(|user| BashCommand {
             name: "c76_disable_private_click_measurement_for_everyuser",
@@ -2199,37 +2671,29 @@ pub fn c76_disable_private_click_measurement() -> Vec<BashCommand> {
             executable: "sudo",
             args: vec![
                 "-u".to_string(),
-                user.to_string(),
+                user.name.clone(),
                 "/usr/bin/defaults".to_string(),
                 "write".to_string(),
-                format!("/Users/{}/Library/Containers/com.apple.Safari/Data/Library/Preferences/com.apple.Safari", user),
+                crate::root::path(&format!("{}/Library/Containers/com.apple.Safari/Data/Library/Preferences/com.apple.Safari", user.home)),
                 "WebKitPreferences.privateClickMeasurementEnabled".to_string(),
                 "-bool".to_string(),
                 "false".to_string(),
             ],
             dynamic_args: None,
+            write: None,
+            policy: RunPolicy::default(),
         })
         .collect()
 }
 
 pub fn c77_enable_show_full_website_address() -> Vec<BashCommand> {
-    fn fetch_users() -> Vec<String> {
-        let users_dir = "/Users";
-        std::fs::read_dir(users_dir)
-            .expect("Failed to read /Users directory")
-            .filter_map(|entry| entry.ok())
-            .filter_map(|entry| {
-                let name = entry.file_name().into_string().ok()?;
-                if name != "Shared" && name != ".localized" {
-                    Some(name)
-                } else {
-                    None
-                }
-            })
-            .collect()
+    if crate::os::at_least(crate::os::Release::Sonoma) {
+        return safari_managed(
+            "7.2.8 Ensure Show Full Website Address in Safari is Enabled.",
+            &[("c77_enable_full_website_address_for_everyuser", "ShowFullURLInSmartSearchField", "-bool", "true")],
+        );
     }
-
-    fetch_users()
+    crate::users::human()
         .iter()
         .map(|user| BashCommand {
             name: "c77_enable_full_website_address_for_everyuser",
@@ -2237,34 +2701,20 @@ pub fn c77_enable_show_full_website_address() -> Vec<BashCommand> {
             executable: "sudo",
             args: vec![
                 "-u".to_string(),
-                user.to_string(),
+                user.name.clone(),
                 "/usr/bin/defaults".to_string(),
                 "read".to_string(),
-                format!("/Users/{}/Library/Containers/com.apple.Safari/Data/Library/Preferences/com.apple.Safari", user),
+                crate::root::path(&format!("{}/Library/Containers/com.apple.Safari/Data/Library/Preferences/com.apple.Safari", user.home)),
                 "ShowFullURLInSmartSearchField".to_string(),
             ],
             dynamic_args: None,
+            write: None,
+            policy: RunPolicy::default(),
         })
         .collect()
 }
 
 pub fn c78_disable_bluetooth_sharing() -> Vec<BashCommand> {
-    fn fetch_users() -> Vec<String> {
-        let users_dir = "/Users";
-        std::fs::read_dir(users_dir)
-            .expect("Failed to read /Users directory")
-            .filter_map(|entry| entry.ok())
-            .filter_map(|entry| {
-                let name = entry.file_name().into_string().ok()?;
-                if name != "Shared" && name != ".localized" {
-                    Some(name)
-                } else {
-                    None
-                }
-            })
-            .collect()
-    }
-
     let mut commands = vec![
         // Comando para root
         BashCommand {
@@ -2274,12 +2724,14 @@ pub fn c78_disable_bluetooth_sharing() -> Vec<BashCommand> {
             args: vec![
                 "defaults".to_string(),
                 "write".to_string(),
-                "/var/root/Library/Preferences/com.apple.Bluetooth".to_string(),
+                crate::root::path("/var/root/Library/Preferences/com.apple.Bluetooth"),
                 "PrefKeyServicesEnabled".to_string(),
                 "-bool".to_string(),
                 "false".to_string(),
             ],
             dynamic_args: None,
+            write: None,
+            policy: RunPolicy::default(),
         },
         BashCommand {
             name: "c78_disable_bluetooth_sharing_root_byhost",
@@ -2288,17 +2740,19 @@ pub fn c78_disable_bluetooth_sharing() -> Vec<BashCommand> {
             args: vec![
                 "defaults".to_string(),
                 "write".to_string(),
-                "/var/root/Library/Preferences/ByHost/com.apple.Bluetooth".to_string(),
+                crate::root::path("/var/root/Library/Preferences/ByHost/com.apple.Bluetooth"),
                 "PrefKeyServicesEnabled".to_string(),
                 "-bool".to_string(),
                 "false".to_string(),
             ],
             dynamic_args: None,
+            write: None,
+            policy: RunPolicy::default(),
         },
     ];
 
     // Comandos para cada usuário
-    let user_commands = fetch_users()
+    let user_commands = crate::users::human()
         .iter()
         .map(|user| BashCommand {
             name: "c78_disable_bluetooth_sharing_for_everyuser",
@@ -2306,7 +2760,7 @@ pub fn c78_disable_bluetooth_sharing() -> Vec<BashCommand> {
             executable: "sudo",
             args: vec![
                 "-u".to_string(),
-                user.to_string(),
+                user.name.clone(),
                 "/usr/bin/defaults".to_string(),
                 "-currentHost".to_string(),
                 "write".to_string(),
@@ -2316,6 +2770,8 @@ pub fn c78_disable_bluetooth_sharing() -> Vec<BashCommand> {
                 "false".to_string(),
             ],
             dynamic_args: None,
+            write: None,
+            policy: RunPolicy::default(),
         })
         .collect::<Vec<BashCommand>>();
 
@@ -2324,7 +2780,7 @@ pub fn c78_disable_bluetooth_sharing() -> Vec<BashCommand> {
 }
 
 /// This function sets a login window banner for macOS systems.
-/// The banner text is in English and references a generic organization name.
+/// The banner text is in English and references the configured organization name.
 pub fn c79_set_login_window_banner() -> BashCommand {
     BashCommand {
         name: "c79_set_login_window_banner",
@@ -2332,7 +2788,7 @@ pub fn c79_set_login_window_banner() -> BashCommand {
         executable: "sh",
         args: vec![
             "-c".to_string(),
-            r#"sudo tee /Library/Security/PolicyBanner.txt > /dev/null <<EOL
+            r#"sudo tee '[BANNER]' > /dev/null <<EOL
 =================================================================
                                                         LOGIN NOTICE
 =================================================================
@@ -2350,17 +2806,21 @@ the policies may result in disciplinary or legal actions, as appropriate.
 
                                                         [ORGANIZATION]
 EOL"#
-            .to_string(),
+            .replace("[BANNER]", &crate::root::path("/Library/Security/PolicyBanner.txt"))
+            .replace("[ORGANIZATION]", &crate::config::current().organization),
         ],
         dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
     }
 }
 
 
-pub fn c80_restart_wazuh_agent() -> BashCommand {
+// Wazuh re-runs its assessment when restarted after any change of the run
+pub fn c80_restart_wazuh_agent() -> PostAction {
     let file_path = "/Library/Ossec/bin/wazuh-control";
 
-    if std::path::Path::new(file_path).exists() {
+    let command = if std::path::Path::new(&crate::root::path(file_path)).exists() {
         // Reiniciar o agente Wazuh
         BashCommand {
             name: "c80_restart_wazuh_agent",
@@ -2368,6 +2828,8 @@ pub fn c80_restart_wazuh_agent() -> BashCommand {
             executable: "sudo",
             args: vec![file_path.to_string(), "restart".to_string()],
             dynamic_args: None,
+            write: None,
+            policy: RunPolicy::default(),
         }
     } else {
         // Comando alternativo se o arquivo não for encontrado
@@ -2377,6 +2839,377 @@ pub fn c80_restart_wazuh_agent() -> BashCommand {
             executable: "echo",
             args: vec!["Wazuh Agent control file not found.".to_string()],
             dynamic_args: None,
+            write: None,
+            policy: RunPolicy::default(),
         }
+    };
+    PostAction { after: &["*"], user: None, command }
+}
+
+// Writes the browser's policies when it is installed and they are not all in place
+fn browser_policies(name: &'static str, description: &'static str, browser: &crate::browser::Browser) -> BashCommand {
+    let message = |text: String| BashCommand {
+        name,
+        description,
+        executable: "echo",
+        args: vec![text],
+        dynamic_args: None,
+        write: None,
+        policy: RunPolicy::default(),
+    };
+
+    if !browser.is_installed() {
+        return message(format!("{} is not installed.", browser.name));
+    }
+    match browser.policy_write() {
+        Ok(Some(write)) => file_command(name, description, write),
+        Ok(None) => message(format!("The {} policies are already configured.", browser.name)),
+        Err(text) => error(name, description, text),
+    }
+}
+
+pub fn c81_configure_chrome_policies() -> BashCommand {
+    browser_policies(
+        "c81_configure_chrome_policies",
+        "Configure Google Chrome Security Policies.",
+        &crate::browser::CHROME,
+    )
+}
+
+pub fn c82_configure_edge_policies() -> BashCommand {
+    browser_policies(
+        "c82_configure_edge_policies",
+        "Configure Microsoft Edge Security Policies.",
+        &crate::browser::EDGE,
+    )
+}
+
+pub fn c83_configure_firefox_policies() -> BashCommand {
+    browser_policies(
+        "c83_configure_firefox_policies",
+        "Configure Firefox Security Policies.",
+        &crate::browser::FIREFOX,
+    )
+}
+
+// Controls run against a scripted fake macOS: the argument vectors they build, the branches
+// they take on the outputs they read, and what they hand to the runner
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{fixture, recorded, simulate, sw_vers};
+
+    fn written(command: &BashCommand) -> String {
+        let write = command.write.as_ref().expect("file write");
+        String::from_utf8(write.content.clone()).expect("text file")
+    }
+
+    const SYSCTL_CPU: &str = "/usr/sbin/sysctl -n machdep.cpu.brand_string";
+
+    fn with_timeout(seconds: u32) {
+        crate::config::install_for_thread(crate::config::Config {
+            command_timeout: seconds,
+            ..crate::config::Config::default()
+        });
+    }
+
+    #[test]
+    fn c36_leaves_apple_cpus_alone() {
+        let runner = simulate(&format!("$ {}\nApple M1\n", SYSCTL_CPU));
+        let command = c36_disable_powernap_based_on_cpu();
+        assert_eq!(command.message().as_deref(), Some("No action required for Apple CPUs."));
+        assert_eq!(recorded(runner), vec![SYSCTL_CPU]);
+    }
+
+    #[test]
+    fn c36_disables_power_nap_on_intel_cpus() {
+        simulate(&format!("$ {}\nIntel(R) Core(TM) i7-8700B CPU @ 3.20GHz\n", SYSCTL_CPU));
+        assert_eq!(c36_disable_powernap_based_on_cpu().argv(), vec!["sudo", "/usr/bin/pmset", "-a", "powernap", "0"]);
+    }
+
+    #[test]
+    fn c36_reports_a_failed_sysctl() {
+        simulate(&format!("$1 {}\n2> unknown oid\n", SYSCTL_CPU));
+        let message = c36_disable_powernap_based_on_cpu().message().expect("message");
+        assert!(message.starts_with("Failed to retrieve the CPU model"), "{}", message);
+        assert!(message.contains("unknown oid"), "{}", message);
+
+        // Without command_timeout a hung sysctl would be waited for, the script cannot hang
+        simulate(&format!("$timeout {}\n", SYSCTL_CPU));
+        let message = c36_disable_powernap_based_on_cpu().message().expect("message");
+        assert!(message.contains("scripted to hang"), "{}", message);
+
+        with_timeout(60);
+        let message = c36_disable_powernap_based_on_cpu().message().expect("message");
+        assert!(message.contains("stopped at the timeout"), "{}", message);
+    }
+
+    #[test]
+    fn errors_are_failures_and_messages_are_skips() {
+        simulate(&format!("$1 {}\n2> unknown oid\n", SYSCTL_CPU));
+        let outcome = c36_disable_powernap_based_on_cpu().execute();
+        assert_eq!(outcome.status, ExecutionStatus::Failed);
+        assert!(outcome.stderr.starts_with("Failed to retrieve the CPU model"), "{}", outcome.stderr);
+
+        simulate(&format!("$ {}\nApple M2\n", SYSCTL_CPU));
+        assert_eq!(c36_disable_powernap_based_on_cpu().execute().status, ExecutionStatus::Skipped);
+
+        simulate("$timeout find /System/Volumes/Data/Library *\n");
+        let outcomes: Vec<ExecutionOutcome> = c50_fix_world_writable_library_files().iter().map(|command| command.execute()).collect();
+        assert_eq!(outcomes.len(), 1);
+        assert_eq!(outcomes[0].status, ExecutionStatus::Failed);
+
+        fixture("errors-missing", &[]);
+        for command in [c38_configure_auditing_flags(), c39_configure_install_log_retention(), c40_configure_auditing_retention()] {
+            let outcome = command.execute();
+            assert_eq!(outcome.status, ExecutionStatus::Failed, "{}", outcome.name);
+            assert!(outcome.stderr.starts_with("Failed to read"), "{}", outcome.stderr);
+            assert_eq!(command.resource(), None);
+        }
+
+        fixture("errors-configured", &[(crate::audit_control::PATH, "dir:/var/audit\nflags:-fm,ad,-ex,aa,-fr,lo,-fw\nexpire-after:60d\n")]);
+        assert_eq!(c38_configure_auditing_flags().execute().status, ExecutionStatus::Skipped);
+        assert_eq!(c40_configure_auditing_retention().execute().status, ExecutionStatus::Skipped);
+    }
+
+    #[test]
+    fn c38_adds_the_missing_flags() {
+        let root = fixture("c38", &[(crate::audit_control::PATH, "# site\ndir:/var/audit\nflags:lo,aa\nminfree:5\n")]);
+        let runner = simulate("");
+        let command = c38_configure_auditing_flags();
+        assert_eq!(written(&command), "# site\ndir:/var/audit\nflags:lo,aa,-fm,ad,-ex,-fr,-fw\nminfree:5\n");
+
+        let outcome = command.execute();
+        assert_eq!(outcome.status, ExecutionStatus::Applied);
+        assert_eq!(
+            recorded(runner),
+            vec![format!("write {}/etc/security/audit_control ({} bytes)", root, written(&command).len())]
+        );
+    }
+
+    #[test]
+    fn c38_keeps_configured_flags() {
+        fixture("c38-configured", &[(crate::audit_control::PATH, "dir:/var/audit\nflags:-fm,ad,-ex,aa,-fr,lo,-fw,-fc\n")]);
+        let command = c38_configure_auditing_flags();
+        assert_eq!(command.message().as_deref(), Some("The auditing flags are already configured correctly."));
+
+        fixture("c38-missing", &[]);
+        let message = c38_configure_auditing_flags().message().expect("message");
+        assert!(message.starts_with("Failed to read"), "{}", message);
+    }
+
+    #[test]
+    fn c39_retains_install_log_for_a_year() {
+        let rule = "* file /var/log/install.log mode=0640 format=bsd rotate=utc compress file_max=50M ttl=30 all_max=150M\n";
+        let root = fixture("c39", &[(crate::asl::INSTALL_CONFIG, rule)]);
+        let content = written(&c39_configure_install_log_retention());
+        assert!(content.contains("ttl=365"), "{}", content);
+        assert!(!content.contains("all_max"), "{}", content);
+        assert!(content.contains("rotate=utc"), "{}", content);
+
+        // Once written, the file is left as it is
+        std::fs::write(format!("{}{}", root, crate::asl::INSTALL_CONFIG), &content).expect("fixture file");
+        let command = c39_configure_install_log_retention();
+        assert_eq!(command.message().as_deref(), Some("install.log retention is already configured."));
+    }
+
+    #[test]
+    fn c40_raises_a_short_retention() {
+        fixture("c40", &[(crate::audit_control::PATH, "dir:/var/audit\nexpire-after:10M\n")]);
+        assert_eq!(written(&c40_configure_auditing_retention()), "dir:/var/audit\nexpire-after:60d\n");
+    }
+
+    #[test]
+    fn c40_keeps_a_longer_retention() {
+        for value in ["60d", "1y", "90d OR 10G", "60d AND 5G"] {
+            fixture("c40-longer", &[(crate::audit_control::PATH, &format!("dir:/var/audit\nexpire-after:{}\n", value))]);
+            let command = c40_configure_auditing_retention();
+            assert_eq!(command.message().as_deref(), Some("Auditing retention already configured."), "{}", value);
+        }
+    }
+
+    #[test]
+    fn c17_confirms_on_stdin() {
+        let runner = simulate("$ sudo /usr/sbin/systemsetup -setremotelogin off\n");
+        let outcome = c17_disable_remote_login().execute();
+        assert_eq!(outcome.status, ExecutionStatus::Applied);
+
+        let invocations = runner.invocations();
+        assert_eq!(invocations.len(), 1);
+        assert_eq!(invocations[0].command_line, "sudo /usr/sbin/systemsetup -setremotelogin off");
+        assert_eq!(invocations[0].stdin.as_deref(), Some("yes\n"));
+    }
+
+    #[test]
+    fn c48_fixes_every_world_writable_app() {
+        simulate("$ find /Applications *\n/Applications/Bad.app\n/Applications/Other App.app\n");
+        let commands = c48_ensure_permissions_for_system_apps();
+        let argvs: Vec<Vec<String>> = commands.iter().map(|command| command.argv()).collect();
+        assert_eq!(
+            argvs,
+            vec![
+                vec!["sudo", "/bin/chmod", "-R", "o-w", "/Applications/Bad.app"],
+                vec!["sudo", "/bin/chmod", "-R", "o-w", "/Applications/Other App.app"],
+            ]
+        );
+
+        simulate("$ find /Applications *\n");
+        assert!(c48_ensure_permissions_for_system_apps().is_empty());
+    }
+
+    #[test]
+    fn c49_keeps_the_paths_of_a_partial_search() {
+        let runner = simulate("$1 find /System/Volumes/Data/System *\n/System/Volumes/Data/System/Shared\n2> find: Permission denied\n");
+        let commands = c49_fix_world_writable_system_files();
+        assert_eq!(commands.len(), 1);
+        assert_eq!(commands[0].argv(), vec!["sudo", "/bin/chmod", "-R", "o-w", "/System/Volumes/Data/System/Shared"]);
+        assert_eq!(
+            recorded(runner),
+            vec!["find /System/Volumes/Data/System -type d -perm -2 -not -path '*Drop Box*' -print"]
+        );
+    }
+
+    #[test]
+    fn c49_and_c50_leave_out_the_paths_of_the_benchmark() {
+        let root = fixture("world-writable", &[]);
+        let folders = [
+            "System/Volumes/Data/System/Library/Open",
+            "System/Volumes/Data/System/Library/User Template/Drop Box",
+            "System/Volumes/Data/Library/Open",
+            "System/Volumes/Data/Library/Caches/com.example",
+            "System/Volumes/Data/Library/Preferences/Audio/Data",
+            "System/Volumes/Data/Library/Closed",
+        ];
+        for folder in folders {
+            let path = format!("{}/{}", root, folder);
+            std::fs::create_dir_all(&path).expect("fixture folder");
+            let mode = if folder.ends_with("Closed") { 0o755 } else { 0o777 };
+            std::fs::set_permissions(&path, std::os::unix::fs::PermissionsExt::from_mode(mode)).expect("mode");
+        }
+
+        let target = |commands: Vec<BashCommand>| -> Vec<String> {
+            commands.iter().map(|command| command.argv().last().cloned().unwrap_or_default()).collect()
+        };
+        assert_eq!(
+            target(c49_fix_world_writable_system_files()),
+            vec![format!("{}/System/Volumes/Data/System/Library/Open", root)]
+        );
+        assert_eq!(
+            target(c50_fix_world_writable_library_files()),
+            vec![format!("{}/System/Volumes/Data/Library/Open", root)]
+        );
+    }
+
+    #[test]
+    fn c50_reports_a_search_stopped_at_the_timeout() {
+        simulate("$timeout find /System/Volumes/Data/Library *\n");
+        with_timeout(60);
+        let commands = c50_fix_world_writable_library_files();
+        assert_eq!(commands.len(), 1);
+        let message = commands[0].message().expect("message");
+        assert!(message.contains("stopped at the timeout"), "{}", message);
+    }
+
+    #[test]
+    fn a_command_stopped_at_its_timeout_is_retried() {
+        let runner = simulate("$timeout sudo /usr/sbin/softwareupdate -l\n");
+        let outcome = c2_ensure_apple_software_is_current().execute();
+        assert_eq!(outcome.status, ExecutionStatus::Failed);
+        assert!(outcome.timed_out);
+        assert_eq!(outcome.attempts, 3);
+        assert_eq!(outcome.exit_code, None);
+        assert!(outcome.stderr.contains("Killed after 300s without finishing"), "{}", outcome.stderr);
+        assert_eq!(recorded(runner).len(), 3);
+    }
+
+    #[test]
+    fn a_failing_command_is_retried_up_to_its_attempts() {
+        let sntp = format!("sudo sntp -sS {}", crate::config::current().time_server);
+        let runner = simulate(&format!("$1 {}\n2> no server suitable for synchronization found\n", sntp));
+        let outcome = c10_sync_time().execute();
+        assert_eq!(outcome.status, ExecutionStatus::Failed);
+        assert!(!outcome.timed_out);
+        assert_eq!(outcome.attempts, 3);
+        assert_eq!(recorded(runner), vec![sntp.clone(), sntp.clone(), sntp.clone()]);
+
+        let runner = simulate(&format!("$ {}\n", sntp));
+        let outcome = c10_sync_time().execute();
+        assert_eq!(outcome.status, ExecutionStatus::Applied);
+        assert_eq!(outcome.attempts, 1);
+        assert_eq!(recorded(runner).len(), 1);
+    }
+
+    #[test]
+    fn a_command_without_retries_runs_once() {
+        let runner = simulate("$1 sudo /usr/sbin/systemsetup -setremotelogin off\n");
+        let outcome = c17_disable_remote_login().execute();
+        assert_eq!(outcome.status, ExecutionStatus::Failed);
+        assert_eq!(outcome.attempts, 1);
+        assert_eq!(recorded(runner).len(), 1);
+    }
+
+    #[test]
+    fn c13_uses_launchctl_from_sonoma() {
+        simulate(&sw_vers("13.6"));
+        assert_eq!(c13_disable_remote_apple_events().argv(), vec!["sudo", "systemsetup", "-setremoteappleevents", "off"]);
+
+        simulate(&sw_vers("14.0"));
+        assert_eq!(c13_disable_remote_apple_events().argv(), vec!["sudo", "launchctl", "disable", "system/com.apple.AEServer"]);
+    }
+
+    #[test]
+    fn c25_uses_socketfilterfw_from_sequoia() {
+        simulate(&sw_vers("14.5"));
+        assert_eq!(
+            c25_enable_firewall().argv(),
+            vec!["sudo", "/usr/bin/defaults", "write", "/Library/Preferences/com.apple.alf", "globalstate", "-int", "1"]
+        );
+
+        simulate(&sw_vers("15.0"));
+        assert_eq!(
+            c25_enable_firewall().argv(),
+            vec!["sudo", "/usr/libexec/ApplicationFirewall/socketfilterfw", "--setglobalstate", "on"]
+        );
+    }
+
+    #[test]
+    fn c44_disables_the_http_server_from_ventura() {
+        simulate(&sw_vers("12.7"));
+        assert_eq!(
+            c44_disable_http_server().argv(),
+            vec!["sudo", "/bin/launchctl", "unload", "-w", "/System/Library/LaunchDaemons/org.apache.httpd.plist"]
+        );
+
+        simulate(&sw_vers("13.0"));
+        assert_eq!(c44_disable_http_server().argv(), vec!["sudo", "/bin/launchctl", "disable", "system/org.apache.httpd"]);
+    }
+
+    #[test]
+    fn safari_settings_go_to_the_managed_preferences_from_sonoma() {
+        simulate(&sw_vers("13.6"));
+        for command in c74_enable_cross_site_tracking_prevention() {
+            assert!(command.argv().iter().any(|arg| arg.contains("/Library/Containers/com.apple.Safari/")), "{:?}", command.argv());
+        }
+
+        simulate(&sw_vers("14.5"));
+        let argvs: Vec<Vec<String>> = c74_enable_cross_site_tracking_prevention().iter().map(|command| command.argv()).collect();
+        let write = |key: &str, value: &str| {
+            vec!["sudo", "/usr/bin/defaults", "write", SAFARI_MANAGED_PREFERENCES, key, "-int", value]
+                .into_iter()
+                .map(String::from)
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(
+            argvs,
+            vec![
+                write("BlockStoragePolicy", "2"),
+                write("WebKitPreferences.storageBlockingPolicy", "1"),
+                write("WebKitStorageBlockingPolicy", "1"),
+            ]
+        );
+        assert_eq!(
+            c72_disable_auto_open_safe_files()[0].argv(),
+            vec!["sudo", "/usr/bin/defaults", "write", SAFARI_MANAGED_PREFERENCES, "AutoOpenSafeDownloads", "-bool", "false"]
+        );
     }
 }
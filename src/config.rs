@@ -0,0 +1,389 @@
+// Organization settings used by the controls, loaded from a `key = value` file with --config
+//
+// Example:
+//     # Example Corp hardening settings
+//     organization = Example Corp
+//     timezone = Europe/Paris
+//     min_password_length = 15
+//     audit_expire_after = 60d OR 5G
+//     exclude_users = kiosk, lab*
+//     exclude_users.c11 = presenter
+
+// Tunable values of the controls; the defaults are the values the tool always used
+pub struct Config {
+    pub organization: String,           // Name written in the login window banner (c79)
+    pub login_window_text: String,      // Custom login screen message (c31)
+    pub timezone: String,               // c9_1, e.g. "America/Sao_Paulo"
+    pub time_server: String,            // c9_2 and c10
+    pub screensaver_idle_time: u32,     // c11, in seconds
+    pub max_failed_login_attempts: u32, // c51
+    pub min_password_length: u32,       // c52
+    pub password_history: u32,          // c58
+    pub audit_flags: String,            // c38, comma separated
+    pub audit_expire_after: String,     // c40, e.g. "60d" or "60d OR 5G"
+    pub profile_identifier: String,     // Reverse-DNS prefix of the exported profile and its payloads
+    pub users: crate::users::Filter,    // Accounts the per-user controls act on (`users`, `exclude_users`)
+    pub console_user_only: bool,        // Per-user controls only act on the account logged in at the console
+    pub jobs: u32,                      // Worker threads of an apply run (--jobs)
+    pub command_timeout: u32,           // Seconds after which a command is killed, 0 to wait forever
+    pub control_users: Vec<(&'static str, crate::users::Filter)>, // `users.<control>`, `exclude_users.<control>`
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            organization: "[ORGANIZATION]".to_string(),
+            login_window_text: "Access for authorized personnel only. \\nThis system is being monitored.".to_string(),
+            timezone: "America/Sao_Paulo".to_string(),
+            time_server: "time.apple.com".to_string(),
+            screensaver_idle_time: 600,
+            max_failed_login_attempts: 5,
+            min_password_length: 12,
+            password_history: 15,
+            audit_flags: crate::audit_control::REQUIRED_FLAGS.join(","),
+            audit_expire_after: "60d".to_string(),
+            profile_identifier: "local.cis-compliance".to_string(),
+            users: crate::users::Filter::default(),
+            console_user_only: false,
+            jobs: 1,
+            command_timeout: 0,
+            control_users: Vec::new(),
+        }
+    }
+}
+
+static CURRENT: std::sync::OnceLock<Config> = std::sync::OnceLock::new();
+
+// Makes a loaded configuration the one used by every control (only the first call has an effect)
+pub fn install(config: Config) {
+    let _ = CURRENT.set(config);
+}
+
+#[cfg(test)]
+thread_local! {
+    // Configuration of one test, so tests running in parallel can each use their own
+    static TEST_CONFIG: std::cell::Cell<Option<&'static Config>> = const { std::cell::Cell::new(None) };
+}
+
+// Makes a configuration the one used by the current thread (tests only)
+#[cfg(test)]
+pub fn install_for_thread(config: Config) {
+    TEST_CONFIG.set(Some(Box::leak(Box::new(config))));
+}
+
+// Configuration in effect: the installed one, or the defaults
+pub fn current() -> &'static Config {
+    #[cfg(test)]
+    if let Some(config) = TEST_CONFIG.get() {
+        return config;
+    }
+    CURRENT.get_or_init(Config::default)
+}
+
+impl Config {
+    // Reads a configuration file; keys that are not set keep their default value
+    pub fn load(path: &str) -> Result<Config, String> {
+        let content = std::fs::read_to_string(path).map_err(|error| format!("Failed to read {}: {}", path, error))?;
+        Config::parse(&content).map_err(|errors| {
+            format!("Invalid configuration {}:\n  {}", path, errors.join("\n  "))
+        })
+    }
+
+    // Parses `key = value` lines (blank lines and lines starting with '#' are ignored),
+    // then validates the result. Every problem is reported, not only the first one.
+    fn parse(content: &str) -> Result<Config, Vec<String>> {
+        let mut config = Config::default();
+        let mut errors = Vec::new();
+        let mut seen: Vec<String> = Vec::new();
+
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let number = index + 1;
+
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), unquote(value.trim())),
+                None => {
+                    errors.push(format!("line {}: expected 'key = value'", number));
+                    continue;
+                }
+            };
+            if seen.iter().any(|previous| previous == key) {
+                errors.push(format!("line {}: '{}' is set more than once", number, key));
+                continue;
+            }
+            seen.push(key.to_string());
+
+            let number_value = || {
+                value
+                    .parse::<u32>()
+                    .map_err(|_| format!("line {}: '{}' expects a whole number, got '{}'", number, key, value))
+            };
+            let result = match key {
+                "organization" => set_text(&mut config.organization, value),
+                "login_window_text" => set_text(&mut config.login_window_text, value),
+                "timezone" => set_text(&mut config.timezone, value),
+                "time_server" => set_text(&mut config.time_server, value),
+                "screensaver_idle_time" => number_value().map(|value| config.screensaver_idle_time = value),
+                "max_failed_login_attempts" => number_value().map(|value| config.max_failed_login_attempts = value),
+                "min_password_length" => number_value().map(|value| config.min_password_length = value),
+                "password_history" => number_value().map(|value| config.password_history = value),
+                "audit_flags" => set_text(&mut config.audit_flags, value),
+                "audit_expire_after" => set_text(&mut config.audit_expire_after, value),
+                "profile_identifier" => set_text(&mut config.profile_identifier, value),
+                "jobs" => number_value().map(|value| config.jobs = value),
+                "command_timeout" => number_value().map(|value| config.command_timeout = value),
+                "users" => set_list(&mut config.users.include, value),
+                "exclude_users" => set_list(&mut config.users.exclude, value),
+                "console_user_only" => match value {
+                    "true" | "yes" => Ok(true),
+                    "false" | "no" => Ok(false),
+                    _ => Err(format!("line {}: '{}' expects true or false, got '{}'", number, key, value)),
+                }
+                .map(|value| config.console_user_only = value),
+                _ => match key.split_once('.') {
+                    // Per-control settings, e.g. "exclude_users.c11 = presenter"
+                    Some((setting @ ("users" | "exclude_users"), control)) => match crate::catalog::find(control) {
+                        Some(control) => {
+                            let filter = match config.control_users.iter().position(|(name, _)| *name == control.name) {
+                                Some(index) => &mut config.control_users[index].1,
+                                None => {
+                                    config.control_users.push((control.name, crate::users::Filter::default()));
+                                    &mut config.control_users.last_mut().expect("just pushed").1
+                                }
+                            };
+                            match setting {
+                                "users" => set_list(&mut filter.include, value),
+                                _ => set_list(&mut filter.exclude, value),
+                            }
+                        }
+                        None => Err(format!("line {}: unknown control '{}' in '{}'", number, control, key)),
+                    },
+                    _ => Err(format!("line {}: unknown setting '{}'", number, key)),
+                },
+            };
+            if let Err(error) = result {
+                errors.push(error);
+            }
+        }
+
+        errors.extend(config.validate());
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(errors)
+        }
+    }
+
+    // Checks that every value stays within what the benchmark accepts
+    fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        let mut require = |valid: bool, message: String| {
+            if !valid {
+                errors.push(message);
+            }
+        };
+
+        // Text ends up in a shell here-document (c79), keep it to plain characters
+        require(
+            !self.organization.is_empty()
+                && !self.organization.chars().any(|c| c.is_control() || "$`\\'\"".contains(c)),
+            format!("organization '{}' must be non-empty plain text (no quotes, '$', '`' or '\\')", self.organization),
+        );
+        require(!self.login_window_text.is_empty(), "login_window_text must not be empty".to_string());
+        require(
+            !self.timezone.is_empty() && !self.timezone.contains(char::is_whitespace),
+            format!("timezone '{}' must be a zone name such as 'Europe/Paris'", self.timezone),
+        );
+        require(
+            !self.time_server.is_empty()
+                && self.time_server.chars().all(|c| c.is_ascii_alphanumeric() || ".-:".contains(c)),
+            format!("time_server '{}' must be a host name or address", self.time_server),
+        );
+        require(
+            (1..=1200).contains(&self.screensaver_idle_time),
+            format!("screensaver_idle_time {} must be between 1 and 1200 seconds (2.3.1)", self.screensaver_idle_time),
+        );
+        require(
+            (1..=5).contains(&self.max_failed_login_attempts),
+            format!("max_failed_login_attempts {} must be between 1 and 5 (5.2.1)", self.max_failed_login_attempts),
+        );
+        require(
+            (12..=128).contains(&self.min_password_length),
+            format!("min_password_length {} must be between 12 and 128 (5.2.2)", self.min_password_length),
+        );
+        require(
+            (15..=100).contains(&self.password_history),
+            format!("password_history {} must be between 15 and 100 (5.2.8)", self.password_history),
+        );
+
+        let flags: Vec<&str> = self.audit_flags.split(',').map(|flag| flag.trim()).collect();
+        let missing = crate::audit_control::missing_flags(&flags);
+        require(
+            missing.is_empty() && !flags.iter().any(|flag| flag.is_empty() || flag.contains(char::is_whitespace)),
+            format!("audit_flags '{}' must be comma separated and include {} (3.2)", self.audit_flags, missing.join(",")),
+        );
+        if let Some(error) = crate::audit_control::expire_after_error(&self.audit_expire_after) {
+            require(false, format!("audit_expire_after '{}': {} (3.4)", self.audit_expire_after, error));
+        }
+        require(
+            (1..=64).contains(&self.jobs),
+            format!("jobs {} must be between 1 and 64", self.jobs),
+        );
+        require(
+            self.command_timeout <= 86400,
+            format!("command_timeout {} must be at most 86400 seconds (0 waits forever)", self.command_timeout),
+        );
+        require(
+            self.profile_identifier.split('.').count() >= 2
+                && self
+                    .profile_identifier
+                    .split('.')
+                    .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')),
+            format!("profile_identifier '{}' must be a reverse-DNS name such as 'com.example.cis'", self.profile_identifier),
+        );
+
+        errors
+    }
+}
+
+fn set_text(target: &mut String, value: &str) -> Result<(), String> {
+    *target = value.to_string();
+    Ok(())
+}
+
+// Comma separated values, e.g. "kiosk, lab*"
+fn set_list(target: &mut Vec<String>, value: &str) -> Result<(), String> {
+    *target = value
+        .split(',')
+        .map(|item| item.trim())
+        .filter(|item| !item.is_empty())
+        .map(|item| item.to_string())
+        .collect();
+    Ok(())
+}
+
+// Removes one pair of surrounding double quotes, so values can keep leading or trailing spaces
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(content: &str) -> Config {
+        Config::parse(content).unwrap_or_else(|errors| panic!("{:?}", errors))
+    }
+
+    fn errors(content: &str) -> Vec<String> {
+        match Config::parse(content) {
+            Ok(_) => Vec::new(),
+            Err(errors) => errors,
+        }
+    }
+
+    #[test]
+    fn defaults_are_valid() {
+        assert!(Config::default().validate().is_empty());
+        assert_eq!(parsed("").screensaver_idle_time, 600);
+    }
+
+    #[test]
+    fn parses_settings_and_ignores_comments() {
+        let config = parsed(
+            "# Example Corp hardening settings\n\
+             \n\
+             organization = Example Corp\n\
+             \x20  # indented comment\n\
+             login_window_text = \"  Authorized use only  \"\n\
+             timezone=Europe/Paris\n\
+             min_password_length = 15\n\
+             command_timeout = 900\n\
+             console_user_only = yes\n\
+             exclude_users = kiosk, lab*,\n\
+             exclude_users.c11 = presenter\n",
+        );
+        assert_eq!(config.organization, "Example Corp");
+        assert_eq!(config.login_window_text, "  Authorized use only  ");
+        assert_eq!(config.timezone, "Europe/Paris");
+        assert_eq!(config.min_password_length, 15);
+        assert_eq!(config.command_timeout, 900);
+        assert!(config.console_user_only);
+        assert_eq!(config.users.exclude, vec!["kiosk", "lab*"]);
+        assert_eq!(config.control_users.len(), 1);
+        assert!(config.control_users[0].0.starts_with("c11"));
+        assert_eq!(config.control_users[0].1.exclude, vec!["presenter"]);
+        // Keys that are not set keep their default value
+        assert_eq!(config.password_history, 15);
+    }
+
+    #[test]
+    fn reports_every_malformed_line() {
+        assert_eq!(
+            errors(
+                "colour = blue\n\
+                 min_password_length = fifteen\n\
+                 password_history = -1\n\
+                 jobs = 2.5\n\
+                 console_user_only = maybe\n\
+                 timezone\n\
+                 exclude_users.c999 = kiosk\n\
+                 organization = A\n\
+                 organization = B\n"
+            ),
+            vec![
+                "line 1: unknown setting 'colour'",
+                "line 2: 'min_password_length' expects a whole number, got 'fifteen'",
+                "line 3: 'password_history' expects a whole number, got '-1'",
+                "line 4: 'jobs' expects a whole number, got '2.5'",
+                "line 5: 'console_user_only' expects true or false, got 'maybe'",
+                "line 6: expected 'key = value'",
+                "line 7: unknown control 'c999' in 'exclude_users.c999'",
+                "line 9: 'organization' is set more than once",
+            ]
+        );
+    }
+
+    #[test]
+    fn numbers_must_stay_within_the_benchmark_ranges() {
+        for (setting, valid, invalid) in [
+            ("screensaver_idle_time", ["1", "1200"], ["0", "1201"]),
+            ("max_failed_login_attempts", ["1", "5"], ["0", "6"]),
+            ("min_password_length", ["12", "128"], ["11", "129"]),
+            ("password_history", ["15", "100"], ["14", "101"]),
+            ("command_timeout", ["0", "86400"], ["86401", "4294967295"]),
+        ] {
+            for value in valid {
+                assert!(errors(&format!("{} = {}", setting, value)).is_empty(), "{} = {}", setting, value);
+            }
+            for value in invalid {
+                let errors = errors(&format!("{} = {}", setting, value));
+                assert_eq!(errors.len(), 1, "{} = {}: {:?}", setting, value, errors);
+                assert!(errors[0].starts_with(&format!("{} {} must be", setting, value)), "{}", errors[0]);
+            }
+        }
+    }
+
+    #[test]
+    fn text_values_are_validated() {
+        let errors = errors(
+            "organization = Example $(id)\n\
+             timezone = Europe / Paris\n\
+             time_server = time.apple.com;reboot\n\
+             audit_flags = lo,aa\n\
+             profile_identifier = cis\n",
+        );
+        assert_eq!(errors.len(), 5, "{:?}", errors);
+        assert!(errors[0].starts_with("organization 'Example $(id)'"));
+        assert!(errors[1].starts_with("timezone 'Europe / Paris'"));
+        assert!(errors[2].starts_with("time_server 'time.apple.com;reboot'"));
+        assert!(errors[3].starts_with("audit_flags 'lo,aa' must be comma separated and include "));
+        assert!(errors[4].starts_with("profile_identifier 'cis'"));
+    }
+}
@@ -0,0 +1,1155 @@
+use crate::checks::{read_combined_output, read_output};
+use crate::commands::BashCommand;
+
+// Journal used by apply and rollback when --journal is not given
+pub const DEFAULT_PATH: &str = "cis-journal.txt";
+
+const HEADER: &str = "# CIS_Hardening rollback journal (one change per line, tab separated)";
+
+// Keys changed by the c34 shell script, depending on the CPU
+const PMSET_KEYS: [&str; 7] = [
+    "sleep",
+    "displaysleep",
+    "hibernatemode",
+    "standbydelaylow",
+    "standbydelayhigh",
+    "highstandbythreshold",
+    "destroyfvkeyonstandby",
+];
+
+// How a recorded change is undone
+pub enum Restore {
+    Run { executable: String, args: Vec<String>, stdin: Option<String> }, // Command putting the previous value back
+    WriteFile { path: String, mode: u32, uid: u32, gid: u32, content: Vec<u8> }, // Previous content of a file
+    RemoveFile(String),                                      // The file did not exist before the run
+    Ownership { path: String, mode: u32, uid: u32, gid: u32 }, // Previous permissions and owner of a path
+    Manual,                                                  // Cannot be undone automatically (see the description)
+}
+
+// Previous state of one thing a command is about to change
+pub struct JournalEntry {
+    pub control: String,
+    pub command: String,
+    pub description: String, // What was captured, e.g. "nvram boot-args was 'amfi_get_out_of_my_way=1'"
+    pub restore: Restore,
+}
+
+// Result of undoing one entry
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RollbackStatus {
+    Restored, // The previous state was put back
+    Manual,   // Nothing was done, the entry has to be restored by hand
+    Failed,   // The restore command or file operation failed
+}
+
+type Change = (String, Restore);
+
+// Journal opened in append mode, so several runs can be undone in one go
+pub struct Journal {
+    file: std::fs::File,
+    pub path: String,
+    pub recorded: usize, // Entries written by this run
+}
+
+impl Journal {
+    pub fn open(path: &str) -> std::io::Result<Journal> {
+        use std::io::Write;
+        use std::os::unix::fs::OpenOptionsExt;
+
+        // The journal holds previous file contents and authorization rights, keep it private
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .mode(0o600)
+            .open(path)?;
+        if file.metadata()?.len() == 0 {
+            writeln!(file, "{}", HEADER)?;
+        }
+        writeln!(file, "# run {}", crate::report::format_timestamp(std::time::SystemTime::now()))?;
+
+        Ok(Journal {
+            file,
+            path: path.to_string(),
+            recorded: 0,
+        })
+    }
+
+    // Captures the current state of everything the command changes, before it runs
+    pub fn record(&mut self, control: &str, command: &BashCommand) -> std::io::Result<()> {
+        use std::io::Write;
+
+        // Skipped commands change nothing, there is nothing to record
+        if command.acts_on_running_system() {
+            return Ok(());
+        }
+        for (description, restore) in capture(command) {
+            let entry = JournalEntry {
+                control: control.to_string(),
+                command: command.name().to_string(),
+                description,
+                restore,
+            };
+            writeln!(self.file, "{}", entry.to_line())?;
+            self.recorded += 1;
+        }
+        // Written before the command runs, so an interrupted run can still be undone
+        self.file.flush()
+    }
+}
+
+// Reads every entry of a journal, in the order the changes were made
+pub fn load(path: &str) -> Result<Vec<JournalEntry>, String> {
+    let content = std::fs::read_to_string(path).map_err(|error| format!("Failed to read {}: {}", path, error))?;
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(index, line)| {
+            JournalEntry::parse(line).ok_or_else(|| format!("{}:{}: malformed journal entry", path, index + 1))
+        })
+        .collect()
+}
+
+impl JournalEntry {
+    fn to_line(&self) -> String {
+        let mut fields = vec![self.control.clone(), self.command.clone()];
+        match &self.restore {
+            Restore::Run { executable, args, stdin } => {
+                fields.push("run".to_string());
+                fields.push(self.description.clone());
+                // "-" when nothing is written to stdin, "+<input>" otherwise
+                fields.push(match stdin {
+                    Some(input) => format!("+{}", input),
+                    None => "-".to_string(),
+                });
+                fields.push(executable.clone());
+                fields.extend(args.iter().cloned());
+            }
+            Restore::WriteFile { path, mode, uid, gid, content } => {
+                fields.push("write-file".to_string());
+                fields.push(self.description.clone());
+                fields.extend([path.clone(), format!("{:o}", mode), uid.to_string(), gid.to_string()]);
+                fields.push(content.iter().map(|byte| format!("{:02x}", byte)).collect());
+            }
+            Restore::RemoveFile(path) => {
+                fields.push("remove-file".to_string());
+                fields.push(self.description.clone());
+                fields.push(path.clone());
+            }
+            Restore::Ownership { path, mode, uid, gid } => {
+                fields.push("ownership".to_string());
+                fields.push(self.description.clone());
+                fields.extend([path.clone(), format!("{:o}", mode), uid.to_string(), gid.to_string()]);
+            }
+            Restore::Manual => {
+                fields.push("manual".to_string());
+                fields.push(self.description.clone());
+            }
+        }
+
+        fields.iter().map(|field| escape(field)).collect::<Vec<String>>().join("\t")
+    }
+
+    fn parse(line: &str) -> Option<JournalEntry> {
+        let fields: Vec<String> = line.split('\t').map(unescape).collect();
+        let [control, command, kind, description, rest @ ..] = fields.as_slice() else {
+            return None;
+        };
+
+        let number = |value: &String, radix: u32| u32::from_str_radix(value, radix).ok();
+        let restore = match (kind.as_str(), rest) {
+            ("run", [stdin, executable, args @ ..]) => Restore::Run {
+                executable: executable.clone(),
+                args: args.to_vec(),
+                stdin: stdin.strip_prefix('+').map(|input| input.to_string()),
+            },
+            ("write-file", [path, mode, uid, gid, content]) => Restore::WriteFile {
+                path: path.clone(),
+                mode: number(mode, 8)?,
+                uid: number(uid, 10)?,
+                gid: number(gid, 10)?,
+                content: (0..content.len())
+                    .step_by(2)
+                    .map(|index| u8::from_str_radix(content.get(index..index + 2)?, 16).ok())
+                    .collect::<Option<Vec<u8>>>()?,
+            },
+            ("remove-file", [path]) => Restore::RemoveFile(path.clone()),
+            ("ownership", [path, mode, uid, gid]) => Restore::Ownership {
+                path: path.clone(),
+                mode: number(mode, 8)?,
+                uid: number(uid, 10)?,
+                gid: number(gid, 10)?,
+            },
+            ("manual", []) => Restore::Manual,
+            _ => return None,
+        };
+
+        Some(JournalEntry {
+            control: control.clone(),
+            command: command.clone(),
+            description: description.clone(),
+            restore,
+        })
+    }
+
+    // Prints what rollback would do for this entry
+    pub fn plan(&self, step: usize) {
+        println!("[{}] {} - {}", step, self.command, self.description);
+        match &self.restore {
+            Restore::Run { executable, args, stdin } => {
+                println!("    {} {}", executable, args.join(" "));
+                if let Some(input) = stdin {
+                    println!("    stdin: {} bytes", input.len());
+                }
+            }
+            Restore::WriteFile { path, mode, content, .. } => {
+                println!("    write {} bytes to {} (mode {:o})", content.len(), path, mode)
+            }
+            Restore::RemoveFile(path) => println!("    remove {}", path),
+            Restore::Ownership { path, mode, uid, gid } => {
+                println!("    chown {}:{} and chmod {:o} {}", uid, gid, mode, path)
+            }
+            Restore::Manual => println!("    manual action required"),
+        }
+    }
+
+    // Puts the previous state back
+    pub fn restore(&self) -> (RollbackStatus, String) {
+        let result = match &self.restore {
+            Restore::Run { executable, args, stdin } => run(executable, args, stdin.as_deref()),
+            Restore::WriteFile { path, mode, uid, gid, content } => write_file(path, *mode, *uid, *gid, content),
+            Restore::RemoveFile(path) => match std::fs::remove_file(path) {
+                Err(error) if error.kind() != std::io::ErrorKind::NotFound => Err(error.to_string()),
+                _ => Ok(()),
+            },
+            Restore::Ownership { path, mode, uid, gid } => set_ownership(path, *mode, *uid, *gid),
+            Restore::Manual => return (RollbackStatus::Manual, self.description.clone()),
+        };
+
+        match result {
+            Ok(()) => (RollbackStatus::Restored, self.description.clone()),
+            Err(error) => (RollbackStatus::Failed, format!("{}: {}", self.description, error)),
+        }
+    }
+}
+
+fn run(executable: &str, args: &[String], stdin: Option<&str>) -> Result<(), String> {
+    let output = crate::runner::current()
+        .run(executable, args, stdin, crate::runner::default_timeout())
+        .map_err(|error| format!("Failed to start '{}': {}", executable, error))?;
+
+    if output.success() {
+        Ok(())
+    } else if output.timed_out {
+        Err(format!("'{}' was killed at the timeout without finishing", executable))
+    } else {
+        Err(output.stderr.trim().to_string())
+    }
+}
+
+// Replaces the file through a temporary copy so it is never left half written
+fn write_file(path: &str, mode: u32, uid: u32, gid: u32, content: &[u8]) -> Result<(), String> {
+    let write = crate::runner::FileWrite {
+        path: path.to_string(),
+        content: content.to_vec(),
+        ownership: Some((mode, uid, gid)),
+        validate: None,
+    };
+    crate::runner::current().write_file(&write).map_err(|error| error.to_string())
+}
+
+fn set_ownership(path: &str, mode: u32, uid: u32, gid: u32) -> Result<(), String> {
+    use std::os::unix::fs::PermissionsExt;
+
+    // chown clears the setuid/setgid bits, so the mode is set afterwards
+    std::os::unix::fs::chown(path, Some(uid), Some(gid))
+        .and_then(|_| std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode)))
+        .map_err(|error| error.to_string())
+}
+
+// Escapes a field so it fits on one tab separated line
+fn escape(field: &str) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn unescape(field: &str) -> String {
+    let mut value = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => value.push('\t'),
+            Some('n') => value.push('\n'),
+            Some('r') => value.push('\r'),
+            Some(other) => value.push(other),
+            None => value.push('\\'),
+        }
+    }
+    value
+}
+
+// Current state of everything a command changes, compared before and after it runs to tell
+// whether it changed anything. None when that cannot be told: the targets of the command are not
+// known, or one of them is only described (e.g. Remote Management being deactivated).
+pub fn state(command: &BashCommand) -> Option<Vec<String>> {
+    if let Some(outline) = outline(&command.argv()) {
+        return Some(outline);
+    }
+    let changes = capture(command);
+    if changes.is_empty() || changes.iter().any(|(_, restore)| matches!(restore, Restore::Manual)) {
+        return None;
+    }
+    let entries = changes.into_iter().map(|(description, restore)| JournalEntry {
+        control: String::new(),
+        command: String::new(),
+        description,
+        restore,
+    });
+    Some(entries.map(|entry| entry.to_line()).collect())
+}
+
+// State of the commands whose journal entries do not show it: whether the paths `rm` removes
+// exist, a digest of the owners and modes of the trees a recursive chmod or chown changes, the
+// services that can only be turned off, and nothing for programs that change no stored state
+fn outline(argv: &[String]) -> Option<Vec<String>> {
+    if argv.iter().any(|arg| arg == "&&") {
+        return None;
+    }
+    let (_, program, args) = split_prefix(argv)?;
+    let args: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+
+    match program.rsplit('/').next().unwrap_or(program) {
+        "rm" => Some(
+            args.iter()
+                .filter(|arg| !arg.starts_with('-'))
+                .map(|path| match std::fs::symlink_metadata(path) {
+                    Ok(_) => format!("{} exists", path),
+                    Err(_) => format!("{} does not exist", path),
+                })
+                .collect(),
+        ),
+        "chmod" | "chown" if args.contains(&"-R") => Some(permission_operands(&args).map(tree_digest).collect()),
+        "kickstart" => Some(vec![format!("ARDAgent running: {}", read_output("/usr/bin/pgrep", &["-x", "ARDAgent"]).is_some())]),
+        "dsenableroot" => Some(vec![format!(
+            "root enabled: {}",
+            read_output("/usr/bin/dscl", &[".", "-read", "/Users/root", "AuthenticationAuthority"]).is_some()
+        )]),
+        "killall" | "sntp" | "softwareupdate" => Some(Vec::new()),
+        _ => None,
+    }
+}
+
+// Number of entries of a tree and a digest of their paths, owners and modes (symbolic links are
+// not followed, as chmod -R and chown -R do not follow them)
+fn tree_digest(path: &str) -> String {
+    use std::hash::{Hash, Hasher};
+    use std::os::unix::fs::MetadataExt;
+
+    fn walk(path: &std::path::Path, hasher: &mut std::collections::hash_map::DefaultHasher, entries: &mut usize) {
+        let Ok(metadata) = std::fs::symlink_metadata(path) else {
+            return;
+        };
+        (path, metadata.mode(), metadata.uid(), metadata.gid()).hash(hasher);
+        *entries += 1;
+        if metadata.is_dir() {
+            let mut children: Vec<std::path::PathBuf> = std::fs::read_dir(path)
+                .map(|entries| entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).collect())
+                .unwrap_or_default();
+            children.sort();
+            for child in children {
+                walk(&child, hasher, entries);
+            }
+        }
+    }
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    let mut entries = 0;
+    walk(std::path::Path::new(path), &mut hasher, &mut entries);
+    format!("{}: {} entries, owners and modes {:016x}", path, entries, hasher.finish())
+}
+
+// Works out what a command changes and reads the current value of each target
+fn capture(command: &BashCommand) -> Vec<Change> {
+    let sudo = ["sudo".to_string()];
+
+    match command.name() {
+        // Shell scripts are not parsed, their targets are known by name
+        "c32_ensure_admin_password_for_system_preferences" => {
+            security(&Tool::new(&sudo, "/usr/bin/security"), &["authorizationdb", "write", "system.preferences"])
+        }
+        "c34_configure_sleep_settings_based_on_cpu" => {
+            let tool = Tool::new(&sudo, "/usr/bin/pmset");
+            PMSET_KEYS.iter().flat_map(|key| pmset(&tool, &["-a", key, ""])).collect()
+        }
+        "c79_set_login_window_banner" => file(&crate::root::path("/Library/Security/PolicyBanner.txt")),
+        _ => command
+            .argv()
+            .split(|arg| arg == "&&")
+            .flat_map(segment)
+            .collect(),
+    }
+}
+
+// Program invoked by a command, with the sudo prefix it runs under
+struct Tool<'a> {
+    prefix: &'a [String], // e.g. ["sudo"] or ["sudo", "-u", "alice"]
+    program: &'a str,
+}
+
+impl<'a> Tool<'a> {
+    fn new(prefix: &'a [String], program: &'a str) -> Tool<'a> {
+        Tool { prefix, program }
+    }
+
+    fn argv(&self, args: &[&str]) -> Vec<String> {
+        self.prefix
+            .iter()
+            .map(|arg| arg.as_str())
+            .chain(std::iter::once(self.program))
+            .chain(args.iter().copied())
+            .map(|arg| arg.to_string())
+            .collect()
+    }
+
+    // Values are read as the same user the command runs as
+    fn read(&self, args: &[&str]) -> Option<String> {
+        let argv = self.argv(args);
+        let args: Vec<&str> = argv[1..].iter().map(|arg| arg.as_str()).collect();
+        read_output(&argv[0], &args)
+    }
+
+    fn read_combined(&self, args: &[&str]) -> Option<String> {
+        let argv = self.argv(args);
+        let args: Vec<&str> = argv[1..].iter().map(|arg| arg.as_str()).collect();
+        read_combined_output(&argv[0], &args)
+    }
+
+    fn restore(&self, args: &[&str], stdin: Option<String>) -> Restore {
+        let mut argv = self.argv(args);
+        let executable = argv.remove(0);
+        Restore::Run { executable, args: argv, stdin }
+    }
+
+    // " (user alice)" for commands run with `sudo -u`
+    fn user_suffix(&self) -> String {
+        match self.prefix {
+            [_, flag, user] if flag == "-u" => format!(" (user {})", user),
+            _ => String::new(),
+        }
+    }
+}
+
+// Sudo prefix, program and arguments of a command line
+fn split_prefix(argv: &[String]) -> Option<(&[String], &String, &[String])> {
+    let prefix_len = match argv {
+        [sudo, flag, _, ..] if sudo == "sudo" && flag == "-u" => 3,
+        [sudo, ..] if sudo == "sudo" => 1,
+        _ => 0,
+    };
+    let (prefix, rest) = argv.split_at(prefix_len.min(argv.len()));
+    let (program, args) = rest.split_first()?;
+    Some((prefix, program, args))
+}
+
+// Dispatches one command line (commands joined with "&&" are split beforehand)
+fn segment(argv: &[String]) -> Vec<Change> {
+    let Some((prefix, program, args)) = split_prefix(argv) else {
+        return Vec::new();
+    };
+    let tool = Tool::new(prefix, program);
+    let args: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+
+    match program.rsplit('/').next().unwrap_or(program) {
+        "defaults" => defaults(&tool, &args),
+        "nvram" => nvram(&tool, &args),
+        "pwpolicy" => pwpolicy(&tool, &args),
+        "launchctl" => launchctl(&tool, &args),
+        "systemsetup" => systemsetup(&tool, &args),
+        "socketfilterfw" => socketfilterfw(&tool, &args),
+        "cupsctl" => cupsctl(&tool, &args),
+        "spctl" => spctl(&tool, &args),
+        "sysadminctl" => sysadminctl(&tool, &args),
+        "pmset" => pmset(&tool, &args),
+        "dscl" => dscl(&tool, &args),
+        "security" => security(&tool, &args),
+        "AssetCacheManagerUtil" => asset_cache(&tool, &args),
+        "dsenableroot" => root_account(&args),
+        "kickstart" if args.contains(&"-deactivate") => vec![(
+            "Remote Management was deactivated; turn it back on in Sharing if it was in use".to_string(),
+            Restore::Manual,
+        )],
+        "chmod" | "chown" => permissions(&args),
+        "rm" => removed(&args),
+        "sed" if args.contains(&"-i") => args.last().map(|path| file(path)).unwrap_or_default(),
+        "write" => args.last().map(|path| file(path)).unwrap_or_default(), // Replaced natively (FileWrite)
+        _ => Vec::new(), // killall, sntp, softwareupdate, echo and error change no stored state
+    }
+}
+
+// `defaults [-currentHost] write|delete <domain | -app <name>> <key> ...`
+fn defaults(tool: &Tool, args: &[&str]) -> Vec<Change> {
+    let (scope, args) = match args.split_first() {
+        Some((&"-currentHost", rest)) => (vec!["-currentHost"], rest),
+        _ => (Vec::new(), args),
+    };
+    let Some((&verb, rest)) = args.split_first() else {
+        return Vec::new();
+    };
+    if verb != "write" && verb != "delete" {
+        return Vec::new();
+    }
+    let domain_len = if rest.first() == Some(&"-app") { 2 } else { 1 };
+    if rest.len() <= domain_len {
+        return Vec::new();
+    }
+    let (domain, rest) = rest.split_at(domain_len);
+    let key = rest[0];
+    if key.starts_with('-') {
+        return Vec::new(); // A value without a key (e.g. `write <domain> -int 1`) is rejected by defaults
+    }
+
+    let with = |verb: &'static str| -> Vec<&str> {
+        scope.iter().copied().chain([verb]).chain(domain.iter().copied()).chain([key]).collect()
+    };
+    let label = format!("{} {}{}", domain.join(" "), key, tool.user_suffix());
+
+    let value_type = tool
+        .read(&with("read-type"))
+        .and_then(|output| output.strip_prefix("Type is ").map(|kind| kind.trim().to_string()));
+    let Some(value_type) = value_type else {
+        return match verb {
+            "write" => vec![(format!("{} was not set", label), tool.restore(&with("delete"), None))],
+            _ => Vec::new(),
+        };
+    };
+    let value = tool.read(&with("read")).unwrap_or_default();
+
+    let typed = match value_type.as_str() {
+        "boolean" => Some(["-bool", if value == "1" { "true" } else { "false" }]),
+        "integer" => Some(["-int", value.as_str()]),
+        "float" => Some(["-float", value.as_str()]),
+        "string" => Some(["-string", value.as_str()]),
+        _ => None,
+    };
+    let mut write = with("write");
+    match (typed, value_type.as_str()) {
+        (Some(typed), _) => write.extend(typed),
+        // `defaults read` prints dictionaries and arrays in the plist syntax `defaults write` accepts
+        (None, "dictionary" | "array") => write.push(value.as_str()),
+        _ => {
+            return vec![(
+                format!("{} was the {} value '{}', restore it by hand", label, value_type, value),
+                Restore::Manual,
+            )]
+        }
+    }
+    vec![(format!("{} was {} '{}'", label, value_type, value), tool.restore(&write, None))]
+}
+
+// `nvram <name>=<value>`
+fn nvram(tool: &Tool, args: &[&str]) -> Vec<Change> {
+    args.iter()
+        .filter_map(|arg| arg.split_once('=').map(|(name, _)| name))
+        .map(|name| match tool.read(&[name]) {
+            // Printed as "<name>\t<value>"
+            Some(output) => {
+                let value = output.split_once('\t').map(|(_, value)| value).unwrap_or("");
+                (
+                    format!("nvram {} was '{}'", name, value),
+                    tool.restore(&[&format!("{}={}", name, value)], None),
+                )
+            }
+            None => (format!("nvram {} was not set", name), tool.restore(&["-d", name], None)),
+        })
+        .collect()
+}
+
+// `pwpolicy -n <node> -setglobalpolicy "<key>=<value> ..."`
+fn pwpolicy(tool: &Tool, args: &[&str]) -> Vec<Change> {
+    let Some(position) = args.iter().position(|arg| *arg == "-setglobalpolicy") else {
+        return Vec::new();
+    };
+    let node = &args[..position];
+    let current = tool
+        .read(&[node, &["-getglobalpolicy"]].concat())
+        .unwrap_or_default();
+
+    args.get(position + 1)
+        .into_iter()
+        .flat_map(|setting| setting.split_whitespace())
+        .filter_map(|pair| pair.split_once('=').map(|(key, _)| key))
+        .map(|key| {
+            let previous = current
+                .split_whitespace()
+                .filter_map(|pair| pair.split_once('='))
+                .find(|(name, _)| *name == key);
+            match previous {
+                Some((_, value)) => (
+                    format!("global password policy {} was {}", key, value),
+                    tool.restore(&[node, &["-setglobalpolicy", &format!("{}={}", key, value)]].concat(), None),
+                ),
+                None => (
+                    format!("global password policy {} was not set; pwpolicy cannot unset a single key", key),
+                    Restore::Manual,
+                ),
+            }
+        })
+        .collect()
+}
+
+// `launchctl disable <domain>/<label>` and `launchctl load|unload -w <plist>`
+fn launchctl(tool: &Tool, args: &[&str]) -> Vec<Change> {
+    match args {
+        ["disable", target] => {
+            let Some((domain, label)) = target.rsplit_once('/') else {
+                return Vec::new();
+            };
+            let needle = format!("\"{}\"", label);
+            let disabled = tool.read(&["print-disabled", domain]).is_some_and(|output| {
+                output.lines().any(|line| {
+                    line.contains(&needle) && (line.contains("=> true") || line.contains("=> disabled"))
+                })
+            });
+            if disabled {
+                return Vec::new();
+            }
+            vec![(format!("{} was enabled", target), tool.restore(&["enable", target], None))]
+        }
+        [verb @ ("load" | "unload"), "-w", plist] => {
+            let label = std::path::Path::new(plist)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let loaded = tool.read(&["list", &label]).is_some();
+            match (*verb, loaded) {
+                ("load", false) => vec![(format!("{} was not loaded", label), tool.restore(&["unload", "-w", plist], None))],
+                ("unload", true) => vec![(format!("{} was loaded", label), tool.restore(&["load", "-w", plist], None))],
+                _ => Vec::new(),
+            }
+        }
+        _ => Vec::new(),
+    }
+}
+
+// `systemsetup -set<option> <value>`, read back with `-get<option>` (e.g. "Time Zone: America/Sao_Paulo")
+fn systemsetup(tool: &Tool, args: &[&str]) -> Vec<Change> {
+    let [set, value] = args else {
+        return Vec::new();
+    };
+    let Some(option) = set.strip_prefix("-set") else {
+        return Vec::new();
+    };
+    let Some((_, previous)) = tool
+        .read_combined(&[&format!("-get{}", option)])
+        .and_then(|output| output.rsplit_once(": ").map(|(label, value)| (label.to_string(), value.trim().to_string())))
+    else {
+        return Vec::new();
+    };
+    let previous = match previous.as_str() {
+        "On" | "Off" => previous.to_lowercase(),
+        _ => previous,
+    };
+    if previous.eq_ignore_ascii_case(value) {
+        return Vec::new();
+    }
+
+    // -setremotelogin asks for confirmation, -f skips the prompt
+    let mut restore = vec![*set, previous.as_str()];
+    if option == "remotelogin" {
+        restore.insert(0, "-f");
+    }
+    vec![(format!("systemsetup {} was {}", option, previous), tool.restore(&restore, None))]
+}
+
+// `socketfilterfw --set<option> <value>`, read back with `--get<option>` (e.g. "Log mode is on")
+fn socketfilterfw(tool: &Tool, args: &[&str]) -> Vec<Change> {
+    let [set, value] = args else {
+        return Vec::new();
+    };
+    let Some(option) = set.strip_prefix("--set") else {
+        return Vec::new();
+    };
+    let Some(output) = tool.read(&[&format!("--get{}", option)]) else {
+        return Vec::new();
+    };
+    let previous = match output.split_whitespace().last().unwrap_or("").trim_end_matches('.') {
+        "enabled" => "on".to_string(),
+        "disabled" => "off".to_string(),
+        word => word.to_lowercase(),
+    };
+    if previous.is_empty() || previous == *value {
+        return Vec::new();
+    }
+    vec![(format!("firewall {} was {}", option, previous), tool.restore(&[set, &previous], None))]
+}
+
+// `cupsctl --[no-]<setting>`, read back from the `_<setting>=0|1` lines printed by `cupsctl`
+fn cupsctl(tool: &Tool, args: &[&str]) -> Vec<Change> {
+    let current = tool.read(&[]).unwrap_or_default();
+    args.iter()
+        .filter_map(|arg| arg.strip_prefix("--"))
+        .filter_map(|setting| {
+            let (enable, name) = match setting.strip_prefix("no-") {
+                Some(name) => (false, name),
+                None => (true, setting),
+            };
+            let line = format!("_{}=", name.replace('-', "_"));
+            let enabled = current.lines().find_map(|entry| entry.strip_prefix(&line))? == "1";
+            if enabled == enable {
+                return None;
+            }
+            let flag = if enabled { format!("--{}", name) } else { format!("--no-{}", name) };
+            Some((
+                format!("cups {} was {}", name, if enabled { "on" } else { "off" }),
+                tool.restore(&[&flag], None),
+            ))
+        })
+        .collect()
+}
+
+// `spctl --master-enable|--master-disable`, read back with `spctl --status`
+fn spctl(tool: &Tool, args: &[&str]) -> Vec<Change> {
+    let enable = match args {
+        ["--master-enable"] => true,
+        ["--master-disable"] => false,
+        _ => return Vec::new(),
+    };
+    let Some(status) = tool.read_combined(&["--status"]) else {
+        return Vec::new();
+    };
+    let enabled = status.contains("assessments enabled");
+    if enabled == enable {
+        return Vec::new();
+    }
+    let flag = if enabled { "--master-enable" } else { "--master-disable" };
+    vec![(format!("Gatekeeper was {}", status), tool.restore(&[flag], None))]
+}
+
+// `sysadminctl -screenLock <value>` and `sysadminctl -smbGuestAccess on|off`, read back with `<option> status`
+fn sysadminctl(tool: &Tool, args: &[&str]) -> Vec<Change> {
+    let Some((&option, value)) = args.split_first() else {
+        return Vec::new();
+    };
+    if value == ["status"] {
+        return Vec::new();
+    }
+    let Some(status) = tool.read_combined(&[option, "status"]) else {
+        return Vec::new();
+    };
+
+    // "screenLock delay is 300 seconds", "screenLock is off" or "SMB guest access disabled"
+    let words: Vec<&str> = status.split_whitespace().collect();
+    let previous: Vec<&str> = if words.contains(&"off") || words.contains(&"disabled") {
+        vec!["off"]
+    } else if words.contains(&"immediate") {
+        vec!["immediate"]
+    } else if words.contains(&"enabled") {
+        vec!["on"]
+    } else if let Some(index) = words.iter().position(|word| word.starts_with("second")) {
+        vec![words[index.saturating_sub(1)], "seconds"]
+    } else {
+        return Vec::new();
+    };
+    if previous == value {
+        return Vec::new();
+    }
+
+    let restore: Vec<&str> = std::iter::once(option).chain(previous.iter().copied()).collect();
+    vec![(format!("sysadminctl {} was {}", option, previous.join(" ")), tool.restore(&restore, None))]
+}
+
+// `pmset -a <key> <value> ...`, read back from `pmset -g`
+fn pmset(tool: &Tool, args: &[&str]) -> Vec<Change> {
+    let Some(("-a", settings)) = args.split_first().map(|(scope, rest)| (*scope, rest)) else {
+        return Vec::new();
+    };
+    let current = tool.read(&["-g"]).unwrap_or_default();
+
+    settings
+        .chunks(2)
+        .filter_map(|pair| {
+            let key = pair[0];
+            let previous = current.lines().find_map(|line| {
+                let mut parts = line.split_whitespace();
+                (parts.next()? == key).then(|| parts.next()).flatten()
+            })?;
+            if pair.get(1) == Some(&previous) {
+                return None;
+            }
+            Some((format!("pmset {} was {}", key, previous), tool.restore(&["-a", key, previous], None)))
+        })
+        .collect()
+}
+
+// `dscl . -delete <record> <attribute>`, read back with `dscl . -read`
+fn dscl(tool: &Tool, args: &[&str]) -> Vec<Change> {
+    let [node, "-delete", record, attribute] = args else {
+        return Vec::new();
+    };
+    // Printed as "<attribute>: <value>" (or with the value on the next line)
+    let Some(value) = tool
+        .read(&[node, "-read", record, attribute])
+        .and_then(|output| output.split_once(':').map(|(_, value)| value.trim().to_string()))
+    else {
+        return Vec::new();
+    };
+    vec![(
+        format!("{} {} was '{}'", record, attribute, value),
+        tool.restore(&[node, "-create", record, attribute, &value], None),
+    )]
+}
+
+// `security authorizationdb write <right> ...`, the previous rule is written back from stdin
+fn security(tool: &Tool, args: &[&str]) -> Vec<Change> {
+    let ["authorizationdb", "write", right, ..] = args else {
+        return Vec::new();
+    };
+    let Some(rule) = tool.read(&["authorizationdb", "read", right]) else {
+        return Vec::new();
+    };
+    vec![(
+        format!("authorization right {} was saved", right),
+        tool.restore(&["authorizationdb", "write", right], Some(rule)),
+    )]
+}
+
+// `AssetCacheManagerUtil deactivate`
+fn asset_cache(tool: &Tool, args: &[&str]) -> Vec<Change> {
+    if args != ["deactivate"] {
+        return Vec::new();
+    }
+    let active = tool
+        .read_combined(&["isActivated"])
+        .is_some_and(|output| output.contains("true"));
+    if !active {
+        return Vec::new();
+    }
+    vec![("Content caching was activated".to_string(), tool.restore(&["activate"], None))]
+}
+
+// `dsenableroot -d`: the root password cannot be recovered, only reported
+fn root_account(args: &[&str]) -> Vec<Change> {
+    let enabled = read_output("/usr/bin/dscl", &[".", "-read", "/Users/root", "AuthenticationAuthority"]).is_some();
+    if args != ["-d"] || !enabled {
+        return Vec::new();
+    }
+    vec![(
+        "The root account was enabled; re-enable it with `dsenableroot` and a new password if needed".to_string(),
+        Restore::Manual,
+    )]
+}
+
+// Paths of `chmod|chown [-R] <mode|owner> <path>...`
+fn permission_operands<'a>(args: &'a [&'a str]) -> impl Iterator<Item = &'a str> + 'a {
+    args.iter()
+        .filter(|arg| !arg.starts_with('-') || arg.len() == 1)
+        .skip(1) // Mode or owner
+        .copied()
+}
+
+// `chmod|chown [-R] <mode|owner> <path>...`
+fn permissions(args: &[&str]) -> Vec<Change> {
+    let recursive = args.contains(&"-R");
+    let mut changes = Vec::new();
+    for path in permission_operands(args) {
+        collect_ownership(std::path::Path::new(path), recursive, &mut changes);
+    }
+    changes
+}
+
+// Owner and mode of a path. The contents of a directory changed recursively are not walked (a
+// home folder holds far too many files to record), they are only described.
+fn collect_ownership(path: &std::path::Path, recursive: bool, changes: &mut Vec<Change>) {
+    use std::os::unix::fs::MetadataExt;
+
+    // Symbolic links are not followed, chmod -R and chown -R do not follow them either
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return;
+    };
+    if metadata.file_type().is_symlink() {
+        return;
+    }
+
+    let (mode, uid, gid) = (metadata.mode() & 0o7777, metadata.uid(), metadata.gid());
+    let display = path.to_string_lossy().to_string();
+    changes.push((
+        format!("{} was {:o} {}:{}", display, mode, uid, gid),
+        Restore::Ownership { path: display.clone(), mode, uid, gid },
+    ));
+
+    if recursive && metadata.is_dir() {
+        changes.push((
+            format!("The contents of {} were changed recursively, restore their owner and mode by hand if needed", display),
+            Restore::Manual,
+        ));
+    }
+}
+
+// `rm [-R] <path>...`: files are saved, directories can only be reported
+fn removed(args: &[&str]) -> Vec<Change> {
+    args.iter()
+        .filter(|arg| !arg.starts_with('-'))
+        .flat_map(|path| match std::fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.is_dir() => vec![(
+                format!("{} was removed, restore it from a backup if needed", path),
+                Restore::Manual,
+            )],
+            Ok(_) => file(path),
+            Err(_) => Vec::new(),
+        })
+        .collect()
+}
+
+// Saves the whole content, permissions and owner of a file (or notes that it did not exist)
+fn file(path: &str) -> Vec<Change> {
+    use std::os::unix::fs::MetadataExt;
+
+    match std::fs::metadata(path).and_then(|metadata| Ok((metadata, std::fs::read(path)?))) {
+        Ok((metadata, content)) => vec![(
+            format!("{} was saved ({} bytes)", path, content.len()),
+            Restore::WriteFile {
+                path: path.to_string(),
+                mode: metadata.mode() & 0o7777,
+                uid: metadata.uid(),
+                gid: metadata.gid(),
+                content,
+            },
+        )],
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            vec![(format!("{} did not exist", path), Restore::RemoveFile(path.to_string()))]
+        }
+        Err(error) => vec![(format!("{} could not be saved ({}), restore it by hand", path, error), Restore::Manual)],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{fixture, simulate};
+
+    fn argv(line: &str) -> Vec<String> {
+        line.split_whitespace().map(|word| word.to_string()).collect()
+    }
+
+    fn entry(description: &str, restore: Restore) -> JournalEntry {
+        JournalEntry {
+            control: "c1".to_string(),
+            command: "c1_command".to_string(),
+            description: description.to_string(),
+            restore,
+        }
+    }
+
+    // Journal lines of what a command changes, without the control and command fields
+    fn captured(command: &BashCommand) -> Vec<String> {
+        capture(command)
+            .into_iter()
+            .map(|(description, restore)| entry(&description, restore).to_line().replacen("c1\tc1_command\t", "", 1))
+            .collect()
+    }
+
+    #[test]
+    fn entries_survive_a_round_trip() {
+        let entries = [
+            entry(
+                "login window text was 'Line one\n\tindented \\ done'",
+                Restore::Run {
+                    executable: "sudo".to_string(),
+                    args: argv("/usr/bin/defaults write /Library/Preferences/com.apple.loginwindow LoginwindowText"),
+                    stdin: Some("a\tb\r\n".to_string()),
+                },
+            ),
+            entry("nvram boot-args was not set", Restore::Run { executable: "sudo".to_string(), args: argv("/usr/sbin/nvram -d boot-args"), stdin: None }),
+            entry("empty stdin", Restore::Run { executable: "/bin/true".to_string(), args: Vec::new(), stdin: Some(String::new()) }),
+            entry(
+                "/etc/pam.d/sudo was saved (4 bytes)",
+                Restore::WriteFile { path: "/etc/pam.d/sudo".to_string(), mode: 0o4755, uid: 0, gid: 80, content: vec![0, 9, 0xff, b'\n'] },
+            ),
+            entry("/etc/exports did not exist", Restore::RemoveFile("/etc/exports".to_string())),
+            entry("/var/audit was 700 0:0", Restore::Ownership { path: "/var/audit".to_string(), mode: 0o700, uid: 0, gid: 0 }),
+            entry("Remote Management was deactivated", Restore::Manual),
+        ];
+
+        for entry in &entries {
+            let line = entry.to_line();
+            assert!(!line.contains('\n') && !line.contains('\r'), "{:?}", line);
+            let parsed = JournalEntry::parse(&line).expect("a written line can be read back");
+            assert_eq!((parsed.control.as_str(), parsed.command.as_str()), ("c1", "c1_command"));
+            assert_eq!(parsed.description, entry.description);
+            assert_eq!(parsed.to_line(), line);
+        }
+
+        match JournalEntry::parse(&entries[0].to_line()).expect("run entry").restore {
+            Restore::Run { stdin, .. } => assert_eq!(stdin.as_deref(), Some("a\tb\r\n")),
+            _ => panic!("run entry read back as another kind"),
+        }
+        match JournalEntry::parse(&entries[1].to_line()).expect("run entry").restore {
+            Restore::Run { stdin, args, .. } => assert_eq!((stdin, args.len()), (None, 3)),
+            _ => panic!("run entry read back as another kind"),
+        }
+        match JournalEntry::parse(&entries[3].to_line()).expect("write entry").restore {
+            Restore::WriteFile { mode, gid, content, .. } => assert_eq!((mode, gid, content), (0o4755, 80, vec![0, 9, 0xff, b'\n'])),
+            _ => panic!("write entry read back as another kind"),
+        }
+    }
+
+    #[test]
+    fn malformed_entries_are_rejected() {
+        for line in [
+            "c1\tc1_command\tmanual",
+            "c1\tc1_command\tmanual\tdescription\textra",
+            "c1\tc1_command\tunknown\tdescription",
+            "c1\tc1_command\trun\tdescription\t-",
+            "c1\tc1_command\tremove-file\tdescription",
+            "c1\tc1_command\townership\tdescription\t/var/audit\t789\t0\t0",
+            "c1\tc1_command\twrite-file\tdescription\t/etc/hosts\t644\t0\t0\tabc",
+            "c1\tc1_command\twrite-file\tdescription\t/etc/hosts\t644\t0\t0\tzz",
+        ] {
+            assert!(JournalEntry::parse(line).is_none(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn defaults_are_restored_to_their_previous_value() {
+        const DOMAIN: &str = "/Library/Preferences/com.apple.SoftwareUpdate AutomaticCheckEnabled";
+        simulate(&format!(
+            "$ sudo /usr/bin/defaults read-type {}\nType is boolean\n$ sudo /usr/bin/defaults read {}\n0\n",
+            DOMAIN, DOMAIN
+        ));
+        let command = crate::commands::c3_enable_auto_update();
+        let expected = vec![format!(
+            "run\t{} was boolean '0'\t-\tsudo\t/usr/bin/defaults\twrite\t{}\t-bool\tfalse",
+            DOMAIN,
+            DOMAIN.replace(' ', "\t")
+        )];
+        assert_eq!(captured(&command), expected);
+        assert_eq!(state(&command), Some(vec![format!("\t\t{}", expected[0])]));
+
+        // A key that was not set is deleted again
+        simulate(&format!("$1 sudo /usr/bin/defaults read-type {}\n2> does not exist\n", DOMAIN));
+        assert_eq!(
+            captured(&command),
+            vec![format!(
+                "run\t{} was not set\t-\tsudo\t/usr/bin/defaults\tdelete\t{}",
+                DOMAIN,
+                DOMAIN.replace(' ', "\t")
+            )]
+        );
+    }
+
+    #[test]
+    fn permissions_are_restored_to_their_previous_owner_and_mode() {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        let root = fixture("journal-ownership", &[("/etc/security/audit_control", "flags:lo\n"), ("/var/audit/current", "")]);
+        let file = format!("{}/etc/security/audit_control", root);
+        std::fs::set_permissions(&file, std::fs::Permissions::from_mode(0o644)).expect("chmod");
+        let metadata = std::fs::metadata(&file).expect("fixture file");
+        let owner = format!("{}\t{}", metadata.uid(), metadata.gid());
+
+        assert_eq!(
+            captured(&crate::commands::c41_2_chmod_audit_control()),
+            vec![format!("ownership\t{} was 644 {}:{}\t{}\t644\t{}", file, metadata.uid(), metadata.gid(), file, owner)]
+        );
+
+        // The contents of a directory are described, and its state is a digest of the whole tree
+        let command = crate::commands::c41_4_chmod_var_audit();
+        let changes = captured(&command);
+        assert_eq!(changes.len(), 2, "{:?}", changes);
+        assert!(changes[0].starts_with(&format!("ownership\t{}/var/audit/ was ", root)), "{}", changes[0]);
+        assert!(changes[1].starts_with("manual\tThe contents of "), "{}", changes[1]);
+        let state = state(&command).expect("chmod -R is outlined");
+        assert!(state[0].contains("/var/audit/: 2 entries, owners and modes "), "{:?}", state);
+    }
+
+    #[test]
+    fn removed_files_are_saved_and_directories_described() {
+        let root = fixture("journal-removed", &[("/etc/exports", "/Users -ro\n"), ("/Users/Guest/Desktop/note", "")]);
+        simulate("$ sudo /bin/launchctl print-disabled system\n\"com.apple.nfsd\" => enabled\n");
+        let exports = format!("{}/etc/exports", root);
+        let mode = {
+            use std::os::unix::fs::MetadataExt;
+            let metadata = std::fs::metadata(&exports).expect("fixture file");
+            format!("{:o}\t{}\t{}", metadata.mode() & 0o7777, metadata.uid(), metadata.gid())
+        };
+
+        // NFS is turned off and its exports removed in one command line
+        let command = crate::commands::c45_disable_nfs_server();
+        let expected = vec![
+            "run\tsystem/com.apple.nfsd was enabled\t-\tsudo\t/bin/launchctl\tenable\tsystem/com.apple.nfsd".to_string(),
+            format!("write-file\t{} was saved (11 bytes)\t{}\t{}\t{}", exports, exports, mode, "2f5573657273202d726f0a"),
+        ];
+        assert_eq!(captured(&command), expected);
+        assert_eq!(state(&command).map(|state| state.len()), Some(2));
+
+        let command = crate::commands::c70_remove_guest_home_folder();
+        assert_eq!(
+            captured(&command),
+            vec![format!("manual\t{}/Users/Guest was removed, restore it from a backup if needed", root)]
+        );
+        assert_eq!(state(&command), Some(vec![format!("{}/Users/Guest exists", root)]));
+    }
+
+    #[test]
+    fn written_files_are_saved_or_removed() {
+        let root = fixture("journal-write", &[]);
+        let drop_in = format!("{}{}", root, crate::sudoers::CIS_DROP_IN);
+        let command = crate::commands::c59_configure_sudo_settings();
+        assert_eq!(captured(&command), vec![format!("remove-file\t{} did not exist\t{}", drop_in, drop_in)]);
+
+        std::fs::create_dir_all(format!("{}/etc/sudoers.d", root)).expect("fixture folder");
+        std::fs::write(&drop_in, "Defaults timestamp_timeout=5\n").expect("fixture file");
+        let changes = captured(&command);
+        assert_eq!(changes.len(), 1);
+        assert!(changes[0].starts_with(&format!("write-file\t{} was saved (29 bytes)\t{}\t", drop_in, drop_in)), "{}", changes[0]);
+        assert_eq!(state(&command).map(|state| state.len()), Some(1));
+    }
+
+    #[test]
+    fn removed_paths_are_compared_by_existence() {
+        let root = fixture("journal-rm", &[("/Library/Old/config", "x")]);
+        let command = argv(&format!("sudo /bin/rm -rf {}/Library/Old {}/Library/Gone", root, root));
+        assert_eq!(
+            outline(&command).expect("rm is outlined"),
+            vec![format!("{}/Library/Old exists", root), format!("{}/Library/Gone does not exist", root)]
+        );
+
+        std::fs::remove_dir_all(format!("{}/Library/Old", root)).expect("removed");
+        assert_eq!(
+            outline(&command).expect("rm is outlined"),
+            vec![format!("{}/Library/Old does not exist", root), format!("{}/Library/Gone does not exist", root)]
+        );
+    }
+
+    #[test]
+    fn recursive_permissions_are_compared_by_digest() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = fixture("journal-chmod", &[("/Users/bob/Documents/a.txt", "a"), ("/Users/bob/b.txt", "b")]);
+        let command = argv(&format!("sudo /bin/chmod -R og-rwx {}/Users/bob", root));
+        let before = outline(&command).expect("chmod -R is outlined");
+        assert_eq!(before.len(), 1);
+        assert!(before[0].contains(": 4 entries, owners and modes "), "{}", before[0]);
+        assert_eq!(outline(&command), Some(before.clone()), "a tree left as it was has the same digest");
+
+        // A file deep in the tree changes the digest
+        let file = format!("{}/Users/bob/Documents/a.txt", root);
+        std::fs::set_permissions(&file, std::fs::Permissions::from_mode(0o600)).expect("chmod");
+        assert_ne!(outline(&command), Some(before));
+    }
+
+    #[test]
+    fn programs_without_stored_state_change_nothing() {
+        assert_eq!(outline(&argv("sudo /usr/sbin/softwareupdate -l")), Some(Vec::new()));
+        assert_eq!(outline(&argv("sudo sntp -sS time.apple.com")), Some(Vec::new()));
+        assert_eq!(outline(&argv("sudo /usr/bin/defaults write com.apple.x Key -bool true")), None);
+        assert_eq!(outline(&argv("sudo /bin/chmod o-w /etc/hosts")), None, "one path is captured with its owner and mode");
+    }
+}
@@ -1,125 +1,479 @@
+mod asl;
+mod audit_control;
+mod browser;
+mod catalog;
+mod checks;
+mod cli;
 mod commands;
+mod config;
+mod journal;
+mod os;
+mod plist;
+mod post_action;
+mod preflight;
+mod profile;
+mod report;
+mod root;
+mod runner;
+mod scheduler;
+mod sudoers;
+mod summary;
+#[cfg(test)]
+mod testing;
+mod users;
+use catalog::Control;
 use commands::*;
+use report::Report;
+use summary::{AuditSummary, ControlAudit, ControlRun, RunSummary};
 
 fn main() {
-    let commands: Vec<BashCommand> = vec![
-        vec![
-            // c1_enable_os_autoupdate(),
-            c2_ensure_apple_software_is_current(),
-            c3_enable_auto_update(),
-            c4_enable_download_new_updates(),
-            c5_enable_app_update_installation(),
-            c6_1_enable_system_data_files(),
-            c6_2_enable_security_updates(),
-            c7_1_disable_bluetooth(),
-            c7_2_restart_bluetooth_daemon(),
-            ],
-        c8_show_bluetooth_status(), // Already a Vec<BashCommand>
-        vec![
-            c9_1_set_timezone(),
-            c9_2_set_network_time_server(),
-            c9_3_enable_network_time(),
-            c10_sync_time()
-            ],
-        c11_set_screensaver_inactivity_interval(),
-        c12_ensure_secure_screensaver_corners(),
-        vec![
-            c13_disable_remote_apple_events(),
-            c14_disable_internet_sharing(),
-            c15_disable_screen_sharing(),
-            c16_disable_printer_sharing(),
-            c17_disable_remote_login(),
-            c18_disable_dvd_cd_sharing(),
-            c19_disable_file_sharing(),
-            c20_disable_remote_management(),
-            c21_disable_content_caching(),
-            ],
-        c22_disable_airdrop(),
-        c23_disable_media_sharing(),
-        c24_disable_airplay_receiver(),
-        vec![
-            c25_enable_firewall(),
-            c26_enable_firewall_stealth_mode(),
-            c27_1_enable_location_services(),
-            c27_2_restart_location_services(),
-            c28_1_disable_diagnostic_data(),
-            ],
-        c28_2_disable_diagnostic_data_per_user(),
-        c29_enable_limit_ad_tracking(),
-        vec![
-            c30_enable_gatekeeper(),
-            c31_enable_custom_login_message(),
-            c32_ensure_admin_password_for_system_preferences(),
-            c33_require_password_to_wake(),
-            c34_configure_sleep_settings_based_on_cpu(),
-            c35_disable_wake_for_network_access(),
-            c36_disable_powernap_based_on_cpu(),
-            c37_enable_security_auditing(),
-            c38_configure_auditing_flags(),
-            c39_configure_install_log_retention(),
-            c40_configure_auditing_retention(),
-            c41_1_chown_audit_control(),
-            c41_2_chmod_audit_control(),
-            c41_3_chown_var_audit(),
-            c41_4_chmod_var_audit(),
-            c42_1_enable_logging_mode(),
-            c42_2_configure_logging_detail(),
-            c43_disable_bonjour_advertising(),
-            c44_disable_http_server(),
-            c45_disable_nfs_server(),
-            ],
-        c46_secure_home_folders(),
-        vec![
-            c47_enable_amfi(),
-            c47_1_enable_library_validation(),
-            ],
-        c48_ensure_permissions_for_system_apps(),
-        c49_fix_world_writable_system_files(),
-        c50_fix_world_writable_library_files(),
-        vec![
-            c51_configure_password_account_lockout(),
-            c52_configure_password_min_length(),
-            c53_configure_password_requires_alpha(),
-            c54_configure_password_requires_numeric(),
-            c55_configure_password_requires_symbol(),
-            c56_configure_password_requires_mixed_case(),
-            c57_configure_password_age(),
-            c58_configure_password_history(),
-            c59_configure_sudo_settings(),
-            c60_update_progress(),
-            c61_disable_root_account(),
-            c62_disable_automatic_login(),
-            c63_prevent_admin_login_to_locked_session(),
-            c63_1_disable_fast_user_switching(),
-        ],
-        c64_remove_password_hints(),
-        c65_enable_secure_keyboard_entry(),
-        vec![
-            c66_enable_login_name_password_display(),
-            c67_disable_password_hints(),
-            c68_disable_guest_account(),
-            c69_disable_guest_shared_folders_access(),
-            c70_remove_guest_home_folder(),
-            ],
-        c71_enable_show_all_filename_extensions(),
-        c72_disable_auto_open_safe_files(),
-        c73_enable_warn_about_fraudulent_websites(),
-        c74_enable_cross_site_tracking_prevention(),
-        c75_disable_automatic_opening_of_safe_files(),
-        c76_disable_private_click_measurement(),
-        c77_enable_show_full_website_address(),
-        c78_disable_bluetooth_sharing(),
-        vec![
-            c79_set_login_window_banner(),
-            c80_restart_wazuh_agent()            
-            ]
-        ]
-        .into_iter()
-        .flatten()
+    let options = match cli::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("❌ {}\n\n{}", error, cli::usage());
+            exit(2);
+        }
+    };
+
+    if let Some(path) = &options.config {
+        match config::Config::load(path) {
+            Ok(config) => config::install(config),
+            Err(error) => {
+                eprintln!("❌ {}", error);
+                exit(2);
+            }
+        }
+    }
+
+    if let Some(path) = &options.root {
+        if let Err(error) = root::install(path) {
+            eprintln!("❌ {}", error);
+            exit(2);
+        }
+    }
+
+    // Nothing is spawned in a simulation, every invocation is recorded and listed at exit
+    if let Some(path) = &options.simulate {
+        match runner::ScriptedRunner::load(path) {
+            Ok(script) => {
+                let script: &'static runner::ScriptedRunner = Box::leak(Box::new(script));
+                runner::install(script);
+                let _ = SIMULATION.set(script);
+            }
+            Err(error) => {
+                eprintln!("❌ {}", error);
+                exit(2);
+            }
+        }
+    }
+
+    if let cli::Mode::Explain(name) = &options.mode {
+        explain(name);
+        exit(0);
+    }
+    if let cli::Mode::Help = options.mode {
+        println!("{}", cli::usage());
+        return;
+    }
+    if let cli::Mode::Rollback = options.mode {
+        rollback(&options);
+        exit(0);
+    }
+    if let cli::Mode::Preflight = options.mode {
+        let preflight = preflight::run();
+        preflight.print();
+        exit(match preflight.verdict() {
+            preflight::Verdict::Ok | preflight::Verdict::Warning => 0,
+            preflight::Verdict::AuditOnly => 1,
+            preflight::Verdict::Abort => 2,
+        });
+    }
+
+    let controls = match catalog::select(&options.selection) {
+        Ok(controls) => controls,
+        Err(error) => {
+            eprintln!("❌ {}", error);
+            exit(2);
+        }
+    };
+
+    if matches!(options.mode, cli::Mode::Apply | cli::Mode::Audit) {
+        install_user_scope(&options);
+    }
+
+    match options.mode {
+        cli::Mode::List => list(&controls),
+        cli::Mode::Audit => audit(&controls, &options),
+        cli::Mode::ProfileExport => export_profile(&controls, &options),
+        _ => apply(&controls, &options),
+    }
+    exit(0);
+}
+
+static SIMULATION: std::sync::OnceLock<&'static runner::ScriptedRunner> = std::sync::OnceLock::new();
+
+// Accounts the per-user controls act on: --user replaces the `users` of the configuration and
+// the exclusions of both add up
+fn install_user_scope(options: &cli::Options) {
+    let config = config::current();
+    let mut filter = config.users.clone();
+    if !options.users.is_empty() {
+        filter.include = options.users.clone();
+    }
+    filter.exclude.extend(options.exclude_users.iter().cloned());
+
+    let unknown = users::unknown(&filter.include);
+    if !unknown.is_empty() {
+        eprintln!("❌ No human account named {}", unknown.join(", "));
+        exit(2);
+    }
+
+    let console_user = if options.console_user || config.console_user_only {
+        if root::is_offline() {
+            eprintln!("❌ There is no console user under --root (console_user_only is set in the configuration)");
+            exit(2);
+        }
+        match users::console_user() {
+            Some(name) => Some(name),
+            None => {
+                eprintln!("❌ No user is logged in at the console");
+                exit(2);
+            }
+        }
+    } else {
+        None
+    };
+
+    let scoped = console_user.is_some() || !filter.include.is_empty() || !filter.exclude.is_empty();
+    users::install_scope(users::Scope {
+        filter,
+        console_user,
+        controls: config.control_users.clone(),
+    });
+    if scoped {
+        let (targeted, left_out): (Vec<_>, Vec<_>) =
+            users::human().into_iter().partition(|user| users::exclusion(&user.name).is_none());
+        let names = |users: &[&users::User]| users.iter().map(|user| user.name.as_str()).collect::<Vec<_>>().join(", ");
+        println!("👤 Per-user controls act on: {}", if targeted.is_empty() { "nobody".to_string() } else { names(&targeted) });
+        if !left_out.is_empty() {
+            println!("➖ Left out: {}", names(&left_out));
+        }
+    }
+}
+
+// Exits the process, first listing what a simulation would have executed
+fn exit(code: i32) -> ! {
+    if let Some(script) = SIMULATION.get() {
+        let invocations = script.invocations();
+        println!("============================================");
+        println!("Simulation: {} commands recorded, nothing was executed.", invocations.len());
+        println!("============================================");
+        for invocation in invocations.iter() {
+            let marker = if invocation.scripted { "$" } else { "?" }; // "?" has no scripted response
+            println!("{} {}", marker, invocation.command_line);
+            if let Some(input) = &invocation.stdin {
+                println!("    stdin: {:?}", input);
+            }
+        }
+    }
+    std::process::exit(code)
+}
+
+// Writes the report requested with --report, if any
+fn write_report(report: impl FnOnce() -> Report, options: &cli::Options) {
+    let format = match options.report {
+        Some(format) => format,
+        None => return,
+    };
+
+    let report = report();
+    let path = options.output.clone().unwrap_or_else(|| format.default_path(report.mode));
+    match report.write(format, &path) {
+        Ok(()) => println!("📄 Report written to {}", path),
+        Err(error) => {
+            eprintln!("❌ Failed to write the report to {}: {}", path, error);
+            exit(1);
+        }
+    }
+}
+
+fn apply(controls: &[Control], options: &cli::Options) {
+    // The host is checked before anything changes; a dry run only shows the findings
+    let preflight = preflight::run();
+    preflight.print();
+    if !options.dry_run {
+        match preflight.verdict() {
+            preflight::Verdict::Abort => {
+                eprintln!("❌ Preflight failed, nothing was changed.");
+                exit(2);
+            }
+            preflight::Verdict::AuditOnly => {
+                println!("⛔ This host cannot be hardened as it is: running the audit instead, nothing will be changed.");
+                audit(controls, options);
+                exit(1);
+            }
+            preflight::Verdict::Ok | preflight::Verdict::Warning => {}
+        }
+    }
+
+    // "--dry-run" prints the ordered plan instead of executing it
+    let controls = scheduler::order(controls);
+    let actions = post_action::requested(&controls);
+    if options.dry_run {
+        let commands: Vec<BashCommand> = controls.iter().flat_map(|control| control.remediation()).collect();
+
+        println!("============================================");
+        println!("Dry run: {} commands would be executed, nothing was changed.", commands.len());
+        println!("============================================");
+        for (step, command) in commands.iter().enumerate() {
+            command.plan(step + 1);
+        }
+        post_action::plan(&actions, commands.len() + 1);
+        for control in &controls {
+            if let Some(reason) = control.not_applicable() {
+                println!("➖ {}: {}", control.name, reason);
+            }
+        }
+        return;
+    }
+
+    // Previous values are recorded before each command so the run can be undone with "rollback"
+    let journal = if options.no_journal {
+        None
+    } else {
+        let path = options.journal.as_deref().unwrap_or(journal::DEFAULT_PATH);
+        match journal::Journal::open(path) {
+            Ok(journal) => Some(journal),
+            Err(error) => {
+                eprintln!("❌ Failed to open the rollback journal {}: {}", path, error);
+                exit(1);
+            }
+        }
+    };
+
+    // Independent controls run in parallel with --jobs; the journal records one command at a time
+    let jobs = options.jobs.unwrap_or(config::current().jobs as usize);
+    let journal = std::sync::Mutex::new(journal);
+    let mut runs: Vec<ControlRun> = scheduler::run(&controls, jobs, |control, command| {
+        if let Some(journal) = journal.lock().expect("journal").as_mut() {
+            if let Err(error) = journal.record(control.name, command) {
+                eprintln!("❌ Failed to write the rollback journal {}: {}", journal.path, error);
+                exit(1);
+            }
+        }
+        // Commands followed by a post-action tell whether they really changed something
+        if post_action::awaited(&actions, control) {
+            post_action::execute_tracked(command)
+        } else {
+            command.execute()
+        }
+    });
+    let journal = journal.into_inner().expect("journal");
+    post_action::run(&actions, &mut runs);
+
+    // Controls of other releases ran no command, they are reported as not applicable
+    for run in runs.iter_mut() {
+        if let Some(reason) = run.control.not_applicable() {
+            println!("➖ {}: {}", run.control.name, reason);
+            run.outcomes.push(ExecutionOutcome::not_applicable(run.control.name, run.control.title, reason));
+        }
+    }
+
+    let summary = RunSummary::new(&runs);
+    summary.print(&runs);
+    write_report(|| Report::from_runs(&runs), options);
+    if let Some(journal) = journal.filter(|journal| journal.recorded > 0) {
+        println!(
+            "↩️  {} previous values recorded in {} (undo with: rollback --journal {})",
+            journal.recorded, journal.path, journal.path
+        );
+    }
+
+    if summary.failed > 0 {
+        exit(1);
+    }
+}
+
+fn audit(controls: &[Control], options: &cli::Options) {
+    let audits: Vec<ControlAudit> = controls
+        .iter()
+        .map(|control| ControlAudit {
+            control: *control,
+            results: control.audit_checks().iter().map(|check| check.run()).collect(),
+        })
         .collect();
 
-    for i in commands {
-        i.execute();
+    AuditSummary::new(&audits).print(&audits);
+    write_report(|| Report::from_audits(&audits), options);
+}
+
+fn list(controls: &[Control]) {
+    for control in controls {
+        println!(
+            "{:<50} {:<8} {:<3} {:<9} {} [{}]{}",
+            control.name,
+            control.cis_id,
+            if control.level == 0 { "-".to_string() } else { format!("L{}", control.level) },
+            control.assessment.as_str(),
+            control.title,
+            control.tags.join(", "),
+            if control.default_enabled { "" } else { " (disabled by default)" }
+        );
     }
+    println!("{} controls selected.", controls.len());
+}
+
+// Writes the preference settings of the selected controls as a configuration profile
+fn export_profile(controls: &[Control], options: &cli::Options) {
+    let path = options.output.as_deref().unwrap_or(profile::DEFAULT_PATH);
+    let profile = match profile::export(controls, options.split) {
+        Ok(profile) => profile,
+        Err(error) => {
+            eprintln!("❌ {}", error);
+            exit(1);
+        }
+    };
+
+    for warning in &profile.warnings {
+        eprintln!("⚠️  {}", warning);
+    }
+    if !profile.skipped.is_empty() {
+        println!("➖ Not managed by a profile (apply them with 'apply'): {}", profile.skipped.join(", "));
+    }
+    match std::fs::write(path, plist::to_xml(&profile.content)) {
+        Ok(()) => println!(
+            "📄 Profile with {} settings in {} payloads written to {}",
+            profile.settings, profile.payloads, path
+        ),
+        Err(error) => {
+            eprintln!("❌ Failed to write the profile to {}: {}", path, error);
+            exit(1);
+        }
+    }
+}
 
+fn explain(name: &str) {
+    let control = match catalog::find(name) {
+        Some(control) => control,
+        None => {
+            eprintln!("❌ Unknown control '{}'", name);
+            exit(2);
+        }
+    };
+
+    println!("Control: {}", control.name);
+    println!("CIS recommendation: {}", if control.cis_id.is_empty() { "-" } else { control.cis_id });
+    println!("Title: {}", control.title);
+    println!("Section: {}", control.section());
+    println!(
+        "Profile: {}, {}",
+        if control.level == 0 { "not part of the benchmark".to_string() } else { format!("Level {}", control.level) },
+        control.assessment.as_str()
+    );
+    println!("Rationale: {}", control.rationale);
+    println!("Tags: {}", control.tags.join(", "));
+    println!("Enabled by default: {}", if control.default_enabled { "yes" } else { "no" });
+    let benchmarks: Vec<&str> = control.releases.iter().map(|release| release.benchmark()).collect();
+    println!("Benchmarks: {}", benchmarks.join(", "));
+    if let Some(reason) = control.not_applicable() {
+        println!("Not applicable: {}", reason);
+    }
+    if !control.requires.is_empty() {
+        let required: Vec<&str> = control
+            .requires
+            .iter()
+            .map(|pattern| if *pattern == "*" { "every other selected control" } else { pattern })
+            .collect();
+        println!("Runs after: {}", required.join(", "));
+    }
+    println!("--------------------------------------------");
+    println!("Commands:");
+    let commands = control.remediation();
+    for (step, command) in commands.iter().enumerate() {
+        command.plan(step + 1);
+    }
+    let actions = post_action::requested(&[control]);
+    if !actions.is_empty() {
+        println!("Post-actions:");
+        post_action::plan(&actions, commands.len() + 1);
+    }
+}
+
+// Restores the values recorded in a journal, newest change first
+fn rollback(options: &cli::Options) {
+    let path = options.journal.as_deref().unwrap_or(journal::DEFAULT_PATH);
+    let mut entries = match journal::load(path) {
+        Ok(entries) => entries,
+        Err(error) => {
+            eprintln!("❌ {}", error);
+            exit(2);
+        }
+    };
+
+    // Without filters the whole journal is restored, even controls that are disabled by default
+    let selection = &options.selection;
+    let filtered = !(selection.controls.is_empty()
+        && selection.sections.is_empty()
+        && selection.tags.is_empty()
+        && selection.excludes.is_empty()
+        && selection.level.is_none());
+    if filtered {
+        let controls = match catalog::select(selection) {
+            Ok(controls) => controls,
+            Err(error) => {
+                eprintln!("❌ {}", error);
+                exit(2);
+            }
+        };
+        entries.retain(|entry| controls.iter().any(|control| control.name == entry.control));
+    }
+    entries.reverse();
+
+    if options.dry_run {
+        println!("============================================");
+        println!("Dry run: {} changes would be restored from {}, nothing was changed.", entries.len(), path);
+        println!("============================================");
+        for (step, entry) in entries.iter().enumerate() {
+            entry.plan(step + 1);
+        }
+        return;
+    }
+
+    let (mut restored, mut manual, mut failed) = (0, Vec::new(), Vec::new());
+    for entry in &entries {
+        let (status, detail) = entry.restore();
+        match status {
+            journal::RollbackStatus::Restored => {
+                println!("↩️  {}: {}", entry.command, detail);
+                restored += 1;
+            }
+            journal::RollbackStatus::Manual => {
+                println!("✋ {}: {}", entry.command, detail);
+                manual.push(detail);
+            }
+            journal::RollbackStatus::Failed => {
+                eprintln!("❌ {}: {}", entry.command, detail);
+                failed.push(detail);
+            }
+        }
+    }
+
+    println!("============================================");
+    println!(
+        "Rollback: {} restored, {} failed, {} to restore by hand.",
+        restored,
+        failed.len(),
+        manual.len()
+    );
+    for detail in &manual {
+        println!("  ✋ {}", detail);
+    }
+    for detail in &failed {
+        println!("  ❌ {}", detail);
+    }
+    println!("============================================");
+
+    if !failed.is_empty() {
+        exit(1);
+    }
 }
@@ -0,0 +1,178 @@
+// macOS release of the target system. The catalog declares the releases each control applies to
+// (each release has its own CIS benchmark edition), and the commands and checks whose
+// implementation changed between releases pick the variant of the detected one.
+
+use crate::plist::Value;
+
+pub const SYSTEM_VERSION: &str = "/System/Library/CoreServices/SystemVersion.plist";
+
+// Releases the controls were reviewed against, oldest first
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Release {
+    Monterey, // 12, the release the commands were first written for
+    Ventura,  // 13
+    Sonoma,   // 14
+    Sequoia,  // 15
+}
+
+pub const EVERY_RELEASE: &[Release] = &[Release::Monterey, Release::Ventura, Release::Sonoma, Release::Sequoia];
+
+impl Release {
+    pub fn major(&self) -> u32 {
+        match self {
+            Release::Monterey => 12,
+            Release::Ventura => 13,
+            Release::Sonoma => 14,
+            Release::Sequoia => 15,
+        }
+    }
+
+    // e.g. "macOS 14 Sonoma"
+    pub fn name(&self) -> String {
+        let name = match self {
+            Release::Monterey => "Monterey",
+            Release::Ventura => "Ventura",
+            Release::Sonoma => "Sonoma",
+            Release::Sequoia => "Sequoia",
+        };
+        format!("macOS {} {}", self.major(), name)
+    }
+
+    // Edition of the CIS benchmark written for the release
+    pub fn benchmark(&self) -> &'static str {
+        match self {
+            Release::Monterey => "CIS Apple macOS 12.0 Monterey Benchmark",
+            Release::Ventura => "CIS Apple macOS 13.0 Ventura Benchmark",
+            Release::Sonoma => "CIS Apple macOS 14.0 Sonoma Benchmark",
+            Release::Sequoia => "CIS Apple macOS 15.0 Sequoia Benchmark",
+        }
+    }
+
+    fn from_major(major: u32) -> Option<Release> {
+        EVERY_RELEASE.iter().copied().find(|release| release.major() == major)
+    }
+}
+
+// Version of the target system, e.g. 14.5 (23F79)
+pub struct OsVersion {
+    pub major: u32,
+    pub build: String,
+    pub text: String, // As reported, e.g. "12.0.1"
+}
+
+impl OsVersion {
+    fn parse(version: &str, build: &str) -> Option<OsVersion> {
+        Some(OsVersion {
+            major: version.trim().split('.').next()?.parse().ok()?,
+            build: build.trim().to_string(),
+            text: version.trim().to_string(),
+        })
+    }
+}
+
+static DETECTED: std::sync::OnceLock<Result<Option<OsVersion>, String>> = std::sync::OnceLock::new();
+
+// Version of the target, read once per run (see `detect`). A test with its own runner or root
+// reads it from them on every call.
+pub fn detected() -> &'static Result<Option<OsVersion>, String> {
+    #[cfg(test)]
+    if crate::runner::installed_for_thread() || crate::root::installed_for_thread() {
+        return Box::leak(Box::new(detect()));
+    }
+    DETECTED.get_or_init(detect)
+}
+
+// From sw_vers on the running system, from the SystemVersion.plist of the tree under --root. Err
+// when sw_vers cannot be run (this is not macOS), None when the version cannot be read.
+fn detect() -> Result<Option<OsVersion>, String> {
+    if crate::root::is_offline() {
+        let text = |plist: &Value, key: &str| match plist.get(key) {
+            Some(Value::String(value)) => Some(value.clone()),
+            _ => None,
+        };
+        return Ok(crate::plist::load(&crate::root::path(SYSTEM_VERSION)).ok().and_then(|(plist, _)| {
+            OsVersion::parse(&text(&plist, "ProductVersion")?, &text(&plist, "ProductBuildVersion").unwrap_or_default())
+        }));
+    }
+
+    let read = |flag: &str| -> std::io::Result<Option<String>> {
+        let output = crate::runner::run("/usr/bin/sw_vers", &[flag])?;
+        Ok(Some(output.stdout.trim().to_string()).filter(|stdout| output.success() && !stdout.is_empty()))
+    };
+    let version = read("-productVersion").map_err(|error| error.to_string())?;
+    let build = read("-buildVersion").ok().flatten().unwrap_or_default();
+    Ok(version.and_then(|version| OsVersion::parse(&version, &build)))
+}
+
+// Release the controls are selected for: the detected one, Sequoia for a later version, and
+// Monterey (what the commands were written for) for an earlier or unknown one
+pub fn release() -> Release {
+    match detected() {
+        Ok(Some(version)) if version.major > Release::Sequoia.major() => Release::Sequoia,
+        Ok(Some(version)) => Release::from_major(version.major).unwrap_or(Release::Monterey),
+        _ => Release::Monterey,
+    }
+}
+
+// True when the target runs the release or a later one
+pub fn at_least(release: Release) -> bool {
+    self::release() >= release
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{fixture, simulate, sw_vers, system_version};
+
+    #[test]
+    fn parses_sw_vers_output() {
+        let version = OsVersion::parse("14.5\n", "23F79\n").expect("version");
+        assert_eq!((version.major, version.build.as_str(), version.text.as_str()), (14, "23F79", "14.5"));
+
+        let version = OsVersion::parse("15", "").expect("version");
+        assert_eq!((version.major, version.build.as_str(), version.text.as_str()), (15, "", "15"));
+
+        assert!(OsVersion::parse("", "23F79").is_none());
+        assert!(OsVersion::parse("beta", "23F79").is_none());
+    }
+
+    #[test]
+    fn maps_versions_to_releases() {
+        for (version, expected) in [
+            ("12.7.4", Release::Monterey),
+            ("13.6", Release::Ventura),
+            ("14.5", Release::Sonoma),
+            ("15.0.1", Release::Sequoia),
+            ("16.1", Release::Sequoia),
+            ("26.0", Release::Sequoia),
+            ("11.7", Release::Monterey),
+        ] {
+            simulate(&sw_vers(version));
+            assert_eq!(release(), expected, "{}", version);
+        }
+    }
+
+    #[test]
+    fn unknown_versions_use_monterey() {
+        simulate("");
+        assert!(matches!(detected(), Ok(None)));
+        assert_eq!(release(), Release::Monterey);
+
+        simulate("$1 /usr/bin/sw_vers -productVersion\n");
+        assert!(matches!(detected(), Ok(None)));
+        assert_eq!(release(), Release::Monterey);
+    }
+
+    #[test]
+    fn reads_the_version_of_the_root() {
+        let (path, plist) = system_version("14.5");
+        fixture("os-version", &[(path, &plist)]);
+        let version = detected().as_ref().expect("readable").as_ref().expect("version");
+        assert_eq!((version.major, version.build.as_str()), (14, "23F79"));
+        assert!(at_least(Release::Sonoma) && !at_least(Release::Sequoia));
+
+        fixture("os-version-missing", &[]);
+        assert!(matches!(detected(), Ok(None)));
+        assert_eq!(release(), Release::Monterey);
+    }
+}
@@ -0,0 +1,931 @@
+// Property lists, the format of preference files: a typed value tree read from and written to
+// XML ("<?xml ... <plist>") or binary ("bplist00") files, without going through `defaults`.
+
+// Seconds between 1970-01-01 and 2001-01-01, the reference date of plist dates
+const APPLE_EPOCH: i64 = 978_307_200;
+
+// Deepest nesting accepted when reading, so a malformed file cannot overflow the stack (preference
+// files nest a few levels)
+const MAX_DEPTH: usize = 128;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Value {
+    Dict(Vec<(String, Value)>), // Keys in file order
+    Array(Vec<Value>),
+    Bool(bool),
+    Integer(i64),
+    Real(f64),
+    String(String),
+    Data(Vec<u8>),
+    Date(f64), // Seconds since 2001-01-01 00:00:00 UTC
+}
+
+// Encoding of a plist file, kept when a file is rewritten
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Format {
+    Xml,
+    Binary,
+}
+
+impl Value {
+    // Entry of a dictionary
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Dict(entries) => entries.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    // Sets an entry of a dictionary in place, or appends it (no effect on other values)
+    pub fn insert(&mut self, key: &str, value: Value) {
+        if let Value::Dict(entries) = self {
+            match entries.iter_mut().find(|(name, _)| name == key) {
+                Some((_, current)) => *current = value,
+                None => entries.push((key.to_string(), value)),
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        if let Value::Dict(entries) = self {
+            entries.retain(|(name, _)| name != key);
+        }
+    }
+
+    // Value as `defaults read` prints it: booleans as 1/0, dictionaries and arrays in the
+    // old-style plist syntax, e.g. "{\n    Enabled = 0;\n}"
+    pub fn defaults_text(&self) -> String {
+        self.old_style(0)
+    }
+
+    fn old_style(&self, depth: usize) -> String {
+        let indent = "    ".repeat(depth + 1);
+        let closing = "    ".repeat(depth);
+        match self {
+            Value::Dict(entries) if entries.is_empty() => "{\n}".to_string(),
+            Value::Dict(entries) => {
+                let lines: Vec<String> = entries
+                    .iter()
+                    .map(|(key, value)| format!("{}{} = {};", indent, quote(key), value.old_style(depth + 1)))
+                    .collect();
+                format!("{{\n{}\n{}}}", lines.join("\n"), closing)
+            }
+            Value::Array(items) if items.is_empty() => "(\n)".to_string(),
+            Value::Array(items) => {
+                let lines: Vec<String> = items
+                    .iter()
+                    .map(|item| format!("{}{}", indent, item.old_style(depth + 1)))
+                    .collect();
+                format!("(\n{}\n{})", lines.join(",\n"), closing)
+            }
+            Value::Bool(value) => if *value { "1" } else { "0" }.to_string(),
+            Value::Integer(value) => value.to_string(),
+            Value::Real(value) => value.to_string(),
+            // Only nested strings are quoted, a top-level value is printed as is
+            Value::String(text) if depth > 0 => quote(text),
+            Value::String(text) => text.clone(),
+            Value::Data(bytes) => {
+                let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+                format!("{{length = {}, bytes = 0x{}}}", bytes.len(), hex)
+            }
+            Value::Date(seconds) => format_date(*seconds, " ").replace('Z', " +0000"),
+        }
+    }
+}
+
+// Quotes a string for the old-style syntax unless it only has letters, digits and "._$/"
+fn quote(text: &str) -> String {
+    let bare = !text.is_empty() && text.chars().all(|c| c.is_ascii_alphanumeric() || "._$/".contains(c));
+    if bare {
+        text.to_string()
+    } else {
+        format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+// Reads a plist file in either format
+pub fn load(path: &str) -> Result<(Value, Format), String> {
+    let bytes = std::fs::read(path).map_err(|error| format!("Failed to read {}: {}", path, error))?;
+    parse(&bytes).map_err(|error| format!("Invalid property list {}: {}", path, error))
+}
+
+pub fn parse(bytes: &[u8]) -> Result<(Value, Format), String> {
+    if bytes.starts_with(b"bplist00") {
+        return parse_binary(bytes).map(|value| (value, Format::Binary));
+    }
+    let text = std::str::from_utf8(bytes).map_err(|_| "neither a binary plist nor UTF-8 XML".to_string())?;
+    parse_xml(text).map(|value| (value, Format::Xml))
+}
+
+pub fn serialize(value: &Value, format: Format) -> Vec<u8> {
+    match format {
+        Format::Xml => to_xml(value).into_bytes(),
+        Format::Binary => to_binary(value),
+    }
+}
+
+// Path of a preference domain given as a path: `defaults` adds ".plist" when it is missing
+pub fn domain_file(domain: &str) -> String {
+    if domain.ends_with(".plist") {
+        domain.to_string()
+    } else {
+        format!("{}.plist", domain)
+    }
+}
+
+// ByHost preferences of a user, e.g. ~/Library/Preferences/ByHost/com.apple.screensaver.<UUID>.plist
+// (the most recently modified file when the home was used on several machines)
+pub fn byhost_file(home: &str, domain: &str) -> Option<String> {
+    let directory = format!("{}/Library/Preferences/ByHost", home);
+    let domain = domain.trim_end_matches(".plist");
+    let prefix = format!("{}.", domain);
+
+    std::fs::read_dir(&directory)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let host = name.strip_prefix(&prefix)?.strip_suffix(".plist")?;
+            // Only the host identifier may follow the domain, not a longer domain name
+            if host.is_empty() || host.contains('.') {
+                return None;
+            }
+            let modified = entry.metadata().and_then(|metadata| metadata.modified()).ok()?;
+            Some((modified, format!("{}/{}", directory, name)))
+        })
+        .max()
+        .map(|(_, path)| path)
+}
+
+// XML
+
+struct XmlReader<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+enum Tag<'a> {
+    Open(&'a str),
+    Close(&'a str),
+    Empty(&'a str), // <true/>, <dict/>...
+}
+
+impl<'a> XmlReader<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.position..]
+    }
+
+    fn skip_to(&mut self, end: &str) -> Result<(), String> {
+        match self.rest().find(end) {
+            Some(offset) => {
+                self.position += offset + end.len();
+                Ok(())
+            }
+            None => Err(format!("unterminated markup, expected '{}'", end)),
+        }
+    }
+
+    // Next element tag, skipping whitespace, the XML declaration, the doctype and comments
+    fn next_tag(&mut self) -> Result<Tag<'a>, String> {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.position += rest.len() - trimmed.len();
+            if trimmed.is_empty() {
+                return Err("unexpected end of document".to_string());
+            }
+            if !trimmed.starts_with('<') {
+                return Err(format!("unexpected text at byte {}", self.position));
+            }
+            if trimmed.starts_with("<!--") {
+                self.skip_to("-->")?;
+            } else if trimmed.starts_with("<?") {
+                self.skip_to("?>")?;
+            } else if trimmed.starts_with("<!") {
+                self.skip_to(">")?;
+            } else {
+                break;
+            }
+        }
+
+        let start = self.position + 1;
+        let end = start
+            + self.text[start..]
+                .find('>')
+                .ok_or_else(|| "unterminated tag".to_string())?;
+        self.position = end + 1;
+
+        let inside = &self.text[start..end];
+        if let Some(name) = inside.strip_prefix('/') {
+            return Ok(Tag::Close(name.trim()));
+        }
+        let empty = inside.ends_with('/');
+        let inside = inside.trim_end_matches('/');
+        // Attributes (e.g. version="1.0" on <plist>) are ignored
+        let name = inside.split_whitespace().next().unwrap_or("");
+        Ok(if empty { Tag::Empty(name) } else { Tag::Open(name) })
+    }
+
+    // Character data up to the closing tag, with entities decoded
+    fn text_until(&mut self, name: &str) -> Result<String, String> {
+        let closing = format!("</{}>", name);
+        let offset = self
+            .rest()
+            .find(&closing)
+            .ok_or_else(|| format!("missing {}", closing))?;
+        let raw = &self.rest()[..offset];
+        self.position += offset + closing.len();
+        decode_entities(raw)
+    }
+
+    fn expect_close(&mut self, name: &str) -> Result<(), String> {
+        match self.next_tag()? {
+            Tag::Close(found) if found == name => Ok(()),
+            _ => Err(format!("expected </{}>", name)),
+        }
+    }
+
+    fn value(&mut self, tag: Tag<'a>, depth: usize) -> Result<Value, String> {
+        if depth > MAX_DEPTH {
+            return Err("nesting is too deep".to_string());
+        }
+        match tag {
+            Tag::Empty("dict") => Ok(Value::Dict(Vec::new())),
+            Tag::Empty("array") => Ok(Value::Array(Vec::new())),
+            Tag::Empty("true") => Ok(Value::Bool(true)),
+            Tag::Empty("false") => Ok(Value::Bool(false)),
+            Tag::Empty("string") => Ok(Value::String(String::new())),
+            Tag::Empty("data") => Ok(Value::Data(Vec::new())),
+            Tag::Open("true") => self.expect_close("true").map(|_| Value::Bool(true)),
+            Tag::Open("false") => self.expect_close("false").map(|_| Value::Bool(false)),
+            Tag::Open("dict") => {
+                let mut entries = Vec::new();
+                loop {
+                    let key = match self.next_tag()? {
+                        Tag::Close("dict") => return Ok(Value::Dict(entries)),
+                        Tag::Open("key") => self.text_until("key")?,
+                        Tag::Empty("key") => String::new(),
+                        _ => return Err("expected <key> in <dict>".to_string()),
+                    };
+                    let tag = self.next_tag()?;
+                    entries.push((key, self.value(tag, depth + 1)?));
+                }
+            }
+            Tag::Open("array") => {
+                let mut items = Vec::new();
+                loop {
+                    match self.next_tag()? {
+                        Tag::Close("array") => return Ok(Value::Array(items)),
+                        tag => items.push(self.value(tag, depth + 1)?),
+                    }
+                }
+            }
+            Tag::Open("string") => self.text_until("string").map(Value::String),
+            Tag::Open("integer") => {
+                let text = self.text_until("integer")?;
+                let text = text.trim();
+                let parsed = match text.strip_prefix("0x") {
+                    Some(hex) => i64::from_str_radix(hex, 16).ok(),
+                    None => text.parse().ok(),
+                };
+                parsed
+                    .map(Value::Integer)
+                    .ok_or_else(|| format!("invalid integer '{}'", text))
+            }
+            Tag::Open("real") => {
+                let text = self.text_until("real")?;
+                text.trim()
+                    .parse()
+                    .map(Value::Real)
+                    .map_err(|_| format!("invalid real '{}'", text.trim()))
+            }
+            Tag::Open("data") => base64_decode(&self.text_until("data")?).map(Value::Data),
+            Tag::Open("date") => parse_date(self.text_until("date")?.trim()).map(Value::Date),
+            Tag::Open(name) | Tag::Empty(name) => Err(format!("unexpected <{}>", name)),
+            Tag::Close(name) => Err(format!("unexpected </{}>", name)),
+        }
+    }
+}
+
+pub fn parse_xml(text: &str) -> Result<Value, String> {
+    let mut reader = XmlReader { text, position: 0 };
+    let value = match reader.next_tag()? {
+        Tag::Open("plist") => {
+            let tag = reader.next_tag()?;
+            let value = reader.value(tag, 0)?;
+            reader.expect_close("plist")?;
+            value
+        }
+        tag => reader.value(tag, 0)?,
+    };
+    if !reader.rest().trim().is_empty() {
+        return Err("unexpected content after the root value".to_string());
+    }
+    Ok(value)
+}
+
+fn decode_entities(raw: &str) -> Result<String, String> {
+    let mut text = String::new();
+    let mut rest = raw;
+    while let Some(start) = rest.find('&') {
+        text.push_str(&rest[..start]);
+        let end = rest[start..].find(';').ok_or_else(|| "unterminated entity".to_string())? + start;
+        let entity = &rest[start + 1..end];
+        let decoded = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => match entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+                None => entity
+                    .strip_prefix('#')
+                    .and_then(|decimal| decimal.parse().ok())
+                    .and_then(char::from_u32),
+            },
+        };
+        text.push(decoded.ok_or_else(|| format!("unknown entity '&{};'", entity))?);
+        rest = &rest[end + 1..];
+    }
+    text.push_str(rest);
+    Ok(text)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+// Same layout as the files written by macOS: one element per line, indented with tabs
+pub fn to_xml(value: &Value) -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+         <plist version=\"1.0\">\n",
+    );
+    write_xml(value, 0, &mut xml);
+    xml.push_str("</plist>\n");
+    xml
+}
+
+fn write_xml(value: &Value, depth: usize, xml: &mut String) {
+    let indent = "\t".repeat(depth);
+    match value {
+        Value::Dict(entries) if entries.is_empty() => xml.push_str(&format!("{}<dict/>\n", indent)),
+        Value::Dict(entries) => {
+            xml.push_str(&format!("{}<dict>\n", indent));
+            for (key, value) in entries {
+                xml.push_str(&format!("{}\t<key>{}</key>\n", indent, escape(key)));
+                write_xml(value, depth + 1, xml);
+            }
+            xml.push_str(&format!("{}</dict>\n", indent));
+        }
+        Value::Array(items) if items.is_empty() => xml.push_str(&format!("{}<array/>\n", indent)),
+        Value::Array(items) => {
+            xml.push_str(&format!("{}<array>\n", indent));
+            for item in items {
+                write_xml(item, depth + 1, xml);
+            }
+            xml.push_str(&format!("{}</array>\n", indent));
+        }
+        Value::Bool(true) => xml.push_str(&format!("{}<true/>\n", indent)),
+        Value::Bool(false) => xml.push_str(&format!("{}<false/>\n", indent)),
+        Value::Integer(number) => xml.push_str(&format!("{}<integer>{}</integer>\n", indent, number)),
+        Value::Real(number) => xml.push_str(&format!("{}<real>{}</real>\n", indent, number)),
+        Value::String(text) => xml.push_str(&format!("{}<string>{}</string>\n", indent, escape(text))),
+        Value::Data(bytes) => xml.push_str(&format!("{}<data>\n{}{}\n{}</data>\n", indent, indent, base64_encode(bytes), indent)),
+        Value::Date(seconds) => xml.push_str(&format!("{}<date>{}</date>\n", indent, format_date(*seconds, "T"))),
+    }
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let group = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(BASE64[(group >> (18 - 6 * index) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+fn base64_decode(text: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    let mut group = 0u32;
+    let mut bits = 0;
+    for c in text.chars().filter(|c| !c.is_whitespace() && *c != '=') {
+        let sextet = BASE64
+            .iter()
+            .position(|&symbol| symbol as char == c)
+            .ok_or_else(|| format!("invalid base64 character '{}'", c))?;
+        group = group << 6 | sextet as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((group >> bits) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+// Dates
+
+// "2024-01-10T13:45:00Z" -> seconds since 2001-01-01
+fn parse_date(text: &str) -> Result<f64, String> {
+    let invalid = || format!("invalid date '{}'", text);
+    let digits = |range: std::ops::Range<usize>| -> Result<i64, String> {
+        text.get(range).and_then(|part| part.parse().ok()).ok_or_else(invalid)
+    };
+    if text.len() != 20 || !text.ends_with('Z') {
+        return Err(invalid());
+    }
+    let (year, month, day) = (digits(0..4)?, digits(5..7)?, digits(8..10)?);
+    let (hour, minute, second) = (digits(11..13)?, digits(14..16)?, digits(17..19)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return Err(invalid());
+    }
+
+    // Days since 1970-01-01 from a civil date (Howard Hinnant's algorithm)
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+
+    Ok((days * 86400 + hour * 3600 + minute * 60 + second - APPLE_EPOCH) as f64)
+}
+
+// Seconds since 2001-01-01 -> "2024-01-10T13:45:00Z" (with `separator` between date and time)
+fn format_date(seconds: f64, separator: &str) -> String {
+    let unix = seconds.floor() as i64 + APPLE_EPOCH;
+    let time = if unix >= 0 {
+        std::time::UNIX_EPOCH + std::time::Duration::from_secs(unix as u64)
+    } else {
+        std::time::UNIX_EPOCH
+    };
+    crate::report::format_timestamp(time).replace('T', separator)
+}
+
+// Binary
+
+struct BinaryReader<'a> {
+    bytes: &'a [u8],
+    offsets: Vec<usize>,
+    reference_size: usize,
+}
+
+impl BinaryReader<'_> {
+    fn slice(&self, start: usize, length: usize) -> Result<&[u8], String> {
+        start
+            .checked_add(length)
+            .and_then(|end| self.bytes.get(start..end))
+            .ok_or_else(|| "object extends past the end of the file".to_string())
+    }
+
+    fn read_reference(&self, position: usize) -> Result<usize, String> {
+        Ok(be_uint(self.slice(position, self.reference_size)?) as usize)
+    }
+
+    // Length of a data, string, array or dictionary object, and where its content starts
+    fn length(&self, position: usize, info: u8) -> Result<(usize, usize), String> {
+        if info != 0x0f {
+            return Ok((info as usize, position + 1));
+        }
+        let marker = *self.slice(position + 1, 1)?.first().unwrap_or(&0);
+        if marker >> 4 != 0x1 {
+            return Err("invalid length marker".to_string());
+        }
+        let size = 1usize << (marker & 0x0f);
+        let length = be_uint(self.slice(position + 2, size)?) as usize;
+        Ok((length, position + 2 + size))
+    }
+
+    // Object at an index of the offset table; `ancestors` are the containers being read around it
+    fn object(&self, index: usize, ancestors: &mut Vec<usize>) -> Result<Value, String> {
+        if ancestors.contains(&index) {
+            return Err(format!("object {} contains itself", index));
+        }
+        if ancestors.len() > MAX_DEPTH {
+            return Err("nesting is too deep".to_string());
+        }
+        let position = *self
+            .offsets
+            .get(index)
+            .ok_or_else(|| format!("reference to missing object {}", index))?;
+        let marker = *self.slice(position, 1)?.first().unwrap_or(&0);
+        let (kind, info) = (marker >> 4, marker & 0x0f);
+
+        match (kind, info) {
+            (0x0, 0x8) => Ok(Value::Bool(false)),
+            (0x0, 0x9) => Ok(Value::Bool(true)),
+            (0x1, 0..=3) => {
+                let bytes = self.slice(position + 1, 1 << info)?;
+                // 1, 2 and 4 byte integers are unsigned, 8 byte ones are signed
+                Ok(Value::Integer(be_uint(bytes) as i64))
+            }
+            (0x1, 4) => {
+                // 16 byte integers only hold 64-bit values in practice
+                let bytes = self.slice(position + 1, 16)?;
+                Ok(Value::Integer(be_uint(&bytes[8..]) as i64))
+            }
+            (0x2, 2) => Ok(Value::Real(f32::from_bits(be_uint(self.slice(position + 1, 4)?) as u32) as f64)),
+            (0x2, 3) => Ok(Value::Real(f64::from_bits(be_uint(self.slice(position + 1, 8)?)))),
+            (0x3, 3) => Ok(Value::Date(f64::from_bits(be_uint(self.slice(position + 1, 8)?)))),
+            (0x4, _) => {
+                let (length, start) = self.length(position, info)?;
+                Ok(Value::Data(self.slice(start, length)?.to_vec()))
+            }
+            (0x5, _) => {
+                let (length, start) = self.length(position, info)?;
+                Ok(Value::String(String::from_utf8_lossy(self.slice(start, length)?).to_string()))
+            }
+            (0x6, _) => {
+                let (length, start) = self.length(position, info)?;
+                let units: Vec<u16> = self
+                    .slice(start, length.checked_mul(2).ok_or("string is too long")?)?
+                    .chunks(2)
+                    .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                    .collect();
+                Ok(Value::String(String::from_utf16_lossy(&units)))
+            }
+            // Keyed archiver UIDs, not used by preference files
+            (0x8, _) => Ok(Value::Integer(be_uint(self.slice(position + 1, info as usize + 1)?) as i64)),
+            (0xa, _) => {
+                let (length, start) = self.length(position, info)?;
+                ancestors.push(index);
+                let mut items = Vec::new();
+                for item in 0..length {
+                    let reference = self.read_reference(start + item * self.reference_size)?;
+                    items.push(self.object(reference, ancestors)?);
+                }
+                ancestors.pop();
+                Ok(Value::Array(items))
+            }
+            (0xd, _) => {
+                let (length, start) = self.length(position, info)?;
+                ancestors.push(index);
+                let mut entries = Vec::new();
+                for entry in 0..length {
+                    let key = self.read_reference(start + entry * self.reference_size)?;
+                    let value = self.read_reference(start + (length + entry) * self.reference_size)?;
+                    let key = match self.object(key, ancestors)? {
+                        Value::String(key) => key,
+                        _ => return Err("dictionary key is not a string".to_string()),
+                    };
+                    entries.push((key, self.object(value, ancestors)?));
+                }
+                ancestors.pop();
+                Ok(Value::Dict(entries))
+            }
+            _ => Err(format!("unsupported object type 0x{:02x}", marker)),
+        }
+    }
+}
+
+fn be_uint(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0, |value, byte| value << 8 | *byte as u64)
+}
+
+pub fn parse_binary(bytes: &[u8]) -> Result<Value, String> {
+    if !bytes.starts_with(b"bplist00") || bytes.len() < 8 + 32 {
+        return Err("not a binary property list".to_string());
+    }
+
+    // Trailer: 6 unused bytes, offset size, reference size, object count, root object, offset table
+    let trailer = &bytes[bytes.len() - 32..];
+    let offset_size = trailer[6] as usize;
+    let reference_size = trailer[7] as usize;
+    let count = be_uint(&trailer[8..16]) as usize;
+    let root = be_uint(&trailer[16..24]) as usize;
+    let table = be_uint(&trailer[24..32]) as usize;
+    if !(1..=8).contains(&offset_size) || !(1..=8).contains(&reference_size) {
+        return Err("invalid trailer".to_string());
+    }
+
+    let table_end = count
+        .checked_mul(offset_size)
+        .and_then(|length| length.checked_add(table))
+        .filter(|end| *end <= bytes.len() - 32)
+        .ok_or_else(|| "offset table extends past the end of the file".to_string())?;
+    let offsets = bytes[table..table_end]
+        .chunks(offset_size)
+        .map(|offset| be_uint(offset) as usize)
+        .collect();
+
+    BinaryReader { bytes, offsets, reference_size }.object(root, &mut Vec::new())
+}
+
+// Objects in the order they are written, containers referring to others by index
+enum Object<'a> {
+    Scalar(&'a Value),
+    Key(&'a str),
+    Array(Vec<usize>),
+    Dict(Vec<usize>, Vec<usize>),
+}
+
+fn flatten<'a>(value: &'a Value, objects: &mut Vec<Object<'a>>) -> usize {
+    let index = objects.len();
+    objects.push(Object::Scalar(value));
+    match value {
+        Value::Array(items) => {
+            let references = items.iter().map(|item| flatten(item, objects)).collect();
+            objects[index] = Object::Array(references);
+        }
+        Value::Dict(entries) => {
+            let keys = entries
+                .iter()
+                .map(|(key, _)| {
+                    objects.push(Object::Key(key));
+                    objects.len() - 1
+                })
+                .collect();
+            let values = entries.iter().map(|(_, value)| flatten(value, objects)).collect();
+            objects[index] = Object::Dict(keys, values);
+        }
+        _ => {}
+    }
+    index
+}
+
+fn size_for(max: u64) -> usize {
+    match max {
+        0..=0xff => 1,
+        0x100..=0xffff => 2,
+        0x1_0000..=0xffff_ffff => 4,
+        _ => 8,
+    }
+}
+
+fn push_uint(out: &mut Vec<u8>, value: u64, size: usize) {
+    out.extend_from_slice(&value.to_be_bytes()[8 - size..]);
+}
+
+fn push_integer(out: &mut Vec<u8>, value: i64) {
+    // Negative numbers always take 8 bytes
+    let size = if value < 0 { 8 } else { size_for(value as u64) };
+    out.push(0x10 | size.trailing_zeros() as u8);
+    push_uint(out, value as u64, size);
+}
+
+fn push_header(out: &mut Vec<u8>, kind: u8, length: usize) {
+    if length < 0x0f {
+        out.push(kind << 4 | length as u8);
+    } else {
+        out.push(kind << 4 | 0x0f);
+        push_integer(out, length as i64);
+    }
+}
+
+fn push_string(out: &mut Vec<u8>, text: &str) {
+    if text.is_ascii() {
+        push_header(out, 0x5, text.len());
+        out.extend_from_slice(text.as_bytes());
+    } else {
+        let units: Vec<u16> = text.encode_utf16().collect();
+        push_header(out, 0x6, units.len());
+        for unit in units {
+            out.extend_from_slice(&unit.to_be_bytes());
+        }
+    }
+}
+
+pub fn to_binary(value: &Value) -> Vec<u8> {
+    let mut objects = Vec::new();
+    flatten(value, &mut objects);
+    let reference_size = size_for(objects.len() as u64);
+
+    let mut out = b"bplist00".to_vec();
+    let mut offsets = Vec::new();
+    for object in &objects {
+        offsets.push(out.len() as u64);
+        match object {
+            Object::Key(key) => push_string(&mut out, key),
+            Object::Array(references) => {
+                push_header(&mut out, 0xa, references.len());
+                for reference in references {
+                    push_uint(&mut out, *reference as u64, reference_size);
+                }
+            }
+            Object::Dict(keys, values) => {
+                push_header(&mut out, 0xd, keys.len());
+                for reference in keys.iter().chain(values) {
+                    push_uint(&mut out, *reference as u64, reference_size);
+                }
+            }
+            Object::Scalar(value) => match value {
+                Value::Bool(false) => out.push(0x08),
+                Value::Bool(true) => out.push(0x09),
+                Value::Integer(number) => push_integer(&mut out, *number),
+                Value::Real(number) => {
+                    out.push(0x23);
+                    out.extend_from_slice(&number.to_bits().to_be_bytes());
+                }
+                Value::Date(seconds) => {
+                    out.push(0x33);
+                    out.extend_from_slice(&seconds.to_bits().to_be_bytes());
+                }
+                Value::Data(bytes) => {
+                    push_header(&mut out, 0x4, bytes.len());
+                    out.extend_from_slice(bytes);
+                }
+                Value::String(text) => push_string(&mut out, text),
+                Value::Array(_) | Value::Dict(_) => unreachable!("containers are flattened"),
+            },
+        }
+    }
+
+    let table = out.len() as u64;
+    let offset_size = size_for(offsets.last().copied().unwrap_or(0));
+    for offset in offsets {
+        push_uint(&mut out, offset, offset_size);
+    }
+
+    out.extend_from_slice(&[0; 6]);
+    out.push(offset_size as u8);
+    out.push(reference_size as u8);
+    push_uint(&mut out, objects.len() as u64, 8);
+    push_uint(&mut out, 0, 8); // The root is the first object
+    push_uint(&mut out, table, 8);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Value {
+        Value::Dict(vec![
+            ("Enabled".to_string(), Value::Bool(true)),
+            ("Disabled".to_string(), Value::Bool(false)),
+            ("Small".to_string(), Value::Integer(7)),
+            ("Port".to_string(), Value::Integer(0x1f90)),
+            ("Large".to_string(), Value::Integer(0x1_0000_0000)),
+            ("Negative".to_string(), Value::Integer(-1)),
+            ("Smallest".to_string(), Value::Integer(i64::MIN)),
+            ("Largest".to_string(), Value::Integer(i64::MAX)),
+            ("Ratio".to_string(), Value::Real(0.25)),
+            ("Threshold".to_string(), Value::Real(-1.5e10)),
+            ("Modified".to_string(), Value::Date(757_691_100.0)), // 2025-01-04T13:45:00Z
+            ("Blob".to_string(), Value::Data((0..=255).collect())),
+            ("Empty blob".to_string(), Value::Data(Vec::new())),
+            ("Name".to_string(), Value::String("A string longer than fifteen bytes".to_string())),
+            ("Accents".to_string(), Value::String("Préférences – ✓ 🔒".to_string())),
+            ("Markup".to_string(), Value::String("<b>\"Fish\" & 'Chips'</b>".to_string())),
+            ("".to_string(), Value::String(String::new())),
+            (
+                "Nested".to_string(),
+                Value::Array(vec![
+                    Value::Dict(vec![("Level".to_string(), Value::Integer(2))]),
+                    Value::Array(Vec::new()),
+                    Value::Dict(Vec::new()),
+                    Value::Array((0..20).map(Value::Integer).collect()),
+                ]),
+            ),
+        ])
+    }
+
+    #[test]
+    fn xml_round_trip() {
+        let xml = to_xml(&sample());
+        assert_eq!(parse(xml.as_bytes()), Ok((sample(), Format::Xml)));
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let bytes = to_binary(&sample());
+        assert!(bytes.starts_with(b"bplist00"));
+        assert_eq!(parse(&bytes), Ok((sample(), Format::Binary)));
+    }
+
+    #[test]
+    fn formats_convert_into_each_other() {
+        let (from_xml, _) = parse(to_xml(&sample()).as_bytes()).expect("xml");
+        assert_eq!(parse_binary(&to_binary(&from_xml)), Ok(sample()));
+        let (from_binary, _) = parse(&to_binary(&sample())).expect("binary");
+        assert_eq!(parse_xml(&to_xml(&from_binary)), Ok(sample()));
+    }
+
+    #[test]
+    fn xml_is_escaped() {
+        let value = Value::Dict(vec![("a<b>&c".to_string(), Value::String("<b>\"Fish\" & Chips</b>".to_string()))]);
+        let xml = to_xml(&value);
+        assert!(xml.contains("<key>a&lt;b&gt;&amp;c</key>"), "{}", xml);
+        assert!(xml.contains("<string>&lt;b&gt;\"Fish\" &amp; Chips&lt;/b&gt;</string>"), "{}", xml);
+        assert!(!xml.contains("& "), "{}", xml);
+    }
+
+    #[test]
+    fn reads_xml_written_by_macos() {
+        let xml = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                   <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+                   <plist version=\"1.0\">\n<dict>\n\
+                   \t<!-- comment -->\n\
+                   \t<key>Hex</key>\n\t<integer>0x1F</integer>\n\
+                   \t<key>Entities</key>\n\t<string>&#233;&#x2713;&quot;&apos;</string>\n\
+                   \t<key>Data</key>\n\t<data>\n\tSGVs\n\tbG8=\n\t</data>\n\
+                   \t<key>When</key>\n\t<date>2001-01-01T00:01:00Z</date>\n\
+                   \t<key>Off</key>\n\t<false></false>\n\
+                   \t<key>None</key>\n\t<string/>\n\
+                   </dict>\n</plist>\n";
+        let value = parse_xml(xml).expect("valid plist");
+        assert_eq!(value.get("Hex"), Some(&Value::Integer(31)));
+        assert_eq!(value.get("Entities"), Some(&Value::String("é✓\"'".to_string())));
+        assert_eq!(value.get("Data"), Some(&Value::Data(b"Hello".to_vec())));
+        assert_eq!(value.get("When"), Some(&Value::Date(60.0)));
+        assert_eq!(value.get("Off"), Some(&Value::Bool(false)));
+        assert_eq!(value.get("None"), Some(&Value::String(String::new())));
+    }
+
+    #[test]
+    fn rejects_invalid_xml() {
+        for xml in [
+            "",
+            "<plist><dict><key>A</key></dict></plist>",
+            "<plist><dict><string>no key</string></dict></plist>",
+            "<plist><integer>twelve</integer></plist>",
+            "<plist><string>&unknown;</string></plist>",
+            "<plist><date>2024-13-01T00:00:00Z</date></plist>",
+            "<plist><true/></plist> trailing",
+            "<plist><array><true/></plist>",
+        ] {
+            assert!(parse_xml(xml).is_err(), "{}", xml);
+        }
+    }
+
+    #[test]
+    fn prints_values_like_defaults() {
+        assert_eq!(Value::Bool(true).defaults_text(), "1");
+        assert_eq!(Value::String("plain text".to_string()).defaults_text(), "plain text");
+        let dict = Value::Dict(vec![
+            ("Enabled".to_string(), Value::Bool(false)),
+            ("Name Space".to_string(), Value::String("a \"b\"".to_string())),
+        ]);
+        assert_eq!(dict.defaults_text(), "{\n    Enabled = 0;\n    \"Name Space\" = \"a \\\"b\\\"\";\n}");
+        assert_eq!(Value::Data(vec![0xde, 0xad]).defaults_text(), "{length = 2, bytes = 0xdead}");
+    }
+
+    #[test]
+    fn rejects_a_truncated_binary_plist() {
+        let bytes = to_binary(&sample());
+        for length in [0, 8, 39, bytes.len() / 2, bytes.len() - 1] {
+            assert!(parse_binary(&bytes[..length]).is_err(), "{} of {} bytes", length, bytes.len());
+        }
+    }
+
+    #[test]
+    fn rejects_a_corrupt_binary_trailer() {
+        let bytes = to_binary(&sample());
+        let trailer = bytes.len() - 32;
+        let corrupt = |position: usize, value: u8| {
+            let mut bytes = bytes.clone();
+            bytes[position] = value;
+            parse_binary(&bytes)
+        };
+
+        assert_eq!(corrupt(trailer + 6, 0), Err("invalid trailer".to_string())); // Offset size
+        assert_eq!(corrupt(trailer + 7, 9), Err("invalid trailer".to_string())); // Reference size
+        assert!(corrupt(trailer + 8, 0xff).is_err()); // Object count
+        assert!(corrupt(trailer + 16, 0xff).is_err()); // Root object
+        assert!(corrupt(trailer + 24, 0xff).is_err()); // Offset table
+        assert!(corrupt(trailer + 31, 0xff).is_err());
+    }
+
+    #[test]
+    fn rejects_objects_referring_to_themselves() {
+        // An array whose only item is the array itself
+        let mut bytes = b"bplist00".to_vec();
+        bytes.extend_from_slice(&[0xa1, 0x00]); // Array of one reference, to object 0
+        bytes.push(8); // Offset table: object 0 at byte 8
+        bytes.extend_from_slice(&[0, 0, 0, 0, 0, 0, 1, 1]);
+        bytes.extend_from_slice(&1u64.to_be_bytes());
+        bytes.extend_from_slice(&0u64.to_be_bytes());
+        bytes.extend_from_slice(&10u64.to_be_bytes());
+        assert_eq!(parse_binary(&bytes), Err("object 0 contains itself".to_string()));
+    }
+
+    #[test]
+    fn rejects_deep_nesting() {
+        let mut value = Value::Array(Vec::new());
+        for _ in 0..MAX_DEPTH + 2 {
+            value = Value::Array(vec![value]);
+        }
+        assert_eq!(parse_binary(&to_binary(&value)), Err("nesting is too deep".to_string()));
+        assert_eq!(parse_xml(&to_xml(&value)), Err("nesting is too deep".to_string()));
+
+        let mut value = Value::Dict(Vec::new());
+        for _ in 0..MAX_DEPTH - 1 {
+            value = Value::Dict(vec![("Key".to_string(), value)]);
+        }
+        assert_eq!(parse_binary(&to_binary(&value)), Ok(value.clone()));
+        assert_eq!(parse_xml(&to_xml(&value)), Ok(value));
+    }
+}
@@ -0,0 +1,108 @@
+// Actions that follow the remediation, such as restarting a daemon so it reads the settings that
+// were just written. A control requests them for the changes of the controls it names (`after`,
+// names or globs as in `requires`); they run once the selected controls have finished, only when
+// one of those commands actually changed something, and once per run however many controls
+// request the same command line.
+
+use crate::catalog::Control;
+use crate::commands::{BashCommand, ExecutionStatus};
+use crate::summary::ControlRun;
+
+pub struct PostAction {
+    pub after: &'static [&'static str], // Controls whose changes call for the action
+    pub user: Option<String>,           // Only changes to this user's settings call for it
+    pub command: BashCommand,
+}
+
+// Post-actions requested by the selected controls that apply to the target's release, with the
+// index of the requesting control
+pub fn requested(controls: &[Control]) -> Vec<(usize, PostAction)> {
+    controls
+        .iter()
+        .enumerate()
+        .filter(|(_, control)| control.not_applicable().is_none())
+        .flat_map(|(index, control)| (control.post_actions)().into_iter().map(move |action| (index, action)))
+        .collect()
+}
+
+// True when a post-action waits for the changes of a control, whose commands are then run with
+// `execute_tracked`
+pub fn awaited(actions: &[(usize, PostAction)], control: &Control) -> bool {
+    actions.iter().any(|(_, action)| action.follows(control))
+}
+
+// Runs a command and compares the state of what it changes before and after (see
+// journal::state): a command that ran but left every value as it was did not change anything
+pub fn execute_tracked(command: &BashCommand) -> crate::commands::ExecutionOutcome {
+    let before = crate::journal::state(command);
+    let mut outcome = command.execute();
+    if outcome.status == ExecutionStatus::Applied && before.is_some() && crate::journal::state(command) == before {
+        outcome.changed = false;
+        println!("➖ Command '{}' left everything as it was.", command.name());
+    }
+    outcome
+}
+
+impl PostAction {
+    fn follows(&self, control: &Control) -> bool {
+        self.after.iter().any(|pattern| control.matches_name(pattern))
+    }
+
+    // Commands of the run whose changes call for the action
+    fn changes(&self, runs: &[ControlRun]) -> usize {
+        runs.iter()
+            .filter(|run| self.follows(&run.control))
+            .flat_map(|run| run.outcomes.iter())
+            .filter(|outcome| outcome.changed && (self.user.is_none() || outcome.user == self.user))
+            .count()
+    }
+}
+
+// Runs the post-actions the changes of a run call for, adding their outcomes to the runs of the
+// controls requesting them
+pub fn run(actions: &[(usize, PostAction)], runs: &mut [ControlRun]) {
+    let mut done: Vec<Vec<String>> = Vec::new();
+    for (index, action) in actions {
+        let argv = action.command.argv();
+        if done.contains(&argv) {
+            continue;
+        }
+        match action.changes(runs) {
+            0 => println!("➖ Post-action '{}' skipped: no change by {}.", action.command.name(), describe(action)),
+            changes => {
+                println!("🔄 Post-action '{}' after {} change(s).", action.command.name(), changes);
+                let mut outcome = action.command.execute();
+                outcome.changed = false; // A restart is not a change other post-actions follow
+                runs[*index].outcomes.push(outcome);
+                done.push(argv);
+            }
+        }
+    }
+}
+
+// Prints the post-actions of a dry run, once per command line
+pub fn plan(actions: &[(usize, PostAction)], first_step: usize) {
+    let mut planned: Vec<Vec<String>> = Vec::new();
+    for (_, action) in actions {
+        let argv = action.command.argv();
+        if planned.contains(&argv) {
+            continue;
+        }
+        action.command.plan(first_step + planned.len());
+        println!("    runs only after a change by {}", describe(action));
+        planned.push(argv);
+    }
+}
+
+// Controls (and user) an action follows, e.g. "c71 for bob"
+fn describe(action: &PostAction) -> String {
+    let after: Vec<&str> = action
+        .after
+        .iter()
+        .map(|pattern| if *pattern == "*" { "any selected control" } else { pattern })
+        .collect();
+    match &action.user {
+        Some(user) => format!("{} for {}", after.join(", "), user),
+        None => after.join(", "),
+    }
+}
@@ -0,0 +1,217 @@
+// Environment checks run before `apply` changes anything, and by the `preflight` command: who the
+// tool runs as, the macOS release and architecture, SIP, FileVault, MDM enrollment and the files
+// the controls expect. Under --root only the target tree is inspected, the state of the running
+// system does not matter there.
+
+use crate::os::{OsVersion, Release};
+
+// How a finding affects an apply run, from harmless to blocking
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Verdict {
+    Ok,        // As expected
+    Warning,   // The run goes on, but the finding is worth knowing
+    AuditOnly, // Changes would fail or do harm: apply runs the audit instead
+    Abort,     // Nothing can be run on this host
+}
+
+pub struct Finding {
+    pub verdict: Verdict,
+    pub message: String,
+}
+
+pub struct Preflight {
+    pub os: Option<&'static OsVersion>,
+    pub arch: Option<String>,
+    pub findings: Vec<Finding>,
+}
+
+impl Preflight {
+    // Worst verdict of the findings
+    pub fn verdict(&self) -> Verdict {
+        self.findings.iter().map(|finding| finding.verdict).max().unwrap_or(Verdict::Ok)
+    }
+
+    pub fn print(&self) {
+        let os = match &self.os {
+            Some(os) if os.build.is_empty() => format!("macOS {}", os.text),
+            Some(os) => format!("macOS {} ({})", os.text, os.build),
+            None => "unknown macOS version".to_string(),
+        };
+        println!("============================================");
+        println!("Preflight: {}, {}", os, self.arch.as_deref().unwrap_or("unknown architecture"));
+        println!("============================================");
+        for finding in &self.findings {
+            let icon = match finding.verdict {
+                Verdict::Ok => "✅",
+                Verdict::Warning => "⚠️ ",
+                Verdict::AuditOnly => "⛔",
+                Verdict::Abort => "❌",
+            };
+            println!("{} {}", icon, finding.message);
+        }
+    }
+}
+
+// Trimmed stdout of a command, None when it failed or printed nothing; Err when it cannot be started
+fn read(executable: &str, args: &[&str]) -> std::io::Result<Option<String>> {
+    let output = crate::runner::run(executable, args)?;
+    Ok(Some(output.stdout.trim().to_string()).filter(|stdout| output.success() && !stdout.is_empty()))
+}
+
+fn finding(verdict: Verdict, message: impl Into<String>) -> Finding {
+    Finding {
+        verdict,
+        message: message.into(),
+    }
+}
+
+pub fn run() -> Preflight {
+    let mut findings = Vec::new();
+
+    let os = match crate::os::detected() {
+        Ok(os) => os.as_ref(),
+        Err(error) => {
+            findings.push(finding(
+                Verdict::Abort,
+                format!("sw_vers cannot be run ({}): this is not macOS (use --root for a disk image, or --simulate)", error),
+            ));
+            None
+        }
+    };
+    let (oldest, newest) = (Release::Monterey, Release::Sequoia);
+    match os {
+        Some(os) if os.major < oldest.major() => findings.push(finding(
+            Verdict::AuditOnly,
+            format!("macOS {} is older than {}, the commands rely on its tools", os.text, oldest.name()),
+        )),
+        Some(os) if os.major > newest.major() => findings.push(finding(
+            Verdict::Warning,
+            format!(
+                "macOS {} is newer than {}, the newest release the controls were reviewed against (its variants are used)",
+                os.text,
+                newest.name()
+            ),
+        )),
+        Some(os) => findings.push(finding(
+            Verdict::Ok,
+            format!("macOS {} is supported, controls follow the {}", os.text, crate::os::release().benchmark()),
+        )),
+        None if crate::os::detected().is_ok() => findings.push(finding(
+            Verdict::Warning,
+            if crate::root::is_offline() {
+                format!("No readable {} under the root: the controls for {} are used", crate::os::SYSTEM_VERSION, oldest.name())
+            } else {
+                format!("The macOS version could not be read from sw_vers: the controls for {} are used", oldest.name())
+            },
+        )),
+        None => {}
+    }
+
+    let arch = if crate::root::is_offline() {
+        None
+    } else {
+        read("/usr/bin/uname", &["-m"]).ok().flatten()
+    };
+    if let Some(arch) = arch.as_deref().filter(|arch| !["arm64", "x86_64"].contains(arch)) {
+        findings.push(finding(Verdict::Warning, format!("Unexpected architecture {}", arch)));
+    }
+
+    // Tools some controls run while the catalog is built, to find out what they have to change
+    let root = crate::root::path("/");
+    for (executable, args, controls) in [
+        ("/usr/sbin/sysctl", vec!["-n", "machdep.cpu.brand_string"], "c36"),
+        ("find", vec![root.as_str(), "-maxdepth", "0"], "c48, c49 and c50"),
+    ] {
+        if let Err(error) = crate::runner::run(executable, &args) {
+            findings.push(finding(
+                Verdict::Warning,
+                format!("{} cannot be run ({}): {} will report it and change nothing", executable, error, controls),
+            ));
+        }
+    }
+
+    if crate::root::is_offline() {
+        findings.push(finding(
+            Verdict::Ok,
+            "Working on the --root tree: the running system (user, SIP, FileVault, MDM) is not checked",
+        ));
+    } else {
+        findings.extend(running_system());
+    }
+
+    if !std::path::Path::new(&crate::root::path("/Users")).is_dir() {
+        findings.push(finding(
+            Verdict::AuditOnly,
+            "/Users does not exist: this does not look like a macOS system volume",
+        ));
+    }
+    for (path, controls) in [
+        (crate::audit_control::PATH, "c38, c40 and c41"),
+        (crate::asl::INSTALL_CONFIG, "c39"),
+    ] {
+        if !std::path::Path::new(&crate::root::path(path)).is_file() {
+            findings.push(finding(
+                Verdict::Warning,
+                format!("{} does not exist: {} will report it and change nothing", path, controls),
+            ));
+        }
+    }
+
+    Preflight { os, arch, findings }
+}
+
+// User the tool runs as, SIP, FileVault and MDM enrollment of the running system
+fn running_system() -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut state = |output: Option<String>, unknown: &str, judge: &dyn Fn(&str) -> Finding| {
+        findings.push(match output {
+            Some(output) => judge(&output),
+            None => finding(Verdict::Warning, unknown),
+        });
+    };
+
+    state(read("/usr/bin/id", &["-u"]).ok().flatten(), "The user the tool runs as could not be read", &|uid| {
+        match uid {
+            "0" => finding(Verdict::Ok, "Running as root"),
+            uid => finding(
+                Verdict::AuditOnly,
+                format!("Not running as root (uid {}): run it with sudo so the commands can change the system", uid),
+            ),
+        }
+    });
+    state(read("/usr/bin/csrutil", &["status"]).ok().flatten(), "The SIP status could not be read", &|status| {
+        if status.contains("enabled") {
+            finding(Verdict::Ok, "System Integrity Protection is enabled")
+        } else {
+            finding(
+                Verdict::Warning,
+                "System Integrity Protection is disabled: system files the controls rely on are not protected",
+            )
+        }
+    });
+    state(read("/usr/bin/fdesetup", &["status"]).ok().flatten(), "The FileVault status could not be read", &|status| {
+        if status.contains("FileVault is On") {
+            finding(Verdict::Ok, "FileVault is on")
+        } else if status.contains("FileVault is Off") {
+            finding(Verdict::Warning, "FileVault is off: the disk is not encrypted")
+        } else {
+            finding(Verdict::Warning, format!("FileVault is not fully on: {}", status))
+        }
+    });
+    // e.g. "Enrolled via DEP: No" and "MDM enrollment: Yes (User Approved)"
+    state(
+        read("/usr/bin/profiles", &["status", "-type", "enrollment"]).ok().flatten(),
+        "The MDM enrollment could not be read",
+        &|status| {
+            if status.lines().any(|line| line.starts_with("MDM enrollment: Yes")) {
+                finding(
+                    Verdict::Warning,
+                    "Enrolled in MDM: settings managed by configuration profiles take precedence over the ones apply writes",
+                )
+            } else {
+                finding(Verdict::Ok, "Not enrolled in MDM")
+            }
+        },
+    );
+    findings
+}
@@ -0,0 +1,224 @@
+// Configuration profile (.mobileconfig) built from the preference-based controls, to upload to an
+// MDM server: settings a profile manages take precedence over values written with `defaults`.
+// The profile is unsigned XML; UUIDs are derived from the identifiers, so exporting again
+// produces an update of the same profile rather than a new one.
+
+use crate::catalog::Control;
+use crate::plist::Value;
+
+pub const DEFAULT_PATH: &str = "cis-compliance.mobileconfig";
+
+const FIREWALL: &str = "com.apple.security.firewall";
+const CUSTOM_SETTINGS: &str = "com.apple.ManagedClient.preferences";
+
+// Managed preference and the control it comes from
+struct Setting {
+    control: &'static str,
+    domain: String, // Payload type, e.g. "com.apple.loginwindow"
+    key: String,
+    value: Value,
+}
+
+// Result of an export
+pub struct Profile {
+    pub content: Value,
+    pub settings: usize,
+    pub payloads: usize,
+    pub skipped: Vec<&'static str>, // Selected controls that no payload can manage
+    pub warnings: Vec<String>,      // Settings dropped because an earlier control sets another value
+}
+
+// Controls applied with a tool other than `defaults`, and the payload settings managing the same thing
+fn managed_settings(control: &str) -> Option<Vec<(&'static str, &'static str, Value)>> {
+    use crate::browser::{CHROME, EDGE, FIREFOX};
+
+    let firewall = |key, value| Some(vec![(FIREWALL, key, value)]);
+    match control {
+        "c25_enable_firewall" => firewall("EnableFirewall", Value::Bool(true)),
+        "c26_enable_firewall_stealth_mode" => firewall("EnableStealthMode", Value::Bool(true)),
+        "c42_1_enable_logging_mode" => firewall("EnableLogging", Value::Bool(true)),
+        "c42_2_configure_logging_detail" => firewall("LoggingOption", Value::String("detail".to_string())),
+        "c81_configure_chrome_policies" => Some(policies(&CHROME)),
+        "c82_configure_edge_policies" => Some(policies(&EDGE)),
+        "c83_configure_firefox_policies" => {
+            // Firefox only reads policies from its preference domain when this key is set
+            let mut settings = vec![(FIREFOX.domain, "EnterprisePoliciesEnabled", Value::Bool(true))];
+            settings.extend(policies(&FIREFOX));
+            Some(settings)
+        }
+        _ => None,
+    }
+}
+
+// Browser policies as settings of the browser's preference domain
+fn policies(browser: &crate::browser::Browser) -> Vec<(&'static str, &'static str, Value)> {
+    let domain = browser.domain;
+    browser.policies().into_iter().map(|(key, value)| (domain, key, value)).collect()
+}
+
+// Preference domain as a payload type, e.g.
+// "/Users/bob/Library/Containers/com.apple.Safari/Data/Library/Preferences/com.apple.Safari" -> "com.apple.Safari"
+fn payload_type(domain: &str) -> String {
+    let name = domain.rsplit('/').next().unwrap_or(domain);
+    match name.strip_suffix(".plist").unwrap_or(name) {
+        // Crash reporter settings are managed through the SubmitDiagInfo domain
+        "DiagnosticMessagesHistory" => "com.apple.SubmitDiagInfo".to_string(),
+        name => name.to_string(),
+    }
+}
+
+// Settings written by the controls, once each (per-user commands repeat them for every user)
+fn collect(controls: &[Control]) -> (Vec<Setting>, Vec<&'static str>, Vec<String>) {
+    let mut settings: Vec<Setting> = Vec::new();
+    let mut skipped = Vec::new();
+    let mut warnings = Vec::new();
+
+    for control in controls {
+        let found: Vec<Setting> = match managed_settings(control.name) {
+            Some(settings) => settings
+                .into_iter()
+                .map(|(domain, key, value)| Setting {
+                    control: control.name,
+                    domain: domain.to_string(),
+                    key: key.to_string(),
+                    value,
+                })
+                .collect(),
+            None => (control.commands)()
+                .iter()
+                .filter_map(|command| crate::commands::preference_change(&command.argv()))
+                .filter_map(|change| {
+                    Some(Setting {
+                        control: control.name,
+                        domain: payload_type(&change.domain),
+                        key: change.key,
+                        value: change.value?,
+                    })
+                })
+                .collect(),
+        };
+        if found.is_empty() {
+            skipped.push(control.name);
+        }
+
+        for setting in found {
+            match settings.iter().find(|other| other.domain == setting.domain && other.key == setting.key) {
+                Some(other) if other.value != setting.value => warnings.push(format!(
+                    "{} sets {} {} to {}, already set to {} by {}: keeping the first value",
+                    setting.control,
+                    setting.domain,
+                    setting.key,
+                    setting.value.defaults_text(),
+                    other.value.defaults_text(),
+                    other.control
+                )),
+                Some(_) => {}
+                None => settings.push(setting),
+            }
+        }
+    }
+    (settings, skipped, warnings)
+}
+
+// Name-based UUID (version 8), stable for a given identifier
+fn uuid(name: &str) -> String {
+    // FNV-1a, run twice with different offsets for 128 bits
+    let hash = |offset: u64| {
+        name.bytes()
+            .fold(offset, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3))
+    };
+    let mut bytes = [0u8; 16];
+    bytes[..8].copy_from_slice(&hash(0xcbf2_9ce4_8422_2325).to_be_bytes());
+    bytes[8..].copy_from_slice(&hash(0x6c62_272e_07bb_0142).to_be_bytes());
+    bytes[6] = (bytes[6] & 0x0f) | 0x80;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex: String = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+fn text(value: &str) -> Value {
+    Value::String(value.to_string())
+}
+
+// Payload dictionary with the keys every payload has, followed by its settings
+fn payload(payload_type: &str, identifier: String, display_name: &str, settings: Vec<(String, Value)>) -> Value {
+    let mut entries = vec![
+        ("PayloadType".to_string(), text(payload_type)),
+        ("PayloadVersion".to_string(), Value::Integer(1)),
+        ("PayloadUUID".to_string(), text(&uuid(&identifier))),
+        ("PayloadIdentifier".to_string(), Value::String(identifier)),
+        ("PayloadDisplayName".to_string(), text(display_name)),
+        ("PayloadEnabled".to_string(), Value::Bool(true)),
+    ];
+    entries.extend(settings);
+    Value::Dict(entries)
+}
+
+// Builds the profile: one payload per domain when `split`, otherwise one Custom Settings payload
+// holding every domain (the firewall always has its own payload type)
+pub fn export(controls: &[Control], split: bool) -> Result<Profile, String> {
+    let (settings, skipped, warnings) = collect(controls);
+    if settings.is_empty() {
+        return Err("None of the selected controls sets a preference a profile can manage".to_string());
+    }
+
+    let config = crate::config::current();
+    let base = config.profile_identifier.as_str();
+
+    // Domains in the order the controls set them
+    let mut domains: Vec<(String, Vec<(String, Value)>)> = Vec::new();
+    for setting in &settings {
+        let entry = (setting.key.clone(), setting.value.clone());
+        match domains.iter_mut().find(|(domain, _)| *domain == setting.domain) {
+            Some((_, entries)) => entries.push(entry),
+            None => domains.push((setting.domain.clone(), vec![entry])),
+        }
+    }
+
+    let mut payloads = Vec::new();
+    let mut custom = Vec::new();
+    for (domain, entries) in domains {
+        let identifier = format!("{}.{}", base, domain);
+        if split || domain == FIREWALL {
+            let name = if domain == FIREWALL { "Firewall" } else { domain.as_str() };
+            payloads.push(payload(&domain, identifier, name, entries));
+        } else {
+            // Custom Settings format: <domain> → Forced → [{ mcx_preference_settings: {...} }]
+            let forced = Value::Dict(vec![("mcx_preference_settings".to_string(), Value::Dict(entries))]);
+            custom.push((domain, Value::Dict(vec![("Forced".to_string(), Value::Array(vec![forced]))])));
+        }
+    }
+    if !custom.is_empty() {
+        let content = vec![("PayloadContent".to_string(), Value::Dict(custom))];
+        payloads.insert(0, payload(CUSTOM_SETTINGS, format!("{}.preferences", base), "Custom Settings", content));
+    }
+
+    let count = payloads.len();
+    let mut names: Vec<&str> = settings.iter().map(|setting| setting.control).collect();
+    names.dedup();
+    let mut entries = vec![
+        ("PayloadContent".to_string(), Value::Array(payloads)),
+        ("PayloadDisplayName".to_string(), text("CIS macOS Hardening")),
+        ("PayloadDescription".to_string(), Value::String(format!("Settings of the controls {}", names.join(", ")))),
+        ("PayloadIdentifier".to_string(), text(base)),
+    ];
+    // The placeholder is only meaningful in the banner template
+    if config.organization != "[ORGANIZATION]" {
+        entries.push(("PayloadOrganization".to_string(), text(&config.organization)));
+    }
+    entries.extend([
+        ("PayloadScope".to_string(), text("System")),
+        ("PayloadType".to_string(), text("Configuration")),
+        ("PayloadUUID".to_string(), text(&uuid(base))),
+        ("PayloadVersion".to_string(), Value::Integer(1)),
+    ]);
+
+    Ok(Profile {
+        content: Value::Dict(entries),
+        settings: settings.len(),
+        payloads: count,
+        skipped,
+        warnings,
+    })
+}
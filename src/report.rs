@@ -0,0 +1,349 @@
+mod html;
+mod json;
+mod junit;
+
+use crate::catalog::{section_name, Assessment};
+use crate::checks::CheckStatus;
+use crate::commands::ExecutionStatus;
+use crate::summary::{ControlAudit, ControlRun};
+use json::Json;
+
+// Machine-readable report formats
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ReportFormat {
+    Json,
+    Junit,
+    Html,
+}
+
+impl ReportFormat {
+    pub fn parse(value: &str) -> Option<ReportFormat> {
+        match value {
+            "json" => Some(ReportFormat::Json),
+            "junit" => Some(ReportFormat::Junit),
+            "html" => Some(ReportFormat::Html),
+            _ => None,
+        }
+    }
+
+    // File written when no --output is given, e.g. "cis-audit-report.json"
+    pub fn default_path(&self, mode: &str) -> String {
+        match self {
+            ReportFormat::Json => format!("cis-{}-report.json", mode),
+            ReportFormat::Junit => format!("cis-{}-report.xml", mode),
+            ReportFormat::Html => format!("cis-{}-report.html", mode),
+        }
+    }
+}
+
+// Outcome of a single entry, covering both hardening runs and audits
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Outcome {
+    Applied,
+    Failed,
+    Skipped,
+    Pass,
+    Fail,
+    NotApplicable,
+}
+
+impl Outcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Outcome::Applied => "applied",
+            Outcome::Failed => "failed",
+            Outcome::Skipped => "skipped",
+            Outcome::Pass => "pass",
+            Outcome::Fail => "fail",
+            Outcome::NotApplicable => "not_applicable",
+        }
+    }
+}
+
+// Information about the machine the report was produced on
+pub struct HostInfo {
+    pub hostname: String,
+    pub os_version: String,
+    pub os_build: String,
+    pub model: String,
+    pub architecture: &'static str,
+    pub user: String, // Account that started the tool (the sudo caller when run with sudo)
+}
+
+impl HostInfo {
+    pub fn collect() -> HostInfo {
+        let read = |executable: &str, args: &[&str]| {
+            crate::checks::read_output(executable, args).unwrap_or_else(|| "unknown".to_string())
+        };
+
+        HostInfo {
+            hostname: read("/bin/hostname", &[]),
+            os_version: read("/usr/bin/sw_vers", &["-productVersion"]),
+            os_build: read("/usr/bin/sw_vers", &["-buildVersion"]),
+            model: read("/usr/sbin/sysctl", &["-n", "hw.model"]),
+            architecture: std::env::consts::ARCH,
+            user: std::env::var("SUDO_USER")
+                .or_else(|_| std::env::var("USER"))
+                .unwrap_or_else(|_| "unknown".to_string()),
+        }
+    }
+}
+
+// One command (apply) or check (audit), for one user when the control is per-user
+pub struct ReportEntry {
+    pub control: &'static str,
+    pub cis_id: &'static str,
+    pub title: &'static str,
+    pub level: u8,
+    pub assessment: Assessment,
+    pub name: &'static str,             // Command or check name
+    pub description: &'static str,
+    pub user: Option<String>,
+    pub outcome: Outcome,
+    pub command: Option<String>,        // Command line that was executed (apply only)
+    pub exit_code: Option<i32>,
+    pub duration: Option<std::time::Duration>,
+    pub output: String,                 // stdout of the command, or the detail of the check
+    pub error: String,                  // stderr of the command
+}
+
+// Outcomes of the per-user commands or checks for one account, or why the run left it out
+pub struct UserOutcome {
+    pub name: String,
+    pub kind: &'static str,        // "human" or "admin"
+    pub excluded: Option<String>,  // e.g. "excluded by 'lab*'"
+    pub counts: Vec<(Outcome, usize)>,
+}
+
+pub struct Report {
+    pub mode: &'static str, // "apply" or "audit"
+    pub benchmark: &'static str, // Edition the controls followed, the one of the target's release
+    pub generated_at: std::time::SystemTime,
+    pub host: HostInfo,
+    pub entries: Vec<ReportEntry>,
+    pub users: Vec<UserOutcome>, // Empty when no per-user control ran
+}
+
+impl Report {
+    pub fn from_runs(runs: &[ControlRun]) -> Report {
+        let entries = runs
+            .iter()
+            .flat_map(|run| {
+                run.outcomes.iter().map(move |outcome| ReportEntry {
+                    control: run.control.name,
+                    cis_id: run.control.cis_id,
+                    title: run.control.title,
+                    level: run.control.level,
+                    assessment: run.control.assessment,
+                    name: outcome.name,
+                    description: outcome.description,
+                    user: outcome.user.clone(),
+                    outcome: match outcome.status {
+                        ExecutionStatus::Applied => Outcome::Applied,
+                        ExecutionStatus::Failed => Outcome::Failed,
+                        ExecutionStatus::Skipped => Outcome::Skipped,
+                        ExecutionStatus::NotApplicable => Outcome::NotApplicable,
+                    },
+                    command: Some(outcome.command_line()),
+                    exit_code: outcome.exit_code,
+                    duration: Some(outcome.duration),
+                    output: outcome.stdout.clone(),
+                    error: outcome.stderr.clone(),
+                })
+            })
+            .collect();
+
+        Report::new("apply", entries)
+    }
+
+    pub fn from_audits(audits: &[ControlAudit]) -> Report {
+        let entries = audits
+            .iter()
+            .flat_map(|audit| {
+                audit.results.iter().map(move |result| ReportEntry {
+                    control: audit.control.name,
+                    cis_id: audit.control.cis_id,
+                    title: audit.control.title,
+                    level: audit.control.level,
+                    assessment: audit.control.assessment,
+                    name: result.name,
+                    description: result.description,
+                    user: result.user.clone(),
+                    outcome: match result.status {
+                        CheckStatus::Pass => Outcome::Pass,
+                        CheckStatus::Fail => Outcome::Fail,
+                        CheckStatus::NotApplicable => Outcome::NotApplicable,
+                    },
+                    command: None,
+                    exit_code: None,
+                    duration: None,
+                    output: result.detail.clone(),
+                    error: String::new(),
+                })
+            })
+            .collect();
+
+        Report::new("audit", entries)
+    }
+
+    fn new(mode: &'static str, entries: Vec<ReportEntry>) -> Report {
+        let mut report = Report {
+            mode,
+            benchmark: crate::os::release().benchmark(),
+            generated_at: std::time::SystemTime::now(),
+            host: HostInfo::collect(),
+            entries,
+            users: Vec::new(),
+        };
+        if report.entries.iter().any(|entry| entry.user.is_some()) {
+            report.users = crate::users::human()
+                .into_iter()
+                .map(|user| UserOutcome {
+                    name: user.name.clone(),
+                    kind: user.kind.as_str(),
+                    excluded: crate::users::exclusion(&user.name),
+                    counts: report
+                        .outcomes()
+                        .into_iter()
+                        .map(|outcome| {
+                            let count = report
+                                .entries
+                                .iter()
+                                .filter(|entry| entry.outcome == outcome && entry.user.as_deref() == Some(user.name.as_str()))
+                                .count();
+                            (outcome, count)
+                        })
+                        .collect(),
+                })
+                .collect();
+        }
+        report
+    }
+
+    // Outcomes entries of this mode can have
+    pub fn outcomes(&self) -> Vec<Outcome> {
+        match self.mode {
+            "audit" => vec![Outcome::Pass, Outcome::Fail, Outcome::NotApplicable],
+            _ => vec![Outcome::Applied, Outcome::Failed, Outcome::Skipped, Outcome::NotApplicable],
+        }
+    }
+
+    pub fn count(&self, outcome: Outcome) -> usize {
+        self.entries.iter().filter(|entry| entry.outcome == outcome).count()
+    }
+
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Json => self.to_json().to_pretty_string(),
+            ReportFormat::Junit => junit::render(self),
+            ReportFormat::Html => html::render(self),
+        }
+    }
+
+    pub fn write(&self, format: ReportFormat, path: &str) -> std::io::Result<()> {
+        std::fs::write(path, self.render(format))
+    }
+
+    fn to_json(&self) -> Json {
+        Json::Object(vec![
+            ("benchmark", Json::string(self.benchmark)),
+            ("tool", Json::Object(vec![
+                ("name", Json::string(env!("CARGO_PKG_NAME"))),
+                ("version", Json::string(env!("CARGO_PKG_VERSION"))),
+            ])),
+            ("mode", Json::string(self.mode)),
+            ("generated_at", Json::String(format_timestamp(self.generated_at))),
+            ("host", Json::Object(vec![
+                ("hostname", Json::string(&self.host.hostname)),
+                ("os_version", Json::string(&self.host.os_version)),
+                ("os_build", Json::string(&self.host.os_build)),
+                ("model", Json::string(&self.host.model)),
+                ("architecture", Json::string(self.host.architecture)),
+                ("user", Json::string(&self.host.user)),
+            ])),
+            // True when nothing failed (apply) or every applicable check passed (audit)
+            ("success", Json::Bool(self.count(Outcome::Failed) + self.count(Outcome::Fail) == 0)),
+            ("summary", Json::Object(
+                self.outcomes()
+                    .into_iter()
+                    .map(|outcome| (outcome.as_str(), Json::Int(self.count(outcome) as i64)))
+                    .collect(),
+            )),
+            ("users", Json::Array(self.users.iter().map(|user| {
+                Json::Object(vec![
+                    ("user", Json::string(&user.name)),
+                    ("kind", Json::string(user.kind)),
+                    ("excluded", Json::optional_string(user.excluded.as_deref())),
+                    ("summary", Json::Object(
+                        user.counts
+                            .iter()
+                            .map(|(outcome, count)| (outcome.as_str(), Json::Int(*count as i64)))
+                            .collect(),
+                    )),
+                ])
+            }).collect())),
+            ("entries", Json::Array(self.entries.iter().map(|entry| {
+                Json::Object(vec![
+                    ("control", Json::string(entry.control)),
+                    ("cis_id", Json::string(entry.cis_id)),
+                    ("title", Json::string(entry.title)),
+                    ("section", Json::string(section_name(entry.cis_id))),
+                    ("level", if entry.level == 0 { Json::Null } else { Json::Int(entry.level as i64) }),
+                    ("assessment", Json::string(entry.assessment.as_str())),
+                    ("name", Json::string(entry.name)),
+                    ("description", Json::string(entry.description)),
+                    ("user", Json::optional_string(entry.user.as_deref())),
+                    ("outcome", Json::string(entry.outcome.as_str())),
+                    ("command", Json::optional_string(entry.command.as_deref())),
+                    ("exit_code", entry.exit_code.map(|code| Json::Int(code as i64)).unwrap_or(Json::Null)),
+                    ("duration_seconds", entry.duration.map(|d| Json::Float(d.as_secs_f64())).unwrap_or(Json::Null)),
+                    ("output", Json::string(&entry.output)),
+                    ("error", Json::string(&entry.error)),
+                ])
+            }).collect())),
+        ])
+    }
+}
+
+// Formats a time as an ISO 8601 UTC timestamp, e.g. "2024-01-10T13:45:00Z"
+pub fn format_timestamp(time: std::time::SystemTime) -> String {
+    let seconds = time
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or(0);
+    let (days, rem) = (seconds.div_euclid(86400), seconds.rem_euclid(86400));
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, rem / 3600, rem % 3600 / 60, rem % 60
+    )
+}
+
+// Escapes text for XML/HTML attributes and content, dropping characters XML 1.0 does not allow
+fn escape_markup(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if (c as u32) < 0x20 => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,147 @@
+use super::{escape_markup as escape, format_timestamp, Outcome, Report, ReportEntry};
+use crate::catalog::section_name;
+
+// Inline stylesheet so the report works offline as a single file
+const STYLE: &str = "
+body { font-family: -apple-system, Helvetica, Arial, sans-serif; margin: 2em; color: #222; }
+h1 { margin-bottom: 0.2em; }
+table { border-collapse: collapse; width: 100%; margin-bottom: 2em; }
+th, td { border-bottom: 1px solid #ddd; padding: 0.4em 0.6em; text-align: left; vertical-align: top; }
+th { background: #f4f4f4; }
+.host td { border: none; padding: 0.1em 1em 0.1em 0; }
+.score { font-size: 3em; font-weight: bold; }
+.badge { border-radius: 4px; padding: 0.1em 0.5em; color: #fff; font-size: 0.85em; white-space: nowrap; }
+.ok { background: #2e7d32; }
+.bad { background: #c62828; }
+.neutral { background: #757575; }
+pre { background: #f8f8f8; padding: 0.6em; white-space: pre-wrap; word-break: break-all; }
+summary { cursor: pointer; }
+";
+
+// Renders the report as a standalone HTML page with a score and one table per CIS section
+pub fn render(report: &Report) -> String {
+    let (good, bad) = match report.mode {
+        "audit" => (report.count(Outcome::Pass), report.count(Outcome::Fail)),
+        _ => (report.count(Outcome::Applied), report.count(Outcome::Failed)),
+    };
+    let neutral = report.entries.len() - good - bad;
+    let score = (good * 100).checked_div(good + bad).unwrap_or(100);
+
+    let mut html = String::from("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(&format!(
+        "<title>CIS compliance report - {}</title>\n<style>{}</style>\n</head>\n<body>\n",
+        escape(&report.host.hostname),
+        STYLE
+    ));
+    html.push_str(&format!("<h1>CIS compliance report - {}</h1>\n", escape(&report.host.hostname)));
+    html.push_str(&format!("<p>{} &middot; {} run</p>\n", escape(report.benchmark), report.mode));
+
+    html.push_str("<table class=\"host\">\n");
+    for (label, value) in [
+        ("Generated", format_timestamp(report.generated_at)),
+        ("macOS", format!("{} ({})", report.host.os_version, report.host.os_build)),
+        ("Model", format!("{} ({})", report.host.model, report.host.architecture)),
+        ("Run by", report.host.user.clone()),
+    ] {
+        html.push_str(&format!("<tr><td><b>{}</b></td><td>{}</td></tr>\n", label, escape(&value)));
+    }
+    html.push_str("</table>\n");
+
+    let (good_label, bad_label, neutral_label) = match report.mode {
+        "audit" => ("passed", "failed", "not applicable"),
+        _ => ("applied", "failed", "skipped"),
+    };
+    html.push_str(&format!(
+        "<p><span class=\"score\">{}%</span><br>{} {}, {} {}, {} {}</p>\n",
+        score, good, good_label, bad, bad_label, neutral, neutral_label
+    ));
+
+    if !report.users.is_empty() {
+        html.push_str("<h2>Users</h2>\n<table>\n<tr><th>User</th><th>Account</th><th>Outcome</th></tr>\n");
+        for user in &report.users {
+            let outcome = match &user.excluded {
+                Some(reason) => format!("<span class=\"badge neutral\">left out</span> {}", escape(reason)),
+                None => user
+                    .counts
+                    .iter()
+                    .map(|(outcome, count)| format!("{} {}", count, outcome.as_str().replace('_', " ")))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            };
+            html.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                escape(&user.name),
+                user.kind,
+                outcome
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+
+    // Entries grouped by top-level CIS section, in benchmark order
+    let mut sections: std::collections::BTreeMap<&str, Vec<&ReportEntry>> = std::collections::BTreeMap::new();
+    for entry in &report.entries {
+        sections.entry(section_name(entry.cis_id)).or_default().push(entry);
+    }
+
+    for (section, entries) in sections {
+        let failed = entries
+            .iter()
+            .filter(|entry| matches!(entry.outcome, Outcome::Failed | Outcome::Fail))
+            .count();
+        html.push_str(&format!(
+            "<h2>{}</h2>\n<p>{} entries, {} {}</p>\n",
+            escape(section),
+            entries.len(),
+            failed,
+            bad_label
+        ));
+        html.push_str("<table>\n<tr><th>CIS</th><th>Level</th><th>Control</th><th>User</th><th>Outcome</th><th>Details</th></tr>\n");
+        for entry in entries {
+            html.push_str(&render_entry(entry));
+        }
+        html.push_str("</table>\n");
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn render_entry(entry: &ReportEntry) -> String {
+    let class = match entry.outcome {
+        Outcome::Applied | Outcome::Pass => "ok",
+        Outcome::Failed | Outcome::Fail => "bad",
+        Outcome::Skipped | Outcome::NotApplicable => "neutral",
+    };
+
+    let mut details = String::new();
+    if let Some(command) = &entry.command {
+        details.push_str(&format!("$ {}\n", command));
+    }
+    if let Some(code) = entry.exit_code {
+        details.push_str(&format!("exit code {}\n", code));
+    }
+    if !entry.output.trim().is_empty() {
+        details.push_str(&format!("{}\n", entry.output.trim_end()));
+    }
+    if !entry.error.trim().is_empty() {
+        details.push_str(&format!("{}\n", entry.error.trim_end()));
+    }
+
+    format!(
+        "<tr><td>{}</td><td>{}</td><td>{}<br><small>{}</small></td><td>{}</td><td><span class=\"badge {}\">{}</span></td>\
+         <td><details><summary>{}</summary><pre>{}</pre></details></td></tr>\n",
+        escape(entry.cis_id),
+        match entry.level {
+            0 => "-".to_string(),
+            level => format!("L{} {}", level, entry.assessment.as_str()),
+        },
+        escape(entry.title),
+        escape(entry.name),
+        escape(entry.user.as_deref().unwrap_or("")),
+        class,
+        entry.outcome.as_str(),
+        escape(entry.description),
+        escape(&details)
+    )
+}
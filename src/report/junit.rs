@@ -0,0 +1,94 @@
+use super::{escape_markup as escape, format_timestamp, Outcome, Report};
+
+// Renders the report as JUnit XML: one testcase per command (apply) or check (audit),
+// grouped by control through the testcase classname
+pub fn render(report: &Report) -> String {
+    let failures = report.count(Outcome::Failed) + report.count(Outcome::Fail);
+    let skipped = report.count(Outcome::Skipped) + report.count(Outcome::NotApplicable);
+    let time: f64 = report
+        .entries
+        .iter()
+        .filter_map(|entry| entry.duration)
+        .map(|duration| duration.as_secs_f64())
+        .sum();
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuites name=\"{}\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{:.3}\">\n",
+        escape(report.benchmark),
+        report.entries.len(),
+        failures,
+        skipped,
+        time
+    ));
+    xml.push_str(&format!(
+        "  <testsuite name=\"{} ({})\" hostname=\"{}\" timestamp=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"{}\" time=\"{:.3}\">\n",
+        escape(report.benchmark),
+        report.mode,
+        escape(&report.host.hostname),
+        format_timestamp(report.generated_at),
+        report.entries.len(),
+        failures,
+        skipped,
+        time
+    ));
+    xml.push_str("    <properties>\n");
+    for (name, value) in [
+        ("mode", report.mode),
+        ("os_version", report.host.os_version.as_str()),
+        ("os_build", report.host.os_build.as_str()),
+        ("model", report.host.model.as_str()),
+        ("architecture", report.host.architecture),
+    ] {
+        xml.push_str(&format!("      <property name=\"{}\" value=\"{}\"/>\n", name, escape(value)));
+    }
+    xml.push_str("    </properties>\n");
+
+    for entry in &report.entries {
+        let name = match &entry.user {
+            Some(user) => format!("{} [{}]", entry.name, user),
+            None => entry.name.to_string(),
+        };
+        xml.push_str(&format!(
+            "    <testcase classname=\"{}\" name=\"{}\" time=\"{:.3}\">\n",
+            escape(format!("{} {}", entry.cis_id, entry.control).trim_start()),
+            escape(&name),
+            entry.duration.map(|duration| duration.as_secs_f64()).unwrap_or(0.0)
+        ));
+
+        match entry.outcome {
+            Outcome::Failed => xml.push_str(&format!(
+                "      <failure message=\"{}\" type=\"CommandFailed\">{}</failure>\n",
+                escape(&match entry.exit_code {
+                    Some(code) => format!("{} exited with code {}", entry.name, code),
+                    None => format!("{} did not complete", entry.name),
+                }),
+                escape(&entry.error)
+            )),
+            Outcome::Fail => xml.push_str(&format!(
+                "      <failure message=\"{}\" type=\"NotCompliant\">{}</failure>\n",
+                escape(entry.description),
+                escape(&entry.output)
+            )),
+            Outcome::Skipped | Outcome::NotApplicable => xml.push_str(&format!(
+                "      <skipped message=\"{}\"/>\n",
+                escape(entry.output.trim())
+            )),
+            Outcome::Applied | Outcome::Pass => {}
+        }
+
+        if let Some(command) = &entry.command {
+            xml.push_str(&format!(
+                "      <system-out>$ {}\n{}</system-out>\n",
+                escape(command),
+                escape(&entry.output)
+            ));
+        } else if entry.outcome == Outcome::Pass {
+            xml.push_str(&format!("      <system-out>{}</system-out>\n", escape(&entry.output)));
+        }
+        xml.push_str("    </testcase>\n");
+    }
+
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
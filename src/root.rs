@@ -0,0 +1,66 @@
+// Filesystem root every file path is resolved against (--root), so a mounted disk image or a
+// fixture tree can be audited and hardened offline. Unset, paths refer to the running system.
+
+static ROOT: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+
+// Sets the root, e.g. "/Volumes/Macintosh HD" (only the first call has an effect)
+pub fn install(root: &str) -> Result<(), String> {
+    let canonical = std::fs::canonicalize(root).map_err(|error| format!("Invalid root {}: {}", root, error))?;
+    if !canonical.is_dir() {
+        return Err(format!("Invalid root {}: not a directory", root));
+    }
+    let canonical = canonical.to_string_lossy().trim_end_matches('/').to_string();
+    let _ = ROOT.set(canonical);
+    Ok(())
+}
+
+#[cfg(test)]
+thread_local! {
+    // Root of one test, so tests running in parallel each work on their own fixture tree
+    static TEST_ROOT: std::cell::Cell<Option<&'static str>> = const { std::cell::Cell::new(None) };
+}
+
+// Makes a directory the root of the current thread (tests only)
+#[cfg(test)]
+pub fn install_for_thread(root: &str) {
+    TEST_ROOT.set(Some(Box::leak(root.trim_end_matches('/').to_string().into_boxed_str())));
+}
+
+// True when the current thread has its own root (tests only)
+#[cfg(test)]
+pub fn installed_for_thread() -> bool {
+    TEST_ROOT.get().is_some()
+}
+
+// Prefix added to every path, empty for the running system
+fn prefix() -> &'static str {
+    #[cfg(test)]
+    if let Some(root) = TEST_ROOT.get() {
+        return root;
+    }
+    ROOT.get().map(|root| root.as_str()).unwrap_or("")
+}
+
+// True when working on a tree other than the running system
+pub fn is_offline() -> bool {
+    !prefix().is_empty()
+}
+
+// Absolute path on the running system -> path under the root, e.g. "/etc/exports" -> "/mnt/image/etc/exports"
+pub fn path(path: &str) -> String {
+    format!("{}{}", prefix(), path)
+}
+
+// Path under the root -> the path it has on the target system
+pub fn strip(path: &str) -> &str {
+    match path.strip_prefix(prefix()) {
+        Some(relative) if relative.starts_with('/') => relative,
+        _ => path,
+    }
+}
+
+// True when an argument refers to a file under the root (commands and checks that don't act on
+// the running system instead)
+pub fn touches(arg: &str) -> bool {
+    is_offline() && arg.contains(prefix())
+}
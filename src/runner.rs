@@ -0,0 +1,484 @@
+// Every process the tool starts goes through a CommandRunner, so the whole benchmark can be
+// exercised off a Mac with --simulate and a script of canned outputs.
+//
+// Script example:
+//     # Lines starting with '$' give a command line (as printed by --dry-run), the lines after
+//     # it are its stdout, lines starting with "2> " its stderr. "$1 ..." exits with code 1.
+//     $ /usr/sbin/sysctl -n machdep.cpu.brand_string
+//     Apple M1
+//     $1 /usr/bin/fdesetup status
+//     2> FileVault is Off.
+//     $timeout sudo /usr/sbin/softwareupdate -l
+//     $ sudo /usr/bin/defaults read *
+//
+// A trailing '*' matches any command line starting with what comes before it, lines starting
+// with '#' are comments. Commands without a scripted response succeed with no output, "$timeout"
+// ones never finish and are reported as killed at their timeout.
+
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+// Result of a process that ran to completion, or was killed at its timeout
+pub struct ProcessOutput {
+    pub code: Option<i32>, // None when the process was killed by a signal
+    pub stdout: String,
+    pub stderr: String,
+    pub timed_out: bool,   // Killed because it was still running at the timeout
+}
+
+impl ProcessOutput {
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+}
+
+// New content for a whole file, written atomically: a temporary copy is written next to it,
+// checked, then renamed over the file
+pub struct FileWrite {
+    pub path: String,                       // Already resolved against --root
+    pub content: Vec<u8>,
+    pub ownership: Option<(u32, u32, u32)>, // Mode, uid and gid; None keeps those of the replaced file
+    pub validate: Option<Vec<String>>,      // Command checking the temporary copy (its path is appended), e.g. visudo -c -f
+}
+
+pub trait CommandRunner: Sync {
+    // Runs a process to completion, writing `stdin` to it when given, and stops it when it is
+    // still running after `timeout`
+    fn run(&self, executable: &str, args: &[String], stdin: Option<&str>, timeout: Option<Duration>) -> std::io::Result<ProcessOutput>;
+
+    fn write_file(&self, write: &FileWrite) -> std::io::Result<()>;
+
+    // Waits before a command is retried
+    fn pause(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+// Time a stopped process gets to exit on SIGTERM before it is killed
+const TERMINATION_GRACE: Duration = Duration::from_secs(5);
+
+// Interval at which a process with a timeout is checked
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+// Spawns real processes
+pub struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn run(&self, executable: &str, args: &[String], stdin: Option<&str>, timeout: Option<Duration>) -> std::io::Result<ProcessOutput> {
+        use std::io::Write;
+        use std::process::Stdio;
+
+        let mut child = std::process::Command::new(executable)
+            .args(args)
+            .stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let stdout = drain(child.stdout.take());
+        let stderr = drain(child.stderr.take());
+        // The expected answer is sent to commands that ask for confirmation from its own thread,
+        // so a process that does not read it cannot hold the run past its timeout (the pipe is
+        // closed once written)
+        let answered = child.stdin.take().zip(stdin).map(|(mut pipe, input)| {
+            let input = input.to_string();
+            let (sender, receiver) = std::sync::mpsc::channel();
+            std::thread::spawn(move || {
+                let _ = sender.send(pipe.write_all(input.as_bytes()));
+            });
+            receiver
+        });
+
+        let status = match timeout {
+            Some(timeout) => wait_until(&mut child, timeout)?,
+            None => Some(child.wait()?),
+        };
+        // A process that exits without reading its answer is not an error: its exit status is
+        // the outcome
+        if let Some(Ok(Err(error))) = answered.map(|answered| answered.recv_timeout(Duration::from_secs(1))) {
+            if error.kind() != std::io::ErrorKind::BrokenPipe {
+                return Err(error);
+            }
+        }
+        // Children of a stopped process may keep the pipes open, its output is not waited for long
+        let collect = |output: std::sync::mpsc::Receiver<Vec<u8>>| {
+            let bytes = match status {
+                Some(_) => output.recv(),
+                None => output.recv_timeout(Duration::from_secs(1)).map_err(|_| std::sync::mpsc::RecvError),
+            };
+            String::from_utf8_lossy(&bytes.unwrap_or_default()).to_string()
+        };
+
+        Ok(ProcessOutput {
+            code: status.and_then(|status| status.code()),
+            stdout: collect(stdout),
+            stderr: collect(stderr),
+            timed_out: status.is_none(),
+        })
+    }
+
+    fn write_file(&self, write: &FileWrite) -> std::io::Result<()> {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        // The temporary copy sits next to the file so the rename stays on the same volume
+        let path = write.path.as_str();
+        let temporary = format!("{}.cis-tmp", path);
+        let ownership = write.ownership.or_else(|| {
+            let metadata = std::fs::metadata(path).ok()?;
+            Some((metadata.mode() & 0o7777, metadata.uid(), metadata.gid()))
+        });
+        // Missing parent directories are created, e.g. Firefox's distribution folder
+        if let Some(parent) = std::path::Path::new(path).parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let result = std::fs::write(&temporary, &write.content).and_then(|_| {
+            // chown clears the setuid/setgid bits, so the mode is set afterwards
+            if let Some((mode, uid, gid)) = ownership {
+                std::os::unix::fs::chown(&temporary, Some(uid), Some(gid))?;
+                std::fs::set_permissions(&temporary, std::fs::Permissions::from_mode(mode))?;
+            }
+            if let Some(validate) = &write.validate {
+                check(self, validate, &temporary)?;
+            }
+            std::fs::rename(&temporary, path)
+        });
+        if result.is_err() {
+            let _ = std::fs::remove_file(&temporary);
+        }
+        result
+    }
+}
+
+// Reads a pipe to the end on its own thread, so a process filling both pipes does not block
+fn drain<R: std::io::Read + Send + 'static>(pipe: Option<R>) -> std::sync::mpsc::Receiver<Vec<u8>> {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let mut bytes = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut bytes);
+        }
+        let _ = sender.send(bytes);
+    });
+    receiver
+}
+
+// Waits for a process until the timeout, then asks it to stop with SIGTERM (which sudo passes on
+// to the command it runs) and kills it when it is still there after a grace period. None when the
+// process had to be stopped.
+fn wait_until(child: &mut std::process::Child, timeout: Duration) -> std::io::Result<Option<std::process::ExitStatus>> {
+    let exited = |child: &mut std::process::Child, deadline: std::time::Instant| -> std::io::Result<bool> {
+        while std::time::Instant::now() < deadline {
+            if child.try_wait()?.is_some() {
+                return Ok(true);
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+        Ok(false)
+    };
+
+    if exited(child, std::time::Instant::now() + timeout)? {
+        return child.wait().map(Some);
+    }
+    let _ = std::process::Command::new("/bin/kill")
+        .args(["-TERM", &child.id().to_string()])
+        .stderr(std::process::Stdio::null())
+        .status();
+    if !exited(child, std::time::Instant::now() + TERMINATION_GRACE)? {
+        let _ = child.kill();
+    }
+    child.wait()?;
+    Ok(None)
+}
+
+// A process the scripted runner was asked to start
+pub struct Invocation {
+    pub command_line: String,
+    pub stdin: Option<String>,
+    pub scripted: bool, // A response in the script matched
+}
+
+struct Response {
+    pattern: String,
+    code: i32,
+    stdout: String,
+    stderr: String,
+    hangs: bool, // "$timeout": the process never finishes
+}
+
+// Fake macOS: answers from a script and records every invocation instead of spawning anything
+pub struct ScriptedRunner {
+    responses: Vec<Response>,
+    invocations: Mutex<Vec<Invocation>>,
+}
+
+impl ScriptedRunner {
+    pub fn load(path: &str) -> Result<ScriptedRunner, String> {
+        let content = std::fs::read_to_string(path).map_err(|error| format!("Failed to read {}: {}", path, error))?;
+        ScriptedRunner::parse(&content).map_err(|error| format!("Invalid simulation script {}: {}", path, error))
+    }
+
+    pub fn parse(content: &str) -> Result<ScriptedRunner, String> {
+        let mut responses: Vec<Response> = Vec::new();
+
+        for (index, line) in content.lines().enumerate() {
+            if line.starts_with('#') {
+                continue;
+            }
+            if let Some(header) = line.strip_prefix('$') {
+                let (hangs, header) = match header.strip_prefix("timeout ") {
+                    Some(header) => (true, header),
+                    None => (false, header),
+                };
+                let split = header.find(|c: char| !c.is_ascii_digit()).unwrap_or(header.len());
+                let (code, pattern) = header.split_at(split);
+                let pattern = pattern.trim();
+                if pattern.is_empty() {
+                    return Err(format!("line {}: expected a command line after '$'", index + 1));
+                }
+                responses.push(Response {
+                    pattern: pattern.to_string(),
+                    code: code.parse().unwrap_or(0),
+                    stdout: String::new(),
+                    stderr: String::new(),
+                    hangs,
+                });
+            } else if let Some(response) = responses.last_mut() {
+                match line.strip_prefix("2> ") {
+                    Some(error) => response.stderr.push_str(&format!("{}\n", error)),
+                    None => response.stdout.push_str(&format!("{}\n", line)),
+                }
+            } else if !line.trim().is_empty() {
+                return Err(format!("line {}: output before the first '$' command line", index + 1));
+            }
+        }
+
+        Ok(ScriptedRunner {
+            responses,
+            invocations: Mutex::new(Vec::new()),
+        })
+    }
+
+    // Invocations recorded so far, in order
+    pub fn invocations(&self) -> std::sync::MutexGuard<'_, Vec<Invocation>> {
+        self.invocations.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl CommandRunner for ScriptedRunner {
+    fn run(&self, executable: &str, args: &[String], stdin: Option<&str>, timeout: Option<Duration>) -> std::io::Result<ProcessOutput> {
+        let command_line = crate::commands::shell_quote(executable, args);
+        let response = self.responses.iter().find(|response| match response.pattern.strip_suffix('*') {
+            Some(prefix) => command_line.starts_with(prefix),
+            None => command_line == response.pattern,
+        });
+
+        self.invocations().push(Invocation {
+            command_line,
+            stdin: stdin.map(|input| input.to_string()),
+            scripted: response.is_some(),
+        });
+
+        Ok(match response {
+            // Without a timeout the run would hang like the real process
+            Some(response) if response.hangs && timeout.is_none() => {
+                return Err(std::io::Error::other("scripted to hang and run without a timeout"));
+            }
+            Some(response) => ProcessOutput {
+                code: (!response.hangs).then_some(response.code),
+                stdout: response.stdout.clone(),
+                stderr: response.stderr.clone(),
+                timed_out: response.hangs,
+            },
+            None => ProcessOutput {
+                code: Some(0),
+                stdout: String::new(),
+                stderr: String::new(),
+                timed_out: false,
+            },
+        })
+    }
+
+    // Retries are recorded, not waited for
+    fn pause(&self, _duration: Duration) {}
+
+    fn write_file(&self, write: &FileWrite) -> std::io::Result<()> {
+        // The check runs against the path the temporary copy would have, so scripts can make it fail
+        if let Some(validate) = &write.validate {
+            check(self, validate, &format!("{}.cis-tmp", write.path))?;
+        }
+        self.invocations().push(Invocation {
+            command_line: format!(
+                "{} ({} bytes)",
+                crate::commands::shell_quote("write", std::slice::from_ref(&write.path)),
+                write.content.len()
+            ),
+            stdin: None,
+            scripted: true,
+        });
+        Ok(())
+    }
+}
+
+// Runs the command validating a new file, e.g. `visudo -c -f <temporary copy>`
+fn check(runner: &dyn CommandRunner, validate: &[String], path: &str) -> std::io::Result<()> {
+    let (executable, args) = validate.split_first().expect("validation command is empty");
+    let mut args = args.to_vec();
+    args.push(path.to_string());
+
+    let output = runner.run(executable, &args, None, default_timeout())?;
+    if output.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(format!(
+            "rejected by {}: {}",
+            executable,
+            format!("{}{}", output.stdout, output.stderr).trim()
+        )))
+    }
+}
+
+static CURRENT: OnceLock<&'static dyn CommandRunner> = OnceLock::new();
+
+// Replaces the runner used by the whole program (only the first call has an effect)
+pub fn install(runner: &'static dyn CommandRunner) {
+    let _ = CURRENT.set(runner);
+}
+
+#[cfg(test)]
+thread_local! {
+    // Runner of one test, so tests running in parallel each answer from their own script
+    static TEST_RUNNER: std::cell::Cell<Option<&'static dyn CommandRunner>> = const { std::cell::Cell::new(None) };
+}
+
+// Makes a runner the one used by the current thread (tests only)
+#[cfg(test)]
+pub fn install_for_thread(runner: &'static dyn CommandRunner) {
+    TEST_RUNNER.set(Some(runner));
+}
+
+// True when the current thread has its own runner (tests only)
+#[cfg(test)]
+pub fn installed_for_thread() -> bool {
+    TEST_RUNNER.get().is_some()
+}
+
+// Runner in effect: the installed one, or the real one
+pub fn current() -> &'static dyn CommandRunner {
+    #[cfg(test)]
+    if let Some(runner) = TEST_RUNNER.get() {
+        return runner;
+    }
+    *CURRENT.get_or_init(|| &SystemRunner)
+}
+
+// Runs a read-only command through the current runner
+pub fn run(executable: &str, args: &[&str]) -> std::io::Result<ProcessOutput> {
+    let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+    current().run(executable, &args, None, default_timeout())
+}
+
+// Timeout of the commands that do not set their own (`command_timeout` of the configuration)
+pub fn default_timeout() -> Option<Duration> {
+    match crate::config::current().command_timeout {
+        0 => None,
+        seconds => Some(Duration::from_secs(seconds as u64)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(runner: &dyn CommandRunner, command_line: &str, timeout: Option<Duration>) -> std::io::Result<ProcessOutput> {
+        let mut words = command_line.split_whitespace();
+        let executable = words.next().expect("executable");
+        let args: Vec<String> = words.map(|word| word.to_string()).collect();
+        runner.run(executable, &args, None, timeout)
+    }
+
+    #[test]
+    fn script_answers_and_records() {
+        let script = ScriptedRunner::parse(
+            "# comment\n$ /usr/bin/id -u\n0\n$1 /usr/bin/fdesetup status\n2> FileVault is Off.\n$ sudo /usr/bin/defaults read *\n1\n",
+        )
+        .expect("valid script");
+
+        let output = run(&script, "/usr/bin/id -u", None).expect("scripted");
+        assert!(output.success());
+        assert_eq!(output.stdout, "0\n");
+
+        let output = run(&script, "/usr/bin/fdesetup status", None).expect("scripted");
+        assert_eq!(output.code, Some(1));
+        assert_eq!(output.stderr, "FileVault is Off.\n");
+
+        let output = run(&script, "sudo /usr/bin/defaults read /Library/Preferences/x Key", None).expect("scripted");
+        assert_eq!(output.stdout, "1\n");
+
+        let output = run(&script, "/usr/bin/true", None).expect("unscripted");
+        assert!(output.success() && output.stdout.is_empty());
+
+        let invocations = script.invocations();
+        let scripted: Vec<bool> = invocations.iter().map(|invocation| invocation.scripted).collect();
+        assert_eq!(scripted, vec![true, true, true, false]);
+        assert_eq!(invocations[2].command_line, "sudo /usr/bin/defaults read /Library/Preferences/x Key");
+    }
+
+    #[test]
+    fn scripted_hang_needs_a_timeout() {
+        let script = ScriptedRunner::parse("$timeout sudo /usr/sbin/softwareupdate -l\n").expect("valid script");
+        let output = run(&script, "sudo /usr/sbin/softwareupdate -l", Some(Duration::from_secs(1))).expect("scripted");
+        assert!(output.timed_out);
+        assert_eq!(output.code, None);
+        assert!(run(&script, "sudo /usr/sbin/softwareupdate -l", None).is_err());
+    }
+
+    #[test]
+    fn invalid_scripts() {
+        assert!(ScriptedRunner::parse("output first\n$ /usr/bin/id -u\n").is_err());
+        assert!(ScriptedRunner::parse("$ \n").is_err());
+    }
+
+    #[test]
+    fn process_gets_its_stdin() {
+        let output = SystemRunner.run("cat", &[], Some("yes\n"), None).expect("cat runs");
+        assert!(output.success());
+        assert_eq!(output.stdout, "yes\n");
+    }
+
+    #[test]
+    fn process_ignoring_its_stdin_is_waited_for() {
+        // The answer is larger than a pipe buffer, so writing it fails once the process has exited
+        let input = "y\n".repeat(1 << 20);
+        let output = SystemRunner.run("sh", &["-c".to_string(), "exit 3".to_string()], Some(&input), None).expect("sh runs");
+        assert_eq!(output.code, Some(3));
+        assert!(!output.timed_out);
+    }
+
+    #[test]
+    fn process_not_reading_its_stdin_is_stopped_at_its_timeout() {
+        // The answer fills the pipe buffer of a process that never reads it
+        let input = "y\n".repeat(1 << 20);
+        let started = std::time::Instant::now();
+        let output = SystemRunner
+            .run("sleep", &["30".to_string()], Some(&input), Some(Duration::from_millis(200)))
+            .expect("sleep runs");
+        assert!(output.timed_out);
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn process_is_stopped_at_its_timeout() {
+        let started = std::time::Instant::now();
+        let output = SystemRunner
+            .run("sleep", &["30".to_string()], None, Some(Duration::from_millis(200)))
+            .expect("sleep runs");
+        assert!(output.timed_out);
+        assert_eq!(output.code, None);
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn missing_executable_is_an_error() {
+        assert!(run(&SystemRunner, "/nonexistent/tool", None).is_err());
+    }
+}
@@ -0,0 +1,347 @@
+// Order and concurrency of an apply run. A control starts once the controls it depends on have
+// finished: the ones it names in `requires`, and the earlier selected controls sharing a resource
+// with it (a preference domain, a file or a tool, see BashCommand::resource). Independent
+// controls run on up to `jobs` worker threads.
+//
+// Inside a control, consecutive per-user commands run concurrently, one task per user (each
+// user's commands keep their order), and any other command waits for everything before it.
+//
+// With one worker the commands run in exactly the order of the selected controls.
+
+use crate::catalog::Control;
+use crate::commands::{BashCommand, ExecutionOutcome};
+use crate::summary::ControlRun;
+use std::sync::{Condvar, Mutex};
+
+// Selected controls reordered so that each one comes after the controls it requires (otherwise
+// the order of the selection is kept)
+pub fn order(controls: &[Control]) -> Vec<Control> {
+    let mut ordered: Vec<Control> = Vec::new();
+    let mut visiting: Vec<&str> = Vec::new();
+
+    fn visit<'a>(control: &'a Control, controls: &'a [Control], ordered: &mut Vec<Control>, visiting: &mut Vec<&'a str>) {
+        if ordered.iter().any(|done| done.name == control.name) || visiting.contains(&control.name) {
+            return; // Already placed, or a cycle: the selection order decides
+        }
+        visiting.push(control.name);
+        for required in controls.iter().filter(|other| requires(control, other)) {
+            visit(required, controls, ordered, visiting);
+        }
+        visiting.pop();
+        ordered.push(*control);
+    }
+
+    for control in controls {
+        visit(control, controls, &mut ordered, &mut visiting);
+    }
+    ordered
+}
+
+// True when `control` must wait for `other`. A glob such as "*" does not match controls that
+// require a glob themselves, so two catch-all controls cannot wait for each other.
+fn requires(control: &Control, other: &Control) -> bool {
+    control.name != other.name
+        && control.requires.iter().any(|pattern| {
+            if pattern.contains('*') {
+                !other.requires.iter().any(|other| other.contains('*')) && crate::catalog::glob_match(pattern, other.name)
+            } else {
+                other.name == *pattern || other.name.starts_with(&format!("{}_", pattern))
+            }
+        })
+}
+
+// Work item: commands of one control run in order by a single worker
+struct Task {
+    control: usize,
+    commands: Vec<usize>, // Indexes in the control's command list
+}
+
+// Commands of a control split into steps run one after the other; the tasks of a step run concurrently
+fn steps(commands: &[BashCommand]) -> Vec<Vec<Vec<usize>>> {
+    let mut steps: Vec<Vec<Vec<usize>>> = Vec::new();
+    let mut users: Vec<(String, Vec<usize>)> = Vec::new();
+
+    for (index, command) in commands.iter().enumerate() {
+        match command.target_user() {
+            Some(user) => match users.iter_mut().find(|(name, _)| *name == user) {
+                Some((_, indexes)) => indexes.push(index),
+                None => users.push((user, vec![index])),
+            },
+            None => {
+                if !users.is_empty() {
+                    steps.push(users.drain(..).map(|(_, indexes)| indexes).collect());
+                }
+                steps.push(vec![vec![index]]);
+            }
+        }
+    }
+    if !users.is_empty() {
+        steps.push(users.drain(..).map(|(_, indexes)| indexes).collect());
+    }
+    steps
+}
+
+struct State {
+    ready: Vec<Task>,
+    waiting_for: Vec<Vec<usize>>,                   // Unfinished controls each control depends on
+    started: Vec<bool>,
+    steps: Vec<std::collections::VecDeque<Vec<Vec<usize>>>>,
+    running: Vec<usize>,                            // Unfinished tasks of the current step, per control
+    finished: usize,
+    outcomes: Vec<Vec<Option<ExecutionOutcome>>>,
+}
+
+impl State {
+    // Queues the next step of a control, or marks it finished and starts the controls waiting for it
+    fn advance(&mut self, control: usize) {
+        let mut pending = vec![control];
+        while let Some(control) = pending.pop() {
+            match self.steps[control].pop_front() {
+                Some(step) => {
+                    self.running[control] = step.len();
+                    self.ready.extend(step.into_iter().map(|commands| Task { control, commands }));
+                }
+                None => {
+                    self.finished += 1;
+                    for (other, waiting) in self.waiting_for.iter_mut().enumerate() {
+                        waiting.retain(|done| *done != control);
+                        if waiting.is_empty() && !self.started[other] {
+                            self.started[other] = true;
+                            pending.push(other);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Task of the earliest control, so that a single worker keeps the selection order
+    fn next(&mut self) -> Option<Task> {
+        let index = (0..self.ready.len()).min_by_key(|index| (self.ready[*index].control, self.ready[*index].commands[0]))?;
+        Some(self.ready.swap_remove(index))
+    }
+}
+
+// Text given to panic!, when there is one
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> &str {
+    panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(|message| message.as_str()))
+        .unwrap_or("panic")
+}
+
+// Runs the remediation of the controls (already ordered) on `jobs` workers; `execute` runs one
+// command and is called from the worker threads
+pub fn run<F>(controls: &[Control], jobs: usize, execute: F) -> Vec<ControlRun>
+where
+    F: Fn(&Control, &BashCommand) -> ExecutionOutcome + Sync,
+{
+    let commands: Vec<Vec<BashCommand>> = controls.iter().map(|control| control.remediation()).collect();
+    let resources: Vec<Vec<String>> = commands
+        .iter()
+        .map(|commands| commands.iter().filter_map(|command| command.resource()).collect())
+        .collect();
+
+    // Only earlier controls are waited for (see `order`), so the dependencies cannot form a cycle
+    let waiting_for: Vec<Vec<usize>> = (0..controls.len())
+        .map(|control| {
+            (0..control)
+                .filter(|other| {
+                    requires(&controls[control], &controls[*other])
+                        || resources[control].iter().any(|resource| resources[*other].contains(resource))
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut state = State {
+        ready: Vec::new(),
+        started: waiting_for.iter().map(|waiting| waiting.is_empty()).collect(),
+        waiting_for,
+        steps: commands.iter().map(|commands| steps(commands).into()).collect(),
+        running: vec![0; controls.len()],
+        finished: 0,
+        outcomes: commands.iter().map(|commands| commands.iter().map(|_| None).collect()).collect(),
+    };
+    let independent: Vec<usize> = (0..controls.len()).filter(|control| state.started[*control]).collect();
+    for control in independent {
+        state.advance(control);
+    }
+
+    let state = Mutex::new(state);
+    let changed = Condvar::new();
+    let worker = || loop {
+        let task = {
+            let mut state = state.lock().expect("scheduler state");
+            loop {
+                if let Some(task) = state.next() {
+                    break task;
+                }
+                if state.finished == controls.len() {
+                    return;
+                }
+                state = changed.wait(state).expect("scheduler state");
+            }
+        };
+
+        // A command that panics fails on its own: its control would otherwise never finish and
+        // every worker would wait for it
+        let outcomes: Vec<(usize, ExecutionOutcome)> = task
+            .commands
+            .iter()
+            .map(|index| {
+                let command = &commands[task.control][*index];
+                let outcome = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| execute(&controls[task.control], command)))
+                    .unwrap_or_else(|panic| ExecutionOutcome::failed(command, format!("Stopped by an internal error: {}", panic_message(&*panic))));
+                (*index, outcome)
+            })
+            .collect();
+
+        let mut state = state.lock().expect("scheduler state");
+        for (index, outcome) in outcomes {
+            state.outcomes[task.control][index] = Some(outcome);
+        }
+        state.running[task.control] -= 1;
+        if state.running[task.control] == 0 {
+            state.advance(task.control);
+        }
+        changed.notify_all();
+    };
+
+    std::thread::scope(|scope| {
+        for _ in 1..jobs.max(1) {
+            scope.spawn(worker);
+        }
+        worker();
+    });
+
+    let state = state.into_inner().expect("scheduler state");
+    controls
+        .iter()
+        .zip(state.outcomes)
+        .map(|(control, outcomes)| ControlRun {
+            control: *control,
+            outcomes: outcomes.into_iter().flatten().collect(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::Assessment;
+    use crate::commands::{self, ExecutionStatus};
+    use crate::testing::simulate;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    fn control(name: &'static str, requires: &'static [&'static str], commands: fn() -> Vec<BashCommand>) -> Control {
+        Control {
+            name,
+            cis_id: "",
+            title: name,
+            level: 1,
+            assessment: Assessment::Automated,
+            rationale: "",
+            tags: &[],
+            default_enabled: true,
+            requires,
+            releases: crate::os::EVERY_RELEASE,
+            commands,
+            post_actions: Vec::new,
+            checks: Vec::new,
+        }
+    }
+
+    // Runs the controls with a fake `execute` taking `pause` per command; returns the controls in
+    // the order they started and finished ("+name" and "-name") and the most commands seen
+    // running at once
+    fn trace(controls: &[Control], jobs: usize, pause: Duration) -> (Vec<String>, usize) {
+        let events = Mutex::new(Vec::new());
+        let (running, most) = (AtomicUsize::new(0), AtomicUsize::new(0));
+        let runs = run(controls, jobs, |control, command| {
+            events.lock().unwrap().push(format!("+{}", control.name));
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            most.fetch_max(now, Ordering::SeqCst);
+            std::thread::sleep(pause);
+            running.fetch_sub(1, Ordering::SeqCst);
+            events.lock().unwrap().push(format!("-{}", control.name));
+            ExecutionOutcome::not_applicable(command.name(), "", "not run".to_string())
+        });
+        assert_eq!(runs.len(), controls.len());
+        (events.into_inner().unwrap(), most.into_inner())
+    }
+
+    fn position(events: &[String], event: &str) -> usize {
+        events.iter().position(|found| found == event).unwrap_or_else(|| panic!("{} in {:?}", event, events))
+    }
+
+    #[test]
+    fn controls_wait_for_the_ones_they_require() {
+        simulate("");
+        let controls = [
+            control("update", &["clock"], || vec![commands::c1_enable_os_autoupdate()]),
+            control("clock", &[], || vec![commands::c10_sync_time()]),
+        ];
+        let ordered = order(&controls);
+        assert_eq!(ordered.iter().map(|control| control.name).collect::<Vec<_>>(), vec!["clock", "update"]);
+
+        let (events, _) = trace(&ordered, 4, Duration::from_millis(50));
+        assert!(position(&events, "-clock") < position(&events, "+update"), "{:?}", events);
+    }
+
+    #[test]
+    fn controls_sharing_a_resource_do_not_overlap() {
+        simulate(""); // Monterey: c13 and c17 both call systemsetup
+        let controls = [
+            control("remote_events", &[], || vec![commands::c13_disable_remote_apple_events()]),
+            control("remote_login", &[], || vec![commands::c17_disable_remote_login()]),
+        ];
+        let (events, most) = trace(&controls, 4, Duration::from_millis(50));
+        assert_eq!(events, vec!["+remote_events", "-remote_events", "+remote_login", "-remote_login"]);
+        assert_eq!(most, 1);
+    }
+
+    #[test]
+    fn independent_controls_run_on_every_worker() {
+        simulate("");
+        let controls = [
+            control("update", &[], || vec![commands::c1_enable_os_autoupdate()]),
+            control("clock", &[], || vec![commands::c10_sync_time()]),
+            control("login", &[], || vec![commands::c17_disable_remote_login()]),
+            control("auditing", &[], || vec![commands::c37_enable_security_auditing()]),
+        ];
+        let (_, most) = trace(&controls, 2, Duration::from_millis(100));
+        assert_eq!(most, 2);
+        let (_, most) = trace(&controls, 8, Duration::from_millis(100));
+        assert_eq!(most, 4);
+
+        // One worker runs them one at a time, in the selection order
+        let (events, most) = trace(&controls, 1, Duration::ZERO);
+        assert_eq!(most, 1);
+        let started: Vec<&str> = events.iter().filter_map(|event| event.strip_prefix('+')).collect();
+        assert_eq!(started, vec!["update", "clock", "login", "auditing"]);
+    }
+
+    #[test]
+    fn a_panicking_command_fails_without_stopping_the_run() {
+        simulate("");
+        let controls = [
+            control("update", &[], || vec![commands::c1_enable_os_autoupdate()]),
+            control("clock", &["update"], || vec![commands::c10_sync_time()]),
+            control("login", &[], || vec![commands::c17_disable_remote_login()]),
+        ];
+        for jobs in [1, 3] {
+            let runs = run(&controls, jobs, |control, command| {
+                if control.name == "update" {
+                    panic!("unexpected state");
+                }
+                ExecutionOutcome::not_applicable(command.name(), "", "not run".to_string())
+            });
+            let statuses: Vec<ExecutionStatus> = runs.iter().map(|run| run.outcomes[0].status).collect();
+            assert_eq!(statuses, vec![ExecutionStatus::Failed, ExecutionStatus::NotApplicable, ExecutionStatus::NotApplicable]);
+            assert!(runs[0].outcomes[0].stderr.contains("unexpected state"), "{}", runs[0].outcomes[0].stderr);
+        }
+    }
+}
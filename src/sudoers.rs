@@ -0,0 +1,329 @@
+// sudo configuration: the drop-in written by c59 and an audit of /etc/sudoers and the files it
+// includes. A broken sudoers file locks administrators out, so drop-ins are checked with
+// `visudo -c` before they replace anything and are installed 0440 root:wheel.
+
+use crate::runner::FileWrite;
+
+pub const SUDOERS: &str = "/etc/sudoers";
+pub const CIS_DROP_IN: &str = "/etc/sudoers.d/10_cissudoconfiguration";
+
+// Settings of the benchmark drop-in (5.3)
+pub const CIS_SETTINGS: &str = "Defaults timestamp_timeout=0\nDefaults timestamp_type=tty\n";
+
+const VISUDO: &str = "/usr/sbin/visudo";
+const MODE: u32 = 0o440;
+const ROOT_UID: u32 = 0;
+const WHEEL_GID: u32 = 0;
+
+// Drop-in replacing `path` (already resolved against --root), validated when visudo is installed
+pub fn drop_in(path: String, content: &str) -> FileWrite {
+    let validate = std::path::Path::new(VISUDO)
+        .exists()
+        .then(|| vec![VISUDO.to_string(), "-c".to_string(), "-f".to_string()]);
+    FileWrite {
+        path,
+        content: content.as_bytes().to_vec(),
+        ownership: Some((MODE, ROOT_UID, WHEEL_GID)),
+        validate,
+    }
+}
+
+// Why an installed drop-in does not have the expected content, owner or mode
+pub fn drop_in_problem(path: &str, content: &str) -> Option<String> {
+    match std::fs::read_to_string(path) {
+        Ok(current) if current == content => ownership_problem(path),
+        Ok(_) => Some(format!("{} does not have the expected content", crate::root::strip(path))),
+        Err(error) => Some(format!("{}: {}", crate::root::strip(path), error)),
+    }
+}
+
+// Why a sudoers file is not 0440 root:wheel, e.g. writable by its owner or owned by a user
+pub fn ownership_problem(path: &str) -> Option<String> {
+    use std::os::unix::fs::MetadataExt;
+
+    let metadata = match std::fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(error) => return Some(format!("{}: {}", crate::root::strip(path), error)),
+    };
+    let mode = metadata.mode() & 0o7777;
+    if mode == MODE && metadata.uid() == ROOT_UID && metadata.gid() == WHEEL_GID {
+        return None;
+    }
+    Some(format!(
+        "{} is mode {:o} owned by {}:{} (expected {:o} root:wheel)",
+        crate::root::strip(path),
+        mode,
+        metadata.uid(),
+        metadata.gid(),
+        MODE
+    ))
+}
+
+// A value and where it was set, e.g. "0" at "/etc/sudoers.d/10_cissudoconfiguration:1"
+pub struct Setting {
+    pub value: String,
+    pub at: String,
+}
+
+// What the sudo configuration says about authentication
+#[derive(Default)]
+pub struct SudoersAudit {
+    pub timestamp_timeout: Option<Setting>, // Last `Defaults` setting, the one sudo uses
+    pub timestamp_type: Option<Setting>,
+    pub nopasswd: Vec<Setting>,             // Rules that run commands without a password
+    pub no_authenticate: Vec<Setting>,      // `Defaults !authenticate` lines
+    pub errors: Vec<String>,                // Files that could not be read
+}
+
+// Reads /etc/sudoers and the files it includes, in the order sudo reads them
+pub fn audit() -> SudoersAudit {
+    let mut audit = SudoersAudit::default();
+    audit.include(SUDOERS, 0);
+    audit
+}
+
+// sudo stops at the same depth, which also ends include loops
+const MAX_INCLUDE_DEPTH: usize = 128;
+
+impl SudoersAudit {
+    fn include(&mut self, file: &str, depth: usize) {
+        if depth > MAX_INCLUDE_DEPTH {
+            self.errors.push(format!("{}: includes are nested too deeply", file));
+            return;
+        }
+        match std::fs::read_to_string(crate::root::path(file)) {
+            Ok(content) => self.read(file, &content, depth),
+            Err(error) => self.errors.push(format!("Failed to read {}: {}", file, error)),
+        }
+    }
+
+    fn include_dir(&mut self, directory: &str, depth: usize) {
+        match std::fs::read_dir(crate::root::path(directory)) {
+            Ok(entries) => {
+                // Like sudo, names containing '.' or ending with '~' are skipped
+                let mut names: Vec<String> = entries
+                    .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+                    .filter(|name| !name.contains('.') && !name.ends_with('~'))
+                    .collect();
+                names.sort();
+                for name in names {
+                    self.include(&format!("{}/{}", directory.trim_end_matches('/'), name), depth);
+                }
+            }
+            // sudo ignores a missing directory
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {}
+            Err(error) => self.errors.push(format!("Failed to read {}: {}", directory, error)),
+        }
+    }
+
+    fn read(&mut self, file: &str, content: &str, depth: usize) {
+        for (number, line) in logical_lines(content) {
+            let at = format!("{}:{}", file, number);
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let Some(first) = words.first() else {
+                continue;
+            };
+
+            match *first {
+                "#include" | "@include" => self.include(&included_path(file, &line[first.len()..]), depth + 1),
+                "#includedir" | "@includedir" => {
+                    self.include_dir(&included_path(file, &line[first.len()..]), depth + 1)
+                }
+                // Only unscoped Defaults apply to everyone; Defaults:user, Defaults@host,
+                // Defaults>runas and Defaults!command do not set the global timestamp settings
+                "Defaults" => {
+                    for parameter in line[first.len()..].split(',').map(|parameter| parameter.trim()) {
+                        let (name, value) = match parameter.split_once('=') {
+                            Some((name, value)) => (name.trim(), value.trim().trim_matches('"')),
+                            None => (parameter, ""),
+                        };
+                        let setting = Setting {
+                            value: value.to_string(),
+                            at: at.clone(),
+                        };
+                        match name {
+                            "timestamp_timeout" => self.timestamp_timeout = Some(setting),
+                            "timestamp_type" => self.timestamp_type = Some(setting),
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+
+            // A scoped !authenticate still lets its users, hosts or commands skip the password
+            let defaults = first.starts_with("Defaults");
+            if defaults && line.split(|c: char| c == ',' || c.is_whitespace()).any(|word| word == "!authenticate") {
+                self.no_authenticate.push(Setting {
+                    value: line.clone(),
+                    at: at.clone(),
+                });
+            } else if !defaults && line.contains("NOPASSWD:") {
+                self.nopasswd.push(Setting { value: line.clone(), at });
+            }
+        }
+    }
+}
+
+// Target of an include directive: quotes removed, relative to the including file, and /private/etc
+// read as /etc, which it is on macOS (the default sudoers includes /private/etc/sudoers.d)
+fn included_path(file: &str, target: &str) -> String {
+    let target = target.trim().trim_matches('"');
+    let path = if target.starts_with('/') {
+        target.to_string()
+    } else {
+        let directory = std::path::Path::new(file).parent().and_then(|parent| parent.to_str()).unwrap_or("/");
+        format!("{}/{}", directory.trim_end_matches('/'), target)
+    };
+    match path.strip_prefix("/private/etc/") {
+        Some(relative) => format!("/etc/{}", relative),
+        None => path,
+    }
+}
+
+// Lines without comments, joined when they end with a backslash, with their first line number
+fn logical_lines(content: &str) -> Vec<(usize, String)> {
+    let mut lines = Vec::new();
+    let mut current: Option<(usize, String)> = None;
+
+    for (index, raw) in content.lines().enumerate() {
+        // "#include" and "#includedir" are directives, other '#' start a comment
+        let first = raw.split_whitespace().next().unwrap_or("");
+        let text = if first == "#include" || first == "#includedir" {
+            raw
+        } else {
+            raw.split('#').next().unwrap_or("")
+        };
+        let (text, continued) = match text.trim_end().strip_suffix('\\') {
+            Some(text) => (text, true),
+            None => (text, false),
+        };
+
+        let (number, mut line) = current.take().unwrap_or((index + 1, String::new()));
+        line.push_str(text);
+        line.push(' ');
+        if continued {
+            current = Some((number, line));
+        } else if !line.trim().is_empty() {
+            lines.push((number, normalize(&line)));
+        }
+    }
+    if let Some((number, line)) = current.filter(|(_, line)| !line.trim().is_empty()) {
+        lines.push((number, normalize(&line)));
+    }
+    lines
+}
+
+fn normalize(line: &str) -> String {
+    line.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixture;
+
+    fn at(setting: &Option<Setting>) -> Option<(&str, &str)> {
+        setting.as_ref().map(|setting| (setting.value.as_str(), setting.at.as_str()))
+    }
+
+    #[test]
+    fn logical_lines_join_continuations_and_drop_comments() {
+        let content = "# sudoers file\n\
+                       \n\
+                       Defaults   env_reset # reset the environment\n\
+                       Cmnd_Alias SHUTDOWN = /sbin/halt, \\\n    /sbin/reboot, \\\n    /sbin/shutdown\n\
+                       #include /etc/sudoers.local\n\
+                       #includedir /private/etc/sudoers.d\n\
+                       #include_not_a_directive\n\
+                       @includedir /etc/sudoers.d\n\
+                       alice ALL = (ALL) ALL \\";
+        assert_eq!(
+            logical_lines(content),
+            vec![
+                (3, "Defaults env_reset".to_string()),
+                (4, "Cmnd_Alias SHUTDOWN = /sbin/halt, /sbin/reboot, /sbin/shutdown".to_string()),
+                (7, "#include /etc/sudoers.local".to_string()),
+                (8, "#includedir /private/etc/sudoers.d".to_string()),
+                (10, "@includedir /etc/sudoers.d".to_string()),
+                (11, "alice ALL = (ALL) ALL".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn included_paths_are_relative_to_the_including_file() {
+        assert_eq!(included_path("/etc/sudoers", " /private/etc/sudoers.d"), "/etc/sudoers.d");
+        assert_eq!(included_path("/etc/sudoers", "\"sudoers.local\""), "/etc/sudoers.local");
+        assert_eq!(included_path("/etc/sudoers.d/admins", "extra"), "/etc/sudoers.d/extra");
+        assert_eq!(included_path("/etc/sudoers", "/opt/sudoers"), "/opt/sudoers");
+    }
+
+    #[test]
+    fn audit_follows_includes_in_order() {
+        fixture(
+            "sudoers-includes",
+            &[
+                (
+                    "/etc/sudoers",
+                    "Defaults timestamp_timeout=15\n\
+                     root ALL = (ALL) ALL\n\
+                     #includedir /private/etc/sudoers.d\n\
+                     @include sudoers.local\n",
+                ),
+                ("/etc/sudoers.d/10_cissudoconfiguration", CIS_SETTINGS),
+                ("/etc/sudoers.d/20_admins", "%admin ALL = (ALL) NOPASSWD: ALL\n"),
+                ("/etc/sudoers.d/backup~", "Defaults timestamp_timeout=30\n"),
+                ("/etc/sudoers.d/old.conf", "Defaults timestamp_timeout=30\n"),
+                ("/etc/sudoers.local", "Defaults timestamp_type=global\n"),
+                ("/etc/unincluded", "Defaults !authenticate\n"),
+            ],
+        );
+        let audit = audit();
+
+        assert!(audit.errors.is_empty(), "{:?}", audit.errors);
+        assert_eq!(at(&audit.timestamp_timeout), Some(("0", "/etc/sudoers.d/10_cissudoconfiguration:1")));
+        assert_eq!(at(&audit.timestamp_type), Some(("global", "/etc/sudoers.local:1")));
+        assert_eq!(audit.nopasswd.len(), 1);
+        assert_eq!(audit.nopasswd[0].at, "/etc/sudoers.d/20_admins:1");
+        assert!(audit.no_authenticate.is_empty());
+    }
+
+    #[test]
+    fn audit_ignores_scoped_defaults_but_reports_their_authentication() {
+        fixture(
+            "sudoers-scoped",
+            &[(
+                "/etc/sudoers",
+                "Defaults timestamp_timeout=0\n\
+                 Defaults:alice timestamp_timeout=30\n\
+                 Defaults@kiosk timestamp_type=global\n\
+                 Defaults>root timestamp_timeout=10\n\
+                 Defaults!/usr/bin/true timestamp_timeout=10\n\
+                 Defaults:bob env_reset, !authenticate\n",
+            )],
+        );
+        let audit = audit();
+
+        assert_eq!(at(&audit.timestamp_timeout), Some(("0", "/etc/sudoers:1")));
+        assert!(audit.timestamp_type.is_none());
+        assert!(audit.nopasswd.is_empty());
+        assert_eq!(audit.no_authenticate.len(), 1);
+        assert_eq!(audit.no_authenticate[0].at, "/etc/sudoers:6");
+    }
+
+    #[test]
+    fn audit_reports_missing_files_and_include_loops() {
+        fixture(
+            "sudoers-errors",
+            &[
+                ("/etc/sudoers", "#include /etc/missing\n#includedir /etc/absent.d\n@include loop\n"),
+                ("/etc/loop", "@include /etc/loop\n"),
+            ],
+        );
+        let audit = audit();
+
+        assert!(audit.errors[0].starts_with("Failed to read /etc/missing: "), "{:?}", audit.errors);
+        assert_eq!(audit.errors[1..], ["/etc/loop: includes are nested too deeply".to_string()]);
+    }
+}
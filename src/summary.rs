@@ -0,0 +1,169 @@
+use crate::catalog::Control;
+use crate::checks::{CheckResult, CheckStatus};
+use crate::commands::{ExecutionOutcome, ExecutionStatus};
+
+// Outcomes of every command produced by one control
+pub struct ControlRun {
+    pub control: Control,
+    pub outcomes: Vec<ExecutionOutcome>,
+}
+
+impl ControlRun {
+    // A control failed if any of its commands failed, and was applied if at least one command ran
+    pub fn status(&self) -> ExecutionStatus {
+        if !self.outcomes.is_empty() && self.outcomes.iter().all(|outcome| outcome.status == ExecutionStatus::NotApplicable) {
+            ExecutionStatus::NotApplicable
+        } else if self.outcomes.iter().any(|outcome| outcome.status == ExecutionStatus::Failed) {
+            ExecutionStatus::Failed
+        } else if self.outcomes.iter().any(|outcome| outcome.status == ExecutionStatus::Applied) {
+            ExecutionStatus::Applied
+        } else {
+            ExecutionStatus::Skipped
+        }
+    }
+}
+
+// Counts of controls by outcome at the end of a run
+pub struct RunSummary {
+    pub applied: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub not_applicable: usize,
+    pub duration: std::time::Duration, // Total time spent in commands
+}
+
+impl RunSummary {
+    pub fn new(runs: &[ControlRun]) -> RunSummary {
+        let count = |status| runs.iter().filter(|run| run.status() == status).count();
+
+        RunSummary {
+            applied: count(ExecutionStatus::Applied),
+            failed: count(ExecutionStatus::Failed),
+            skipped: count(ExecutionStatus::Skipped),
+            not_applicable: count(ExecutionStatus::NotApplicable),
+            duration: runs
+                .iter()
+                .flat_map(|run| run.outcomes.iter())
+                .map(|outcome| outcome.duration)
+                .sum(),
+        }
+    }
+
+    pub fn print(&self, runs: &[ControlRun]) {
+        println!("============================================");
+        println!(
+            "Run finished in {:.2?}: {} applied, {} failed, {} skipped, {} not applicable.",
+            self.duration, self.applied, self.failed, self.skipped, self.not_applicable
+        );
+        println!("============================================");
+
+        let outcomes: Vec<(&str, &str)> = runs
+            .iter()
+            .flat_map(|run| run.outcomes.iter())
+            .filter_map(|outcome| {
+                let status = match outcome.status {
+                    ExecutionStatus::Applied => "applied",
+                    ExecutionStatus::Failed => "failed",
+                    ExecutionStatus::Skipped => "skipped",
+                    ExecutionStatus::NotApplicable => "not applicable",
+                };
+                Some((outcome.user.as_deref()?, status))
+            })
+            .collect();
+        print_users(&outcomes, &["applied", "failed", "skipped"]);
+
+        for run in runs.iter().filter(|run| run.status() == ExecutionStatus::Failed) {
+            println!("❌ {} - {}", run.control.name, run.control.title);
+            for outcome in run.outcomes.iter().filter(|outcome| outcome.status == ExecutionStatus::Failed) {
+                let error = outcome.stderr.lines().find(|line| !line.trim().is_empty()).unwrap_or("");
+                println!("    {} - {}", outcome.name, outcome.description);
+                println!("      $ {}", outcome.command_line());
+                println!("      exit code {:?}: {}", outcome.exit_code, error.trim());
+            }
+        }
+    }
+}
+
+// Results of every check produced by one control
+pub struct ControlAudit {
+    pub control: Control,
+    pub results: Vec<CheckResult>,
+}
+
+// Counts of checks by status at the end of an audit
+pub struct AuditSummary {
+    pub passed: usize,
+    pub failed: usize,
+    pub not_applicable: usize,
+    pub score: usize, // Percentage of applicable checks that passed
+}
+
+impl AuditSummary {
+    pub fn new(audits: &[ControlAudit]) -> AuditSummary {
+        let results = || audits.iter().flat_map(|audit| audit.results.iter());
+        let passed = results().filter(|result| result.status == CheckStatus::Pass).count();
+        let failed = results().filter(|result| result.status == CheckStatus::Fail).count();
+
+        AuditSummary {
+            passed,
+            failed,
+            not_applicable: results().count() - passed - failed,
+            score: (passed * 100).checked_div(passed + failed).unwrap_or(100),
+        }
+    }
+
+    pub fn print(&self, audits: &[ControlAudit]) {
+        println!("============================================");
+        println!(
+            "Audit finished: {} passed, {} failed, {} not applicable.",
+            self.passed, self.failed, self.not_applicable
+        );
+        println!("Compliance score: {}%", self.score);
+        println!("============================================");
+
+        let outcomes: Vec<(&str, &str)> = audits
+            .iter()
+            .flat_map(|audit| audit.results.iter())
+            .filter_map(|result| {
+                let status = match result.status {
+                    CheckStatus::Pass => "passed",
+                    CheckStatus::Fail => "failed",
+                    CheckStatus::NotApplicable => "not applicable",
+                };
+                Some((result.user.as_deref()?, status))
+            })
+            .collect();
+        print_users(&outcomes, &["passed", "failed", "not applicable"]);
+
+        let failures = audits
+            .iter()
+            .flat_map(|audit| audit.results.iter())
+            .filter(|result| result.status == CheckStatus::Fail);
+        for result in failures {
+            match &result.user {
+                Some(user) => println!("❌ {} [{}] - {}: {}", result.name, user, result.description, result.detail),
+                None => println!("❌ {} - {}: {}", result.name, result.description, result.detail),
+            }
+        }
+    }
+}
+
+// One line per user of the per-user commands or checks, e.g. "👤 bob: 12 passed, 1 failed, 0 not applicable"
+fn print_users(outcomes: &[(&str, &str)], statuses: &[&str]) {
+    let mut users: Vec<&str> = Vec::new();
+    for (user, _) in outcomes {
+        if !users.contains(user) {
+            users.push(user);
+        }
+    }
+    for user in users {
+        let counts: Vec<String> = statuses
+            .iter()
+            .map(|status| {
+                let count = outcomes.iter().filter(|outcome| **outcome == (user, *status)).count();
+                format!("{} {}", count, status)
+            })
+            .collect();
+        println!("👤 {}: {}", user, counts.join(", "));
+    }
+}
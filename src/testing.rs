@@ -0,0 +1,50 @@
+// Helpers of the unit tests: a scripted fake macOS and fixture trees, installed for the test's
+// thread only so tests running in parallel do not see each other's
+
+use crate::runner::ScriptedRunner;
+
+// Installs a script as the runner of the test's thread
+pub fn simulate(script: &str) -> &'static ScriptedRunner {
+    // The accounts are read once per process, before any script can answer for them
+    crate::users::all();
+    let runner: &'static ScriptedRunner = Box::leak(Box::new(ScriptedRunner::parse(script).expect("valid script")));
+    crate::runner::install_for_thread(runner);
+    runner
+}
+
+// Command lines the script was asked to run, in order
+pub fn recorded(runner: &ScriptedRunner) -> Vec<String> {
+    runner.invocations().iter().map(|invocation| invocation.command_line.clone()).collect()
+}
+
+// Fixture tree with the given files, installed as the root of the test's thread
+pub fn fixture(name: &str, files: &[(&str, &str)]) -> String {
+    crate::users::all();
+    let root = std::env::temp_dir().join(format!("cis-hardening-test-{}", name));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).expect("fixture root");
+    for (path, content) in files {
+        let path = root.join(path.trim_start_matches('/'));
+        std::fs::create_dir_all(path.parent().expect("file in a folder")).expect("fixture folder");
+        std::fs::write(path, content).expect("fixture file");
+    }
+    let root = root.to_string_lossy().to_string();
+    crate::root::install_for_thread(&root);
+    root
+}
+
+// Script lines answering the version checks with a macOS release, e.g. "14.5"
+pub fn sw_vers(version: &str) -> String {
+    format!("$ /usr/bin/sw_vers -productVersion\n{}\n$ /usr/bin/sw_vers -buildVersion\n23F79\n", version)
+}
+
+// SystemVersion.plist of a fixture root running a macOS release, e.g. "14.5"
+pub fn system_version(version: &str) -> (&'static str, String) {
+    let plist = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<plist version=\"1.0\">\n<dict>\n\
+         \t<key>ProductBuildVersion</key>\n\t<string>23F79</string>\n\
+         \t<key>ProductVersion</key>\n\t<string>{}</string>\n</dict>\n</plist>\n",
+        version
+    );
+    (crate::os::SYSTEM_VERSION, plist)
+}
@@ -0,0 +1,504 @@
+// Accounts of the system the per-user controls act on. The directory records are the source of
+// truth (UID, IsHidden, shell, home), read with `dscl` on the running system and from the local
+// directory node under --root, so a fixture tree can provide its own users as plists in
+// var/db/dslocal/nodes/Default/users. Without directory records the home folders in /Users are used.
+// Which of the human accounts each control acts on is narrowed by the scope (--user,
+// --exclude-user, --console-user and the `users` settings of --config).
+
+use crate::plist::Value;
+
+const DSLOCAL: &str = "/var/db/dslocal/nodes/Default";
+const FIRST_HUMAN_UID: i64 = 501;
+const NO_LOGIN_SHELLS: [&str; 5] = ["/usr/bin/false", "/bin/false", "/sbin/nologin", "/usr/sbin/nologin", "/usr/bin/nologin"];
+const NO_HOMES: [&str; 3] = ["", "/var/empty", "/dev/null"];
+
+// Folders of /Users that are not home folders
+const NOT_HOMES: [&str; 3] = ["Shared", "Guest", "Deleted Users"];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Kind {
+    Human,   // Account someone logs in with
+    Admin,   // Human account in the admin group
+    Service, // Daemon, hidden or disabled account (Guest, _www, root...)
+}
+
+#[derive(Clone, Debug)]
+pub struct User {
+    pub name: String,
+    pub home: String, // Path on the target system, e.g. "/Users/bob" or "/Volumes/Data/bob"
+    pub kind: Kind,
+}
+
+impl User {
+    fn new(name: String, uid: i64, home: String, shell: String, hidden: bool, admins: &[String]) -> User {
+        let service = uid < FIRST_HUMAN_UID
+            || name.starts_with('_')
+            || hidden
+            || NO_LOGIN_SHELLS.contains(&shell.as_str())
+            || NO_HOMES.contains(&home.as_str());
+        let kind = if service {
+            Kind::Service
+        } else if admins.contains(&name) {
+            Kind::Admin
+        } else {
+            Kind::Human
+        };
+        User { name, home, kind }
+    }
+
+    pub fn is_human(&self) -> bool {
+        self.kind != Kind::Service
+    }
+}
+
+impl Kind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Kind::Human => "human",
+            Kind::Admin => "admin",
+            Kind::Service => "service",
+        }
+    }
+}
+
+// Account names or globs (e.g. "lab*") to act on and to leave out
+#[derive(Clone, Default)]
+pub struct Filter {
+    pub include: Vec<String>, // Empty for every account
+    pub exclude: Vec<String>,
+}
+
+impl Filter {
+    // Why the filter leaves an account out, None when it keeps it
+    fn rejects(&self, name: &str) -> Option<String> {
+        let matching = |patterns: &[String]| {
+            patterns
+                .iter()
+                .find(|pattern| crate::catalog::glob_match(pattern, name))
+                .cloned()
+        };
+        if let Some(pattern) = matching(&self.exclude) {
+            return Some(format!("excluded by '{}'", pattern));
+        }
+        if !self.include.is_empty() && matching(&self.include).is_none() {
+            return Some(format!("not in {}", self.include.join(",")));
+        }
+        None
+    }
+}
+
+// Accounts the per-user controls act on
+#[derive(Default)]
+pub struct Scope {
+    pub filter: Filter,                  // Every per-user control
+    pub console_user: Option<String>,    // Only this account (--console-user)
+    pub controls: Vec<(&'static str, Filter)>, // Further narrowing for one control, by control name
+}
+
+static SCOPE: std::sync::OnceLock<Scope> = std::sync::OnceLock::new();
+
+// Makes a scope the one used by every control (only the first call has an effect)
+pub fn install_scope(scope: Scope) {
+    let _ = SCOPE.set(scope);
+}
+
+fn scope() -> &'static Scope {
+    SCOPE.get_or_init(Scope::default)
+}
+
+// Why an account is left out of every per-user control, None when the run acts on it
+pub fn exclusion(name: &str) -> Option<String> {
+    let scope = scope();
+    match &scope.console_user {
+        Some(console_user) if console_user != name => Some("not the console user".to_string()),
+        _ => scope.filter.rejects(name),
+    }
+}
+
+// True when a control acts on an account
+pub fn in_scope(control: &str, name: &str) -> bool {
+    exclusion(name).is_none()
+        && scope()
+            .controls
+            .iter()
+            .filter(|(scoped, _)| *scoped == control)
+            .all(|(_, filter)| filter.rejects(name).is_none())
+}
+
+// Account logged in at the console of the running system, None at the login window
+pub fn console_user() -> Option<String> {
+    let output = crate::runner::run("/usr/bin/stat", &["-f", "%Su", "/dev/console"])
+        .ok()
+        .filter(|output| output.success())?;
+    Some(output.stdout.trim().to_string()).filter(|name| !name.is_empty() && name != "root")
+}
+
+// Patterns naming a single account that does not exist or is not a human account
+pub fn unknown(patterns: &[String]) -> Vec<String> {
+    patterns
+        .iter()
+        .filter(|pattern| !pattern.contains('*'))
+        .filter(|pattern| !human().iter().any(|user| &user.name == *pattern))
+        .cloned()
+        .collect()
+}
+
+static USERS: std::sync::OnceLock<Vec<User>> = std::sync::OnceLock::new();
+
+// Every account, read once per run
+pub fn all() -> &'static [User] {
+    USERS.get_or_init(|| {
+        let users = if crate::root::is_offline() { from_dslocal() } else { from_dscl() };
+        users.unwrap_or_else(|| {
+            eprintln!("⚠️  No directory records found, using the home folders in /Users");
+            from_home_folders()
+        })
+    })
+}
+
+// Human and admin accounts whose home folder exists: the ones per-user controls configure
+pub fn human() -> Vec<&'static User> {
+    all()
+        .iter()
+        .filter(|user| user.is_human() && std::path::Path::new(&crate::root::path(&user.home)).is_dir())
+        .collect()
+}
+
+// Home folder of an account on the target system, "/Users/<name>" for an unknown one
+pub fn home(name: &str) -> String {
+    all()
+        .iter()
+        .find(|user| user.name == name)
+        .map(|user| user.home.clone())
+        .unwrap_or_else(|| format!("/Users/{}", name))
+}
+
+// Human account whose home holds a path of the target system, e.g. "/Users/bob/Library/Preferences/x.plist" -> bob
+pub fn owner_of(path: &str) -> Option<&'static User> {
+    all().iter().filter(|user| user.is_human() && user.home != "/").find(|user| {
+        path.strip_prefix(user.home.as_str())
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    })
+}
+
+// Attributes of one record of `dscl -readall`, e.g. [("RecordName", ["_www", "www"]), ...]
+type Record = Vec<(String, Vec<String>)>;
+
+fn attribute<'a>(record: &'a Record, name: &str) -> Option<&'a String> {
+    record.iter().find(|(attribute, _)| attribute == name).and_then(|(_, values)| values.first())
+}
+
+// Records are separated by "-" lines; values are space separated, or one per indented line when
+// one of them contains a space. Native attributes keep their name without the prefix.
+fn parse_records(output: &str) -> Vec<Record> {
+    let mut records = Vec::new();
+    let mut record: Record = Vec::new();
+    for line in output.lines() {
+        if line == "-" {
+            records.push(std::mem::take(&mut record));
+        } else if let Some(value) = line.strip_prefix(' ') {
+            if let Some((_, values)) = record.last_mut() {
+                values.push(value.trim().to_string());
+            }
+        } else {
+            let (name, values) = match line.strip_suffix(':') {
+                Some(name) => (name, ""),
+                None => match line.split_once(": ") {
+                    Some(attribute) => attribute,
+                    None => continue,
+                },
+            };
+            let name = name.strip_prefix("dsAttrTypeNative:").unwrap_or(name);
+            record.push((name.to_string(), values.split_whitespace().map(|value| value.to_string()).collect()));
+        }
+    }
+    if !record.is_empty() {
+        records.push(record);
+    }
+    records
+}
+
+fn is_true(value: Option<&String>) -> bool {
+    matches!(value.map(|value| value.as_str()), Some("1") | Some("YES") | Some("true"))
+}
+
+// Users of the running system's local directory node
+fn from_dscl() -> Option<Vec<User>> {
+    let output = crate::runner::run(
+        "/usr/bin/dscl",
+        &[".", "-readall", "/Users", "RecordName", "UniqueID", "NFSHomeDirectory", "UserShell", "IsHidden"],
+    )
+    .ok()
+    .filter(|output| output.success())?;
+
+    let admins: Vec<String> = crate::runner::run("/usr/bin/dscl", &[".", "-read", "/Groups/admin", "GroupMembership"])
+        .ok()
+        .filter(|output| output.success())
+        .and_then(|output| parse_records(&output.stdout).into_iter().next())
+        .and_then(|record| record.into_iter().find(|(name, _)| name == "GroupMembership"))
+        .map(|(_, members)| members)
+        .unwrap_or_default();
+
+    let users: Vec<User> = parse_records(&output.stdout)
+        .iter()
+        .filter_map(|record| {
+            Some(User::new(
+                attribute(record, "RecordName")?.clone(),
+                attribute(record, "UniqueID")?.parse().ok()?,
+                attribute(record, "NFSHomeDirectory").cloned().unwrap_or_default(),
+                attribute(record, "UserShell").cloned().unwrap_or_default(),
+                is_true(attribute(record, "IsHidden")),
+                &admins,
+            ))
+        })
+        .collect();
+    (!users.is_empty()).then_some(users)
+}
+
+// First value of an attribute of a dslocal record (every attribute is an array of strings)
+fn dslocal_attribute(record: &Value, name: &str) -> Option<String> {
+    match record.get(name)? {
+        Value::Array(values) => match values.first()? {
+            Value::String(value) => Some(value.clone()),
+            _ => None,
+        },
+        Value::String(value) => Some(value.clone()),
+        _ => None,
+    }
+}
+
+// Users of the local directory node under --root: one plist per record
+fn from_dslocal() -> Option<Vec<User>> {
+    let directory = crate::root::path(&format!("{}/users", DSLOCAL));
+    let mut paths: Vec<_> = std::fs::read_dir(directory)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "plist"))
+        .collect();
+    paths.sort();
+
+    let admins: Vec<String> = crate::plist::load(&crate::root::path(&format!("{}/groups/admin.plist", DSLOCAL)))
+        .ok()
+        .and_then(|(group, _)| match group.get("users") {
+            Some(Value::Array(members)) => Some(
+                members
+                    .iter()
+                    .filter_map(|member| match member {
+                        Value::String(name) => Some(name.clone()),
+                        _ => None,
+                    })
+                    .collect(),
+            ),
+            _ => None,
+        })
+        .unwrap_or_default();
+
+    let users: Vec<User> = paths
+        .iter()
+        .filter_map(|path| {
+            let (record, _) = crate::plist::load(&path.to_string_lossy()).ok()?;
+            Some(User::new(
+                dslocal_attribute(&record, "name")?,
+                dslocal_attribute(&record, "uid")?.parse().ok()?,
+                dslocal_attribute(&record, "home").unwrap_or_default(),
+                dslocal_attribute(&record, "shell").unwrap_or_default(),
+                is_true(dslocal_attribute(&record, "IsHidden").as_ref()),
+                &admins,
+            ))
+        })
+        .collect();
+    (!users.is_empty()).then_some(users)
+}
+
+// Home folders in /Users, taken as human accounts
+fn from_home_folders() -> Vec<User> {
+    let mut users: Vec<User> = std::fs::read_dir(crate::root::path("/Users"))
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| {
+                    let name = entry.file_name().into_string().ok()?;
+                    if name.starts_with('.') || NOT_HOMES.contains(&name.as_str()) || !entry.path().is_dir() {
+                        return None;
+                    }
+                    Some(User {
+                        home: format!("/Users/{}", name),
+                        name,
+                        kind: Kind::Human,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    users.sort_by(|a, b| a.name.cmp(&b.name));
+    users
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{fixture, simulate};
+
+    const DSCL_USERS: &str = "/usr/bin/dscl . -readall /Users RecordName UniqueID NFSHomeDirectory UserShell IsHidden";
+    const DSCL_ADMINS: &str = "/usr/bin/dscl . -read /Groups/admin GroupMembership";
+
+    fn summary(users: &[User]) -> Vec<(String, String, Kind)> {
+        users.iter().map(|user| (user.name.clone(), user.home.clone(), user.kind)).collect()
+    }
+
+    fn entry(name: &str, home: &str, kind: Kind) -> (String, String, Kind) {
+        (name.to_string(), home.to_string(), kind)
+    }
+
+    #[test]
+    fn parses_dscl_records() {
+        let output = "NFSHomeDirectory: /var/empty\n\
+                      RecordName: _www www\n\
+                      UniqueID: 70\n\
+                      UserShell: /usr/bin/false\n\
+                      -\n\
+                      dsAttrTypeNative:IsHidden: 1\n\
+                      NFSHomeDirectory:\n /Volumes/Data/Lab Users/carol\n\
+                      RecordName: carol\n\
+                      -\n";
+        let records = parse_records(output);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0][1], ("RecordName".to_string(), vec!["_www".to_string(), "www".to_string()]));
+        assert_eq!(attribute(&records[0], "RecordName").map(String::as_str), Some("_www"));
+        assert_eq!(attribute(&records[1], "IsHidden").map(String::as_str), Some("1"));
+        assert_eq!(attribute(&records[1], "NFSHomeDirectory").map(String::as_str), Some("/Volumes/Data/Lab Users/carol"));
+        assert_eq!(attribute(&records[1], "UniqueID"), None);
+
+        // The last record may not be followed by a separator
+        assert_eq!(parse_records("RecordName: bob\nUniqueID: 501").len(), 1);
+        assert!(parse_records("").is_empty());
+    }
+
+    #[test]
+    fn classifies_dscl_accounts() {
+        simulate(&format!(
+            "$ {}\n\
+             RecordName: root\nUniqueID: 0\nNFSHomeDirectory: /var/root\nUserShell: /bin/sh\n-\n\
+             RecordName: _www www\nUniqueID: 70\nNFSHomeDirectory: /Library/WebServer\nUserShell: /bin/zsh\n-\n\
+             RecordName: alice\nUniqueID: 501\nNFSHomeDirectory: /Users/alice\nUserShell: /bin/zsh\n-\n\
+             RecordName: bob\nUniqueID: 502\nNFSHomeDirectory: /Users/bob\nUserShell: /bin/bash\n-\n\
+             RecordName: hidden\nUniqueID: 503\nNFSHomeDirectory: /Users/hidden\nUserShell: /bin/zsh\nIsHidden: YES\n-\n\
+             RecordName: kiosk\nUniqueID: 504\nNFSHomeDirectory: /Users/kiosk\nUserShell: /usr/bin/false\n-\n\
+             RecordName: nohome\nUniqueID: 505\nNFSHomeDirectory: /var/empty\nUserShell: /bin/zsh\n-\n\
+             RecordName: broken\nUniqueID: none\n-\n\
+             $ {}\nGroupMembership: root alice\n",
+            DSCL_USERS, DSCL_ADMINS
+        ));
+        let users = from_dscl().expect("records");
+        assert_eq!(
+            summary(&users),
+            vec![
+                entry("root", "/var/root", Kind::Service),
+                entry("_www", "/Library/WebServer", Kind::Service),
+                entry("alice", "/Users/alice", Kind::Admin),
+                entry("bob", "/Users/bob", Kind::Human),
+                entry("hidden", "/Users/hidden", Kind::Service),
+                entry("kiosk", "/Users/kiosk", Kind::Service),
+                entry("nohome", "/var/empty", Kind::Service),
+            ]
+        );
+        assert!(users[2].is_human() && users[3].is_human() && !users[0].is_human());
+
+        // dscl failing or listing nobody leaves the choice to the caller
+        simulate(&format!("$1 {}\n", DSCL_USERS));
+        assert!(from_dscl().is_none());
+        simulate("");
+        assert!(from_dscl().is_none());
+    }
+
+    fn record(name: &str, uid: &str, home: &str, shell: &str, hidden: bool) -> String {
+        let hidden = if hidden { "\t<key>IsHidden</key>\n\t<array><string>1</string></array>\n" } else { "" };
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<plist version=\"1.0\">\n<dict>\n\
+             \t<key>name</key>\n\t<array><string>{}</string></array>\n\
+             \t<key>uid</key>\n\t<array><string>{}</string></array>\n\
+             \t<key>home</key>\n\t<array><string>{}</string></array>\n\
+             \t<key>shell</key>\n\t<array><string>{}</string></array>\n{}\
+             </dict>\n</plist>\n",
+            name, uid, home, shell, hidden
+        )
+    }
+
+    #[test]
+    fn reads_dslocal_records_under_the_root() {
+        let admins = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<plist version=\"1.0\">\n<dict>\n\
+                      \t<key>users</key>\n\t<array><string>root</string><string>carol</string></array>\n</dict>\n</plist>\n";
+        let users = [
+            ("bob", record("bob", "501", "/Users/bob", "/bin/zsh", false)),
+            ("carol", record("carol", "502", "/Volumes/Data/carol", "/bin/zsh", false)),
+            ("daemon", record("daemon", "1", "/var/root", "/usr/bin/false", false)),
+            ("lab1", record("lab1", "503", "/Users/lab1", "/bin/zsh", true)),
+        ];
+        let mut files: Vec<(String, String)> = users
+            .iter()
+            .map(|(name, content)| (format!("{}/users/{}.plist", DSLOCAL, name), content.clone()))
+            .collect();
+        files.push((format!("{}/users/notes.txt", DSLOCAL), "not a record".to_string()));
+        files.push((format!("{}/groups/admin.plist", DSLOCAL), admins.to_string()));
+        let files: Vec<(&str, &str)> = files.iter().map(|(path, content)| (path.as_str(), content.as_str())).collect();
+        fixture("users-dslocal", &files);
+
+        assert_eq!(
+            summary(&from_dslocal().expect("records")),
+            vec![
+                entry("bob", "/Users/bob", Kind::Human),
+                entry("carol", "/Volumes/Data/carol", Kind::Admin),
+                entry("daemon", "/var/root", Kind::Service),
+                entry("lab1", "/Users/lab1", Kind::Service),
+            ]
+        );
+
+        fixture("users-dslocal-missing", &[]);
+        assert!(from_dslocal().is_none());
+    }
+
+    #[test]
+    fn falls_back_to_the_home_folders() {
+        let root = fixture(
+            "users-homes",
+            &[
+                ("/Users/zoe/.zshrc", ""),
+                ("/Users/adam/Desktop/notes.txt", ""),
+                ("/Users/Shared/readme.txt", ""),
+                ("/Users/Guest/.keep", ""),
+                ("/Users/Deleted Users/old.dmg", ""),
+                ("/Users/.localized", ""),
+                ("/Users/stray-file", ""),
+            ],
+        );
+        std::fs::create_dir_all(format!("{}/Users/.hidden-folder", root)).expect("fixture folder");
+        assert_eq!(
+            summary(&from_home_folders()),
+            vec![entry("adam", "/Users/adam", Kind::Human), entry("zoe", "/Users/zoe", Kind::Human)]
+        );
+
+        fixture("users-homes-missing", &[]);
+        assert!(from_home_folders().is_empty());
+    }
+
+    #[test]
+    fn filters_accounts_with_globs() {
+        let filter = Filter {
+            include: vec!["lab*".to_string(), "alice".to_string()],
+            exclude: vec!["lab9".to_string()],
+        };
+        assert_eq!(filter.rejects("alice"), None);
+        assert_eq!(filter.rejects("lab1"), None);
+        assert_eq!(filter.rejects("lab9").as_deref(), Some("excluded by 'lab9'"));
+        assert_eq!(filter.rejects("bob").as_deref(), Some("not in lab*,alice"));
+        assert_eq!(filter.rejects("alicia").as_deref(), Some("not in lab*,alice"));
+
+        let everyone_but_kiosks = Filter {
+            include: Vec::new(),
+            exclude: vec!["kiosk*".to_string()],
+        };
+        assert_eq!(everyone_but_kiosks.rejects("bob"), None);
+        assert_eq!(everyone_but_kiosks.rejects("kiosk-2").as_deref(), Some("excluded by 'kiosk*'"));
+    }
+}